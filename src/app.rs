@@ -0,0 +1,1954 @@
+//! State of the interface around the engine: the input mode, prompts and popups, the
+//! focus timer and the stopwatch, and what a key does in each of them
+
+use crossterm::event::{KeyCode, KeyEvent, MouseButton, MouseEvent, MouseEventKind};
+use std::{
+    cmp,
+    collections::HashSet,
+    io,
+    path::PathBuf,
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+    time::{Duration, Instant},
+};
+use tui::layout::Rect;
+
+use todo_core::{date, file_manager, links, text, Status, TodoData};
+use todo_core::config::PANE_WIDTHS;
+use todo_core::engine::{Engine, MoveCursorOperation};
+use todo_core::file_manager::FileManager;
+use todo_core::keymap::{Action, Key, Resolution};
+use todo_core::lists::ListRole;
+use todo_core::sync::{self, Synced};
+
+use crate::{browser, clipboard};
+use crate::ui::help_lines;
+
+#[derive(Copy, Clone)]
+pub enum InputMode {
+    Normal,
+    /// Typing into the selected entry, while `Engine::editing` is set
+    Editing,
+    /// Typing an answer to `App::prompt`
+    Prompting,
+    /// Letters jump to the next entry starting with them
+    Jumping,
+    /// Typing `Engine::search` while `Engine::searching` is set, the list only shows
+    /// matching entries meanwhile
+    Searching,
+    /// Typing `App::notes_input` in the detail pane
+    Notes,
+}
+
+/// What a prompt in the input box is asking for
+pub enum PromptKind {
+    /// Path to write the active list to
+    ExportPath,
+    /// The export target already exists, asks y/n before replacing it
+    ConfirmOverwrite(PathBuf),
+    /// Which list a someday todo gets promoted to, d(aily) or l(ong term)
+    PromoteTarget,
+    /// Due date of the selected todo, empty clears it
+    DueDate,
+    /// Repeat rule of the selected todo, empty stops it repeating
+    Recurrence,
+    /// How long the selected todo should take, empty clears it
+    Estimate,
+    /// Name of a list to add
+    NewListName,
+    /// New name of the active list
+    RenameList,
+    /// Asks y/n before moving the active list into the closed stash
+    ConfirmDeleteList,
+    /// Asks y/n before deleting the closed stash for good
+    ConfirmPurgeClosed,
+    /// Day to open, `tomorrow` or `+3` plan ahead
+    PlanDay,
+    /// Day the selected todo is put off until
+    Snooze,
+    /// Part of the message of the todo the selected one waits for, empty clears it
+    BlockedBy,
+    /// A command like `archive`, `open 2022-09-27` or the name of an action
+    Command
+}
+
+pub struct Prompt {
+    pub kind : PromptKind,
+    pub text : String
+}
+
+/// How the daily list differs from yesterday's file
+pub struct DayDiff {
+    pub added : Vec<String>,
+    pub completed : Vec<String>,
+    pub removed : Vec<String>
+}
+
+impl DayDiff {
+    pub fn between(yesterday : &[TodoData], today : &[TodoData]) -> DayDiff {
+        let in_list = |list : &[TodoData], message : &str| list.iter().any(|m| m.message == message);
+        let today : Vec<&TodoData> = today.iter().filter(|m| !m.message.is_empty()).collect();
+
+        DayDiff {
+            added : today.iter()
+                .filter(|m| !in_list(yesterday, &m.message))
+                .map(|m| m.message.clone())
+                .collect(),
+            completed : today.iter()
+                .filter(|m| matches!(m.status, Status::Done))
+                .filter(|m| !yesterday.iter().any(|y| y.message == m.message && matches!(y.status, Status::Done)))
+                .map(|m| m.message.clone())
+                .collect(),
+            removed : yesterday.iter()
+                .filter(|y| !today.iter().any(|m| m.message == y.message))
+                .map(|y| y.message.clone())
+                .collect()
+        }
+    }
+}
+
+/// Overlay drawn on top of the lists, closed by any key
+pub enum Popup {
+    /// Changes since yesterday, `None` when there is no file for yesterday
+    DayDiff(Option<DayDiff>),
+    /// Todo's finished per day, oldest first and ending with today
+    Stats(Vec<(date::Date, usize)>),
+    /// Asks y/n before removing the todo with this message
+    ConfirmRemove(String),
+    Calendar(Calendar),
+    History(History),
+    Profiles(Profiles),
+    QuickAdd(QuickAdd),
+    /// Every key binding, scrolled down by this many lines
+    Help(usize),
+    /// A key ran into a bug, the message of the panic it caused
+    Error(String)
+}
+
+/// Todo being typed into the quick add popup and the lists it can go to
+pub struct QuickAdd {
+    pub text : String,
+    /// Index into `Engine::lists` and name of every list that takes new todo's
+    pub targets : Vec<(usize, String)>,
+    /// Index into `targets`
+    pub selected : usize
+}
+
+/// Picker of the profiles in the config
+pub struct Profiles {
+    pub names : Vec<String>,
+    /// Index of the profile the lists are from
+    pub open : usize,
+    pub selected : usize
+}
+
+/// Picker of the days with a file, opened read-only when they're past
+pub struct History {
+    /// Newest first
+    pub days : Vec<date::Date>,
+    pub selected : usize
+}
+
+/// Month grid popup, showing the month of the selected day
+pub struct Calendar {
+    pub selected : date::Date,
+    /// Days with a day file, in the data directory or the archive
+    pub with_file : HashSet<date::Date>,
+    /// Days open todo's are due on
+    pub with_due : HashSet<date::Date>
+}
+
+/// Outcome of FileManager work done on a background thread
+pub enum JobResult {
+    /// Yesterday's entries for the day diff popup
+    DayDiff(file_manager::Result<Option<Vec<TodoData>>>),
+    /// Completion history for the stats popup
+    Stats(file_manager::Result<Vec<(date::Date, usize)>>),
+    /// Days with a file for the calendar
+    Calendar(file_manager::Result<Vec<date::Date>>),
+    /// Days with a file for the history picker
+    History(file_manager::Result<Vec<date::Date>>),
+    Sync(io::Result<Synced>)
+}
+
+/// Days of history shown in the stats popup
+const STATS_DAYS : u32 = 14;
+
+/// Rows the history picker moves on PgUp and PgDn
+const HISTORY_PAGE : usize = 10;
+
+/// FileManager work running off the main thread so the event loop stays responsive
+pub struct BackgroundJob {
+    /// Shown next to the spinner, e.g. "Loading yesterday"
+    pub label : String,
+    pub started : Instant,
+    pub receiver : Receiver<JobResult>
+}
+
+/// Jobs finishing quicker than this never show a spinner
+const SPINNER_DELAY : Duration = Duration::from_millis(150);
+
+const SPINNER_FRAMES : [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+const ASCII_SPINNER_FRAMES : [char; 4] = ['|', '/', '-', '\\'];
+
+/// Where an entry of the active list ended up on screen during the last draw
+pub struct RenderedRow {
+    pub index : usize,
+    pub y : u16,
+    pub height : u16,
+    /// Screen columns `start..end` covered by the status checkbox
+    pub checkbox : (u16, u16)
+}
+
+/// Lines PageUp and PageDown scroll the help overlay by
+const HELP_PAGE : usize = 10;
+
+/// Longest pause between the two clicks of a double-click
+const DOUBLE_CLICK_TIME : Duration = Duration::from_millis(400);
+
+/// Outcome of the last CalDAV sync, shown at the right end of the header
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum SyncState {
+    /// No sync was started since the app started
+    Idle,
+    Running,
+    Synced(date::DateTime),
+    Failed
+}
+
+/// Refresh rate while a background job's spinner is shown, if `tick_rate_ms` is slower
+const SPINNER_TICK_RATE : Duration = Duration::from_millis(100);
+
+/// Messages of `entries` one per line, indented two spaces per level below the first
+fn register_text(entries : &[TodoData]) -> String {
+    let base = entries.first().map_or(0, |entry| entry.indent);
+    entries
+        .iter()
+        .map(|entry| format!("{}{}", "  ".repeat(entry.indent.saturating_sub(base)), entry.message))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// App holds the state of the interface, the lists it shows are kept by `engine`
+pub struct App {
+    pub engine : Engine,
+    /// Mode keys go to unless the engine is editing or searching, see `input_mode`
+    pub mode : InputMode,
+    /// Number of entries that fit in the list at once, updated while drawing
+    pub page_size : usize,
+    /// Running focus timer: when it started and how long it lasts
+    pub timer : Option<(Instant, Duration)>,
+    /// Id of the todo the focus timer was started for
+    pub timer_todo : u64,
+    /// Id of the list the focus timer's todo lives in
+    pub timer_list : usize,
+    /// When the stopwatch toggled with `T` was started
+    pub tracking : Option<Instant>,
+    /// Id of the todo the stopwatch runs for
+    pub tracking_todo : u64,
+    /// Id of the list the stopwatch's todo lives in
+    pub tracking_list : usize,
+    /// Tag highlighted in the tag panel, an index into `Engine::tag_index`
+    pub tag_cursor : usize,
+    /// Overlay currently shown over the lists
+    pub popup : Option<Popup>,
+    /// Question being answered in the input box while in `InputMode::Prompting`
+    pub prompt : Option<Prompt>,
+    /// FileManager work that hasn't finished yet
+    pub job : Option<BackgroundJob>,
+    /// Last contents written to the configured status file
+    pub published_status : Option<String>,
+    /// Set once the outside-work-hours warning was dismissed with Esc
+    pub work_hours_dismissed : bool,
+    /// Screen position of the visible entries, used to map mouse clicks
+    pub rendered_rows : Vec<RenderedRow>,
+    /// Normal mode keys typed so far of a binding like `dd`
+    pub pending_keys : Vec<Key>,
+    /// The notes of the selected entry are shown next to the list
+    pub show_details : bool,
+    /// The daily and the long term list are shown side by side, the active one has the focus
+    pub split_view : bool,
+    /// Where the list without the focus was drawn in the split view, a click on it moves there
+    pub side_pane : Option<Rect>,
+    /// Notes being typed in the detail pane, written back when leaving `InputMode::Notes`
+    pub notes_input : String,
+    /// Grapheme of `notes_input` the cursor is in front of
+    pub notes_column : usize,
+    /// Time and entry of the last left click, to tell double-clicks apart
+    pub last_click : Option<(Instant, usize)>,
+    /// Due todo's a notification was already shown for
+    pub notified : HashSet<(String, date::Date)>,
+    /// Thread showing desktop notifications, when they are turned on
+    pub notifier : Option<mpsc::Sender<String>>,
+    pub sync_state : SyncState,
+    /// Profile picked in the switcher, `run_app` returns and `run` starts over on it
+    pub switch_to : Option<String>,
+    /// Action named in the command line, `run_app` runs it as if its key was pressed
+    pub queued_action : Option<Action>
+}
+
+impl Default for App {
+    fn default() -> App {
+        App {
+            engine : Engine::default(),
+            mode : InputMode::Normal,
+            page_size : 1,
+            timer : None,
+            timer_todo : 0,
+            timer_list : 0,
+            tracking : None,
+            tracking_todo : 0,
+            tracking_list : 0,
+            tag_cursor : 0,
+            popup : None,
+            prompt : None,
+            job : None,
+            published_status : None,
+            work_hours_dismissed : false,
+            rendered_rows : vec![],
+            pending_keys : vec![],
+            sync_state : SyncState::Idle,
+            switch_to : None,
+            queued_action : None,
+            show_details : false,
+            split_view : false,
+            side_pane : None,
+            notes_input : String::new(),
+            notes_column : 0,
+            last_click : None,
+            notified : HashSet::new(),
+            notifier : None
+        }
+    }
+}
+
+impl App {
+    /// Where keys go: into the entry or the search the engine is typing, otherwise `mode`
+    pub fn input_mode(&self) -> InputMode {
+        if self.engine.editing {
+            InputMode::Editing
+        } else if self.engine.searching {
+            InputMode::Searching
+        } else {
+            self.mode
+        }
+    }
+
+    /// Loads the notes of the selected entry into the detail pane's editor, opening the pane
+    pub fn start_editing_notes(&mut self) {
+        if self.engine.get_current_message().is_empty() {
+            self.engine.status_message = Some("Write the todo before adding notes to it".to_string());
+            return;
+        }
+        if self.engine.is_current_entry_locked() {
+            return;
+        }
+
+        self.show_details = true;
+        self.mode = InputMode::Notes;
+        self.notes_input = self.engine.get_messages()[self.engine.target_row as usize].notes.clone();
+        self.notes_column = text::grapheme_count(&self.notes_input);
+    }
+
+    /// Writes the detail pane's notes into the selected entry, as one undo step
+    pub fn commit_notes(&mut self) {
+        self.mode = InputMode::Normal;
+        let notes = self.notes_input.trim_end().to_string();
+        self.engine.set_notes(notes);
+    }
+
+    pub fn notes_add_char(&mut self, new_char : char) {
+        let input_index = text::byte_offset(&self.notes_input, self.notes_column);
+        self.notes_input.insert(input_index, new_char);
+        self.notes_column = text::grapheme_count(&self.notes_input[..input_index + new_char.len_utf8()]);
+    }
+
+    pub fn notes_remove_char(&mut self) {
+        if self.notes_column == 0 {
+            return;
+        }
+        if let Some((start, end)) = text::grapheme_range(&self.notes_input, self.notes_column - 1) {
+            self.notes_input.replace_range(start..end, "");
+        }
+        self.notes_column -= 1;
+    }
+
+    pub fn notes_move_cursor(&mut self, right : bool) {
+        self.notes_column = if right {
+            cmp::min(self.notes_column + 1, text::grapheme_count(&self.notes_input))
+        } else {
+            self.notes_column.saturating_sub(1)
+        };
+    }
+
+    /// Copies the selected entry and the ones nested below it into the register and its
+    /// text onto the system clipboard, `cut` also removes them from the list
+    pub fn yank(&mut self, cut : bool) {
+        if !self.engine.yank(cut) {
+            return;
+        }
+
+        let count = self.engine.register.len();
+        self.engine.status_message = Some(match clipboard::copy(&register_text(&self.engine.register)) {
+            Ok(()) => format!("Copied {} todo{}", count, if count == 1 { "" } else { "'s" }),
+            Err(err) => format!("Copied for pasting here only, {}", err)
+        });
+    }
+
+    /// Pastes the clipboard text as new todo's, one per line. When the clipboard still holds
+    /// what was copied here (or can't be read) the register is pasted, status and all.
+    pub fn paste(&mut self) {
+        if self.engine.is_journal() {
+            return;
+        }
+
+        let entries = match clipboard::paste() {
+            Ok(text) if !text.trim().is_empty() && text.trim_end() != register_text(&self.engine.register) => text
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(|line| TodoData { message : line.trim().to_string(), created_at : Some(date::now()), ..TodoData::default() })
+                .collect(),
+            _ => self.engine.pasted_register()
+        };
+        if entries.is_empty() {
+            self.engine.status_message = Some("Nothing to paste".to_string());
+            return;
+        }
+
+        self.engine.paste_entries(entries);
+    }
+
+    /// Opens the first link in the selected entry's message, or else the first one in its notes
+    pub fn open_link(&mut self) {
+        let entry = &self.engine.active_list().entries[self.engine.target_row as usize];
+        let url = links::first_url(&entry.message).or_else(|| links::first_url(&entry.notes)).map(str::to_string);
+        self.engine.status_message = Some(match url {
+            Some(url) => match browser::open(&url) {
+                Ok(()) => format!("Opened {}", url),
+                Err(err) => format!("Couldn't open {}: {}", url, err)
+            },
+            None => "There is no link in this todo".to_string()
+        });
+    }
+
+    /// Index of the list shown next to the active one, `None` unless the split view is on
+    /// and the daily or the long term list is active
+    pub fn split_partner(&self) -> Option<usize> {
+        let other = match self.engine.active_list().role {
+            _ if !self.split_view => return None,
+            ListRole::Daily(_) => ListRole::LongTerm,
+            ListRole::LongTerm => self.engine.daily_role(),
+            _ => return None
+        };
+        self.engine.lists.iter().position(|list| list.role == other)
+    }
+
+    /// Turns the split view on or off, it starts on the daily list unless the long term one is active
+    pub fn toggle_split_view(&mut self) {
+        self.split_view = !self.split_view;
+        if self.split_view && self.split_partner().is_none() {
+            if let Some(daily) = self.engine.lists.iter().position(|list| matches!(list.role, ListRole::Daily(_))) {
+                self.engine.switch_to(daily);
+            }
+        }
+    }
+
+    /// Moves the focus to the other list of the split view
+    pub fn switch_pane(&mut self) {
+        if let Some(other) = self.split_partner() {
+            self.engine.switch_to(other);
+        }
+    }
+
+    /// Widens the focused list by `step` percent of the width, or narrows it when negative.
+    /// The other list of the split view or the notes pane gives up the space. With both of
+    /// them open the split view is resized, the notes pane keeps its share of the focused list.
+    pub fn resize_pane(&mut self, step : i32) {
+        let step = match self.split_partner() {
+            Some(_) if matches!(self.engine.active_list().role, ListRole::Daily(_)) => step,
+            Some(_) => -step,
+            None if self.show_details => -step,
+            None => {
+                self.engine.status_message = Some("Only the split view and the notes pane can be resized".to_string());
+                return;
+            }
+        };
+        let width = if self.split_partner().is_some() { &mut self.engine.config.split_width } else { &mut self.engine.config.details_width };
+        *width = (*width as i32 + step).clamp(*PANE_WIDTHS.start() as i32, *PANE_WIDTHS.end() as i32) as u16;
+        self.save_layout();
+    }
+
+    /// Keeps a layout changed with a key for the next start
+    pub fn save_layout(&mut self) {
+        if let Err(err) = self.engine.config.save_layout() {
+            self.engine.status_message = Some(format!("Failed to save the layout: {}", err));
+        }
+    }
+
+    /// Removes the selected entry, asking first unless `confirm_remove` is turned off.
+    /// Blank entries go without asking, there is nothing to lose.
+    pub fn request_remove(&mut self) {
+        let marked = self.engine.marked_indices().len();
+        if marked > 0 {
+            if self.engine.config.confirm_remove {
+                self.popup = Some(Popup::ConfirmRemove(format!("The {} marked todo's", marked)));
+            } else {
+                self.engine.remove_marked();
+            }
+            return;
+        }
+
+        let message = self.engine.get_current_message();
+        if self.engine.config.confirm_remove && !message.is_empty() && !self.engine.is_current_entry_locked() {
+            self.popup = Some(Popup::ConfirmRemove(message));
+        } else {
+            self.engine.remove_message();
+        }
+    }
+
+    /// Feeds a normal mode key press to the keymap. When the keys typed before it don't
+    /// lead anywhere with it, the key is looked up on its own.
+    pub fn normal_mode_action(&mut self, event : KeyEvent) -> Option<Action> {
+        if self.split_partner().is_some() && self.pending_keys.is_empty() && event.code == KeyCode::Tab && event.modifiers.is_empty() {
+            return Some(Action::SwitchPane);
+        }
+        self.pending_keys.push(Key::from_event(event));
+
+        loop {
+            match self.engine.config.keymap.resolve(&self.pending_keys) {
+                Resolution::Action(action) if action.changes_list() && self.engine.is_read_only() => {
+                    self.pending_keys.clear();
+                    self.engine.status_message = Some(format!("{} is read-only", self.engine.active_list().name));
+                    return None;
+                }
+                Resolution::Action(action) => {
+                    self.pending_keys.clear();
+                    return Some(action);
+                }
+                Resolution::Pending => return None,
+                Resolution::Unbound if self.pending_keys.len() > 1 => {
+                    self.pending_keys.drain(..self.pending_keys.len() - 1);
+                }
+                Resolution::Unbound => {
+                    self.pending_keys.clear();
+                    return None;
+                }
+            }
+        }
+    }
+
+    /// Moves the selection a screenful of entries down (`down`) or up
+    pub fn move_page(&mut self, down : bool) {
+        let visible = self.engine.visible_indices();
+        let position = visible.iter().position(|index| *index == self.engine.target_row as usize).unwrap_or(0);
+        let step = cmp::max(self.page_size, 1);
+        let position = if down { cmp::min(position + step, visible.len() - 1) } else { position.saturating_sub(step) };
+        self.engine.select_row(visible[position]);
+    }
+
+    /// Left click on the list: selects the clicked entry and toggles its status when the
+    /// click landed on the checkbox or was the second one of a double-click. The wheel
+    /// moves the selection, the list scrolls along with it.
+    pub fn handle_mouse(&mut self, mouse : MouseEvent) {
+        match mouse.kind {
+            MouseEventKind::ScrollUp => return self.engine.move_cursor(MoveCursorOperation::Up),
+            MouseEventKind::ScrollDown => return self.engine.move_cursor(MoveCursorOperation::Down),
+            MouseEventKind::Down(MouseButton::Left) => {}
+            _ => return
+        }
+
+        if self.side_pane.is_some_and(|area| {
+            (area.x..area.x + area.width).contains(&mouse.column) && (area.y..area.y + area.height).contains(&mouse.row)
+        }) {
+            return self.switch_pane();
+        }
+
+        let clicked = self.rendered_rows
+            .iter()
+            .find(|row| mouse.row >= row.y && mouse.row < row.y + row.height)
+            .map(|row| (row.index, mouse.column >= row.checkbox.0 && mouse.column < row.checkbox.1));
+
+        if let Some((index, on_checkbox)) = clicked {
+            let now = Instant::now();
+            let double_click = self.last_click.is_some_and(|(at, row)| row == index && now.duration_since(at) <= DOUBLE_CLICK_TIME);
+            // A third click starts over instead of toggling back
+            self.last_click = if double_click { None } else { Some((now, index)) };
+
+            self.engine.select_row(index);
+            if (on_checkbox || double_click) && !self.engine.get_current_message().is_empty() && !self.engine.is_read_only() {
+                self.engine.toggle_message_status();
+            }
+        }
+    }
+
+    /// The tag highlighted in the tag panel, `None` when the active list has no tags
+    pub fn highlighted_tag(&self) -> Option<String> {
+        let all_tags = self.engine.tag_index();
+        let index = cmp::min(self.tag_cursor, all_tags.len().saturating_sub(1));
+        all_tags.get(index).cloned()
+    }
+
+    /// Moves the tag panel highlight `offset` tags down (or up when negative), wrapping around
+    pub fn move_tag_cursor(&mut self, offset : i32) {
+        let count = self.engine.tag_index().len() as i32;
+        if count == 0 {
+            return;
+        }
+
+        let current = cmp::min(self.tag_cursor as i32, count - 1);
+        self.tag_cursor = (current + offset).rem_euclid(count) as usize;
+    }
+
+    /// Filters the list by the highlighted tag, or shows everything again when it already is
+    pub fn toggle_tag_filter(&mut self) {
+        let tag = match self.highlighted_tag() {
+            Some(tag) => tag,
+            None => {
+                self.engine.tag_filter = None;
+                self.engine.status_message = Some("No #tags in this list".to_string());
+                return;
+            }
+        };
+
+        self.engine.tag_filter = if self.engine.tag_filter.as_ref() == Some(&tag) { None } else { Some(tag) };
+    }
+
+    /// Starts a focus timer for the selected todo, or stops the running one
+    pub fn toggle_timer(&mut self) {
+        if self.timer.is_some() {
+            self.record_timer_session(false);
+            self.engine.status_message = Some("Focus timer stopped".to_string());
+            return;
+        }
+
+        let todo = match self.engine.selected_todo_id() {
+            Some(todo) => todo,
+            None => return
+        };
+        self.timer = Some((Instant::now(), Duration::from_secs(self.engine.config.focus_minutes * 60)));
+        self.timer_todo = todo;
+        self.timer_list = self.engine.active_list().id;
+    }
+
+    /// Message of the todo the focus timer was started for, empty when it was removed since
+    pub fn timer_task(&self) -> &str {
+        self.engine.list_index(self.timer_list)
+            .and_then(|list| self.engine.lists[list].entries.iter().find(|m| m.id == Some(self.timer_todo)))
+            .map_or("", |m| m.message.as_str())
+    }
+
+    /// Ends the running timer and adds the time spent to its todo, and a pomodoro when
+    /// the session was `completed`
+    fn record_timer_session(&mut self, completed : bool) {
+        let (started, duration) = match self.timer.take() {
+            Some(timer) => timer,
+            None => return
+        };
+
+        let spent = cmp::min(started.elapsed(), duration).as_secs() as u32;
+        if let Some(entry) = self.engine.add_spent(self.timer_list, self.timer_todo, spent) {
+            if completed {
+                entry.pomodoros += 1;
+            }
+        }
+    }
+
+    /// Starts the stopwatch on the selected todo. Stops it when it already runs for that
+    /// todo, and moves it over when it runs for another one.
+    pub fn toggle_tracking(&mut self) {
+        let task = self.engine.get_current_message();
+        let list = self.engine.active_list().id;
+        let selected = self.engine.selected_todo_id();
+        let same_task = selected == Some(self.tracking_todo) && self.tracking_list == list;
+        if let Some(spent) = self.stop_tracking() {
+            if same_task {
+                self.engine.status_message = Some(format!("Tracked {} on {}", date::format_duration(spent), task));
+                return;
+            }
+        }
+        let todo = match selected {
+            Some(todo) => todo,
+            None => return
+        };
+
+        self.tracking = Some(Instant::now());
+        self.tracking_todo = todo;
+        self.tracking_list = list;
+    }
+
+    /// Stops the stopwatch and adds the time it ran to its todo
+    pub fn stop_tracking(&mut self) -> Option<u32> {
+        let spent = self.tracking.take()?.elapsed().as_secs() as u32;
+        self.engine.add_spent(self.tracking_list, self.tracking_todo, spent);
+        Some(spent)
+    }
+
+    /// Seconds the stopwatch has been running on `m` of the active list
+    pub fn tracking_secs(&self, m : &TodoData) -> Option<u32> {
+        let started = self.tracking?;
+        let tracked = self.tracking_list == self.engine.active_list().id && m.id == Some(self.tracking_todo);
+        tracked.then(|| started.elapsed().as_secs() as u32)
+    }
+
+    pub fn timer_remaining(&self) -> Option<Duration> {
+        self.timer.map(|(started, duration)| duration.saturating_sub(started.elapsed()))
+    }
+
+    /// Called every tick; returns true when the focus timer just ran out
+    fn update_timer(&mut self) -> bool {
+        if self.timer_remaining() != Some(Duration::ZERO) {
+            return false;
+        }
+
+        self.record_timer_session(true);
+        self.engine.status_message = Some(format!("Focus session finished: {}", self.timer_task()));
+        true
+    }
+
+    pub fn open_prompt(&mut self, kind : PromptKind) {
+        self.prompt = Some(Prompt { kind, text : String::new() });
+        self.mode = InputMode::Prompting;
+    }
+
+    pub fn close_prompt(&mut self) {
+        self.prompt = None;
+        self.mode = InputMode::Normal;
+    }
+
+    pub fn prompt_add_char(&mut self, new_char : char) {
+        if matches!(self.prompt, Some(Prompt { kind : PromptKind::PromoteTarget, .. })) {
+            self.close_prompt();
+            match new_char {
+                'd' => self.engine.promote_someday(self.engine.daily_role()),
+                'l' => self.engine.promote_someday(ListRole::LongTerm),
+                _ => {}
+            }
+            return;
+        }
+
+        let confirm = match &mut self.prompt {
+            Some(Prompt { kind : PromptKind::ConfirmOverwrite(_) | PromptKind::ConfirmDeleteList | PromptKind::ConfirmPurgeClosed, .. }) => {
+                new_char == 'y'
+            }
+            Some(prompt) => {
+                prompt.text.push(new_char);
+                return;
+            }
+            None => return
+        };
+
+        // y/n questions are answered with a single key
+        if confirm {
+            self.submit_prompt();
+        } else {
+            self.close_prompt();
+        }
+    }
+
+    pub fn prompt_remove_char(&mut self) {
+        if let Some(prompt) = &mut self.prompt {
+            prompt.text.pop();
+        }
+    }
+
+    pub fn submit_prompt(&mut self) {
+        let prompt = match self.prompt.take() {
+            Some(prompt) => prompt,
+            None => return
+        };
+        self.mode = InputMode::Normal;
+
+        match prompt.kind {
+            PromptKind::ExportPath => {
+                let path = FileManager::expand_path(prompt.text.trim());
+                if prompt.text.trim().is_empty() {
+                    return;
+                }
+                if path.exists() {
+                    self.open_prompt(PromptKind::ConfirmOverwrite(path));
+                } else {
+                    self.engine.export_active_list(&path);
+                }
+            }
+            PromptKind::ConfirmOverwrite(path) => self.engine.export_active_list(&path),
+            PromptKind::DueDate => self.engine.set_due_date(prompt.text.trim()),
+            PromptKind::Recurrence => self.engine.set_recurrence(prompt.text.trim()),
+            PromptKind::Estimate => self.engine.set_estimate(prompt.text.trim()),
+            PromptKind::NewListName => self.engine.create_list(prompt.text.trim()),
+            PromptKind::RenameList => self.engine.rename_list(prompt.text.trim()),
+            PromptKind::ConfirmDeleteList => self.engine.delete_list(),
+            PromptKind::ConfirmPurgeClosed => self.engine.purge_closed_lists(),
+            PromptKind::PlanDay => self.engine.plan_day(prompt.text.trim()),
+            PromptKind::Snooze => self.engine.snooze(prompt.text.trim()),
+            PromptKind::BlockedBy => self.engine.set_blocked_by(prompt.text.trim()),
+            PromptKind::Command => self.run_command(prompt.text.trim()),
+            // Answered by a single key in `prompt_add_char`
+            PromptKind::PromoteTarget => {}
+        }
+    }
+
+    /// Runs a command typed after `:`. Besides the commands with an argument, every action
+    /// can be run by its name in the config, like `sort` or `stats`.
+    fn run_command(&mut self, text : &str) {
+        let (name, argument) = text.split_once(' ').map_or((text, ""), |(name, argument)| (name, argument.trim()));
+        match (name, argument) {
+            ("", _) => {}
+            ("archive", "") => self.engine.archive_done(),
+            ("sort", keys) if !keys.is_empty() => self.engine.sort_command(keys, false),
+            ("sort!", keys) => self.engine.sort_command(keys, true),
+            ("export", target) if !target.is_empty() => {
+                // A bare format like `md` writes the list to the current directory, named after it
+                let path = if target.contains(['.', '/']) {
+                    FileManager::expand_path(target)
+                } else {
+                    PathBuf::from(format!("{}.{}", self.engine.active_list().name.to_lowercase().replace(' ', "_"), target))
+                };
+                if path.exists() {
+                    self.open_prompt(PromptKind::ConfirmOverwrite(path));
+                } else {
+                    self.engine.export_active_list(&path);
+                }
+            }
+            // Day files are named like `2022_09_27`, so take that too
+            ("open", day) if !day.is_empty() => self.engine.plan_day(&day.replace('_', "-")),
+            _ => match Action::all().find(|(action_name, _)| *action_name == name) {
+                Some((_, action)) if argument.is_empty() && action.changes_list() && self.engine.is_read_only() => {
+                    self.engine.status_message = Some(format!("{} is read-only", self.engine.active_list().name));
+                }
+                Some((_, action)) if argument.is_empty() => self.queued_action = Some(action),
+                _ => self.engine.status_message = Some(format!("Unknown command: {}", text))
+            }
+        }
+    }
+
+    /// Asks which todo the selected one waits for, starting from the one it waits for now
+    pub fn edit_blocked_by(&mut self) {
+        if self.engine.is_journal() || self.engine.get_current_message().is_empty() {
+            return;
+        }
+
+        let current = self.engine.get_messages()[self.engine.target_row as usize].blocked_by;
+        let blocker = self.engine.lists
+            .iter()
+            .flat_map(|list| &list.entries)
+            .find(|entry| current.is_some() && entry.id == current)
+            .map(|entry| entry.message.clone());
+        self.open_prompt(PromptKind::BlockedBy);
+        if let (Some(prompt), Some(blocker)) = (&mut self.prompt, blocker) {
+            prompt.text = blocker;
+        }
+    }
+
+    /// Asks for the due date of the selected todo, starting from the current one
+    pub fn edit_due_date(&mut self) {
+        if self.engine.is_journal() || self.engine.get_current_message().is_empty() {
+            return;
+        }
+
+        let current = self.engine.get_messages()[self.engine.target_row as usize].due;
+        self.open_prompt(PromptKind::DueDate);
+        if let (Some(prompt), Some(due)) = (&mut self.prompt, current) {
+            prompt.text = due.format();
+        }
+    }
+
+    pub fn edit_recurrence(&mut self) {
+        if self.engine.is_journal() || self.engine.get_current_message().is_empty() {
+            return;
+        }
+
+        let current = self.engine.get_messages()[self.engine.target_row as usize].recurrence;
+        self.open_prompt(PromptKind::Recurrence);
+        if let (Some(prompt), Some(recurrence)) = (&mut self.prompt, current) {
+            prompt.text = recurrence.format();
+        }
+    }
+
+    /// Asks for the estimate of the selected todo, starting from the current one
+    pub fn edit_estimate(&mut self) {
+        if self.engine.is_journal() || self.engine.get_current_message().is_empty() {
+            return;
+        }
+
+        let current = self.engine.get_messages()[self.engine.target_row as usize].estimate_secs;
+        self.open_prompt(PromptKind::Estimate);
+        if let Some(prompt) = self.prompt.as_mut().filter(|_| current > 0) {
+            prompt.text = date::format_duration(current);
+        }
+    }
+
+    pub fn start_rename_list(&mut self) {
+        if !self.engine.is_named_list() {
+            return;
+        }
+
+        let name = self.engine.active_list().name.clone();
+        self.open_prompt(PromptKind::RenameList);
+        if let Some(prompt) = &mut self.prompt {
+            prompt.text = name;
+        }
+    }
+
+    pub fn start_delete_list(&mut self) {
+        if matches!(self.engine.active_list().role, ListRole::Day(_)) {
+            self.engine.close_day();
+        } else if self.engine.is_named_list() {
+            self.open_prompt(PromptKind::ConfirmDeleteList);
+        }
+    }
+
+    /// `M` outside the someday view defers the selected todo, inside it asks where to promote it to
+    pub fn someday_action(&mut self) {
+        if self.engine.active_list().role == ListRole::Someday {
+            if !self.engine.get_current_message().is_empty() {
+                self.open_prompt(PromptKind::PromoteTarget);
+            }
+            return;
+        }
+
+        if self.engine.is_journal() {
+            return;
+        }
+
+        if self.engine.move_selected_to(ListRole::Someday) {
+            self.engine.save_someday();
+        }
+    }
+
+    /// Opens a popup comparing the daily list with yesterday's file
+    pub fn show_day_diff(&mut self) {
+        let yesterday = date::Date::today().add_days(-1);
+        self.start_job("Loading yesterday", move |file_manager| {
+            JobResult::DayDiff(file_manager.load_day(yesterday))
+        });
+    }
+
+    pub fn show_calendar(&mut self) {
+        self.start_job("Reading the calendar", |file_manager| {
+            JobResult::Calendar(file_manager.days_with_files())
+        });
+    }
+
+    /// Moves the calendar's selection, or opens or closes it
+    pub fn calendar_key(&mut self, mut calendar : Calendar, key : KeyEvent) {
+        calendar.selected = match key.code {
+            KeyCode::Left | KeyCode::Char('h') => calendar.selected.add_days(-1),
+            KeyCode::Right | KeyCode::Char('l') => calendar.selected.add_days(1),
+            KeyCode::Up | KeyCode::Char('k') => calendar.selected.add_days(-7),
+            KeyCode::Down | KeyCode::Char('j') => calendar.selected.add_days(7),
+            KeyCode::PageUp | KeyCode::Char('<') => calendar.selected.add_months(-1),
+            KeyCode::PageDown | KeyCode::Char('>') => calendar.selected.add_months(1),
+            KeyCode::Enter => return self.engine.open_day(calendar.selected),
+            KeyCode::Esc | KeyCode::Char('q') => return,
+            _ => calendar.selected
+        };
+        self.popup = Some(Popup::Calendar(calendar));
+    }
+
+    pub fn show_history(&mut self) {
+        self.start_job("Reading the archive", |file_manager| {
+            JobResult::History(file_manager.days_with_files())
+        });
+    }
+
+    /// Moves the history picker's selection, or opens the selected day or closes it
+    pub fn history_key(&mut self, mut history : History, key : KeyEvent) {
+        let last = history.days.len().saturating_sub(1);
+        history.selected = match key.code {
+            KeyCode::Up | KeyCode::Char('k') => history.selected.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => (history.selected + 1).min(last),
+            KeyCode::PageUp => history.selected.saturating_sub(HISTORY_PAGE),
+            KeyCode::PageDown => (history.selected + HISTORY_PAGE).min(last),
+            KeyCode::Home | KeyCode::Char('g') => 0,
+            KeyCode::End | KeyCode::Char('G') => last,
+            KeyCode::Enter => {
+                if let Some(&day) = history.days.get(history.selected) {
+                    self.engine.open_day(day);
+                }
+                return;
+            }
+            KeyCode::Esc | KeyCode::Char('q') => return,
+            _ => history.selected
+        };
+        self.popup = Some(Popup::History(history));
+    }
+
+    pub fn show_profiles(&mut self) {
+        let names = self.engine.config.profiles.clone();
+        let open = names.iter().position(|name| *name == self.engine.config.profile).unwrap_or(0);
+        self.popup = Some(Popup::Profiles(Profiles { names, open, selected : open }));
+    }
+
+    /// Moves the profile picker's selection, or switches to the selected profile or closes it
+    pub fn profiles_key(&mut self, mut profiles : Profiles, key : KeyEvent) {
+        let last = profiles.names.len().saturating_sub(1);
+        profiles.selected = match key.code {
+            KeyCode::Up | KeyCode::Char('k') => profiles.selected.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => (profiles.selected + 1).min(last),
+            KeyCode::Enter => {
+                if let Some(name) = profiles.names.get(profiles.selected) {
+                    self.switch_profile(name.clone());
+                }
+                return;
+            }
+            KeyCode::Esc | KeyCode::Char('q') => return,
+            _ => profiles.selected
+        };
+        self.popup = Some(Popup::Profiles(profiles));
+    }
+
+    /// Saves the lists and asks `run` to start over on `profile`, the lists of the two
+    /// never share a file
+    fn switch_profile(&mut self, profile : String) {
+        if profile == self.engine.config.profile {
+            return;
+        }
+        self.stop_tracking();
+        if !self.engine.load_failed {
+            if let Err(err) = self.engine.save_lists() {
+                self.engine.status_message = Some(format!("Failed to save the lists, staying on {}: {}", self.engine.config.profile, err));
+                return;
+            }
+        }
+        self.switch_to = Some(profile);
+    }
+
+    /// Scrolls the help overlay, Esc, q or ? close it
+    pub fn help_key(&mut self, scroll : usize, key : KeyEvent) {
+        let last = help_lines(&self.engine.config.keymap).len().saturating_sub(1);
+        let scroll = match key.code {
+            KeyCode::Up | KeyCode::Char('k') => scroll.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => scroll + 1,
+            KeyCode::PageUp => scroll.saturating_sub(HELP_PAGE),
+            KeyCode::PageDown => scroll + HELP_PAGE,
+            KeyCode::Esc | KeyCode::Char('q' | '?') => return,
+            _ => scroll
+        };
+        self.popup = Some(Popup::Help(cmp::min(scroll, last)));
+    }
+
+    /// Opens the quick add popup, adding to the active list unless it doesn't take new todo's
+    pub fn show_quick_add(&mut self) {
+        let today = date::Date::today();
+        let targets : Vec<(usize, String)> = self.engine.lists
+            .iter()
+            .enumerate()
+            .filter(|(_, list)| !matches!(list.role, ListRole::Journal) && !matches!(list.role, ListRole::Day(day) if day < today))
+            .map(|(index, list)| (index, list.name.clone()))
+            .collect();
+        let selected = targets.iter().position(|(index, _)| *index == self.engine.target_list).unwrap_or(0);
+        self.popup = Some(Popup::QuickAdd(QuickAdd { text : String::new(), targets, selected }));
+    }
+
+    /// Types into the quick add popup, Tab picks the next list and Enter adds the todo
+    pub fn quick_add_key(&mut self, mut quick_add : QuickAdd, key : KeyEvent) {
+        let count = quick_add.targets.len();
+        match key.code {
+            KeyCode::Esc => return,
+            KeyCode::Enter => {
+                if !quick_add.text.trim().is_empty() {
+                    self.engine.quick_add(quick_add.targets[quick_add.selected].0, &quick_add.text);
+                }
+                return;
+            }
+            KeyCode::Tab => quick_add.selected = (quick_add.selected + 1) % count,
+            KeyCode::BackTab => quick_add.selected = (quick_add.selected + count - 1) % count,
+            KeyCode::Backspace => {
+                quick_add.text.pop();
+            }
+            KeyCode::Char(c) => quick_add.text.push(c),
+            _ => {}
+        }
+        self.popup = Some(Popup::QuickAdd(quick_add));
+    }
+
+    /// Syncs with every service set up in the config, `apply_sync` merges in what
+    /// changed there once the job is done
+    pub fn sync(&mut self) {
+        let (caldav, todoist) = (self.engine.config.caldav.clone(), self.engine.config.todoist.clone());
+        if caldav.is_none() && todoist.is_none() {
+            self.engine.status_message = Some("Set up a [caldav] or [todoist] section in the config to sync".to_string());
+            return;
+        }
+        if self.job.is_some() {
+            return;
+        }
+
+        let caldav = match caldav {
+            Some(account) => match self.engine.file_manager.load_synced_ids("caldav") {
+                Ok(known) => Some((account, self.engine.synced_entries(), sync::caldav_removed(&self.engine.lists, &known))),
+                Err(err) => {
+                    self.engine.status_message = Some(format!("Sync failed: {}", err));
+                    return;
+                }
+            },
+            None => None
+        };
+        let todoist = match todoist {
+            Some(account) => match self.engine.file_manager.load_synced_ids("todoist") {
+                Ok(known) => Some((account, sync::todoist_entries(&self.engine.lists), sync::todoist_removed(&self.engine.lists, &known))),
+                Err(err) => {
+                    self.engine.status_message = Some(format!("Sync failed: {}", err));
+                    return;
+                }
+            },
+            None => None
+        };
+        self.sync_state = SyncState::Running;
+        self.start_job("Syncing", move |_| JobResult::Sync(Synced::run(caldav, todoist)));
+    }
+
+    fn apply_sync(&mut self, synced : Synced) {
+        self.sync_state = if self.engine.apply_sync(synced) { SyncState::Synced(date::DateTime::now()) } else { SyncState::Failed };
+    }
+
+    /// Asks which day to put the selected daily todo off until
+    pub fn start_snooze(&mut self) {
+        if !matches!(self.engine.active_list().role, ListRole::Daily(_)) || self.engine.get_current_message().is_empty() {
+            self.engine.status_message = Some("Only todo's on the daily list can be snoozed".to_string());
+            return;
+        }
+        self.open_prompt(PromptKind::Snooze);
+        if let Some(prompt) = &mut self.prompt {
+            prompt.text = "tomorrow".to_string();
+        }
+    }
+
+    pub fn show_stats(&mut self) {
+        self.start_job("Reading the archive", |file_manager| {
+            JobResult::Stats(file_manager.completion_history(STATS_DAYS))
+        });
+    }
+
+    /// Runs `work` on a worker thread, its result is applied by `poll_job` once it's done.
+    /// Only one job runs at a time, starting another while one is busy is ignored.
+    fn start_job<F>(&mut self, label : &str, work : F)
+    where
+        F: FnOnce(FileManager) -> JobResult + Send + 'static
+    {
+        if self.job.is_some() {
+            return;
+        }
+
+        let (sender, receiver) = mpsc::channel();
+        let file_manager = self.engine.file_manager.clone();
+        thread::spawn(move || {
+            // The receiver is gone if the app quit in the meantime, nothing left to do then
+            let _ = sender.send(work(file_manager));
+        });
+
+        self.job = Some(BackgroundJob {
+            label : label.to_string(),
+            started : Instant::now(),
+            receiver
+        });
+    }
+
+    /// Applies the result of the background job once it finished
+    fn poll_job(&mut self) {
+        let result = match &self.job {
+            Some(job) => match job.receiver.try_recv() {
+                Ok(result) => Some(result),
+                Err(TryRecvError::Empty) => return,
+                Err(TryRecvError::Disconnected) => None
+            },
+            None => return
+        };
+        self.job = None;
+
+        match result {
+            Some(JobResult::DayDiff(Ok(entries))) => {
+                let diff = entries.map(|entries| DayDiff::between(&entries, self.engine.entries(self.engine.daily_role())));
+                self.popup = Some(Popup::DayDiff(diff));
+            }
+            Some(JobResult::DayDiff(Err(err))) => {
+                self.engine.status_message = Some(format!("Failed to load yesterday's file: {}", err));
+            }
+            Some(JobResult::Stats(Ok(mut history))) => {
+                // Today's file may be behind on what was checked off since the last save
+                if let Some(today) = history.last_mut() {
+                    today.1 = self.engine.daily_progress().0;
+                }
+                self.popup = Some(Popup::Stats(history));
+            }
+            Some(JobResult::Stats(Err(err))) => {
+                self.engine.status_message = Some(format!("Failed to read the archive: {}", err));
+            }
+            Some(JobResult::Calendar(Ok(days))) => {
+                let with_due = self.engine.lists
+                    .iter()
+                    .flat_map(|list| &list.entries)
+                    .filter(|m| m.status != Status::Done)
+                    .filter_map(|m| m.due)
+                    .collect();
+                let selected = match self.engine.active_list().role {
+                    ListRole::Day(day) => day,
+                    _ => date::Date::today()
+                };
+                self.popup = Some(Popup::Calendar(Calendar { selected, with_file : days.into_iter().collect(), with_due }));
+            }
+            Some(JobResult::Calendar(Err(err))) => {
+                self.engine.status_message = Some(format!("Failed to read the calendar: {}", err));
+            }
+            Some(JobResult::History(Ok(mut days))) => {
+                days.sort_by(|a, b| b.cmp(a));
+                days.dedup();
+                self.popup = Some(Popup::History(History { days, selected : 0 }));
+            }
+            Some(JobResult::History(Err(err))) => {
+                self.engine.status_message = Some(format!("Failed to read the archive: {}", err));
+            }
+            Some(JobResult::Sync(Ok(remote))) => self.apply_sync(remote),
+            Some(JobResult::Sync(Err(err))) => {
+                self.sync_state = SyncState::Failed;
+                self.engine.status_message = Some(format!("Sync failed: {}", err));
+            }
+            None => {
+                if self.sync_state == SyncState::Running {
+                    self.sync_state = SyncState::Failed;
+                }
+                self.engine.status_message = Some("Background job failed".to_string());
+            }
+        }
+    }
+
+    /// Spinner and label of a job that is taking noticeably long
+    pub fn job_spinner(&self) -> Option<String> {
+        let job = self.job.as_ref()?;
+        let elapsed = job.started.elapsed();
+        if elapsed < SPINNER_DELAY {
+            return None;
+        }
+
+        let frames : &[char] = if self.engine.config.ascii { &ASCII_SPINNER_FRAMES } else { &SPINNER_FRAMES };
+        let frame = frames[(elapsed.as_millis() / 100) as usize % frames.len()];
+        Some(format!("{} {}", frame, job.label))
+    }
+
+    /// Warning to show while editing outside the configured work hours
+    pub fn work_hours_warning(&self) -> Option<String> {
+        let (start, end) = self.engine.config.work_hours?;
+        if self.work_hours_dismissed || !self.engine.editing {
+            return None;
+        }
+
+        let hour = date::DateTime::now().hour;
+        if hour < start {
+            Some(format!("It's before {}:00", start))
+        } else if hour >= end {
+            Some(format!("It's after {}:00", end))
+        } else {
+            None
+        }
+    }
+
+    /// Open daily todo's, one per line with the top one first
+    fn status_file_contents(&self) -> String {
+        self.engine.entries(self.engine.daily_role())
+            .iter()
+            .filter(|m| !m.message.is_empty() && m.status != Status::Done)
+            .map(|m| format!("{}\n", m.message))
+            .collect()
+    }
+
+    /// Work that happens between key presses, run before every redraw: background job
+    /// results, the change of date, autosave, the status file, due notifications and the focus timer. Returns
+    /// true when the focus timer just ran out.
+    pub fn on_tick(&mut self) -> bool {
+        self.poll_job();
+        self.engine.poll_saves();
+        if matches!(self.mode, InputMode::Normal) {
+            self.engine.start_new_day();
+        }
+        self.engine.autosave();
+        self.engine.fade_saved();
+        self.publish_status();
+        if let Some(notifier) = self.notifier.clone() {
+            for message in self.newly_due(date::Date::today()) {
+                let _ = notifier.send(message);
+            }
+        }
+        self.update_timer()
+    }
+
+    /// How long to wait for input before the next tick: the configured tick rate, sooner
+    /// while a spinner turns, an autosave is pending or "saved" is about to fade
+    pub fn tick_rate(&self) -> Duration {
+        let tick_rate = Duration::from_millis(self.engine.config.tick_rate_ms);
+        let tick_rate = if self.job.is_some() { cmp::min(tick_rate, SPINNER_TICK_RATE) } else { tick_rate };
+        let tick_rate = self.engine.saved_shown_for().map_or(tick_rate, |left| cmp::min(left, tick_rate));
+        self.engine.autosave_due_in().map_or(tick_rate, |due| cmp::min(due, tick_rate))
+    }
+
+    /// Open todo's of every list that are due by `today` and weren't announced before,
+    /// as the text of their notification
+    fn newly_due(&mut self, today : date::Date) -> Vec<String> {
+        let due : Vec<(String, date::Date)> = self.engine.lists
+            .iter()
+            .filter(|list| list.role != ListRole::Journal)
+            .flat_map(|list| &list.entries)
+            .filter(|m| m.status != Status::Done)
+            .filter_map(|m| m.due.filter(|due| *due <= today).map(|due| (m.message.clone(), due)))
+            .collect();
+
+        due.into_iter()
+            .filter(|key| self.notified.insert(key.clone()))
+            .map(|(message, due)| if due < today {
+                format!("{} (overdue since {})", message, due.format())
+            } else {
+                format!("{} (due today)", message)
+            })
+            .collect()
+    }
+
+    /// Rewrites the status file when the open daily todo's changed since the last write.
+    /// Encrypted lists never get one, it would have their todo's in plain text.
+    fn publish_status(&mut self) {
+        let path = match &self.engine.config.status_file {
+            Some(path) if !self.engine.config.encrypt => FileManager::expand_path(path),
+            _ => return
+        };
+
+        let contents = self.status_file_contents();
+        if self.published_status.as_ref() == Some(&contents) {
+            return;
+        }
+
+        // Also remembered when the write fails, so it's retried on the next change rather than every tick
+        if let Err(err) = self.engine.file_manager.write_atomically(&path, &contents) {
+            self.engine.status_message = Some(format!("Failed to write {}: {}", path.display(), err));
+        }
+        self.published_status = Some(contents);
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crossterm::event::KeyModifiers;
+    use todo_core::{caldav, todoist, Priority};
+    use todo_core::config::{Config, DEFAULT_PROFILE};
+    use todo_core::engine::{AUTOSAVE_DELAY, SAVED_SHOWN_FOR};
+    use todo_core::lists::{self, SaveStatus, SaveWorker};
+    use todo_core::sort::SortKey;
+    use todo_core::sync::SYNC_UID_PREFIX;
+    use crate::test_dir::TestDir;
+
+    /// App whose daily list holds `entries`
+    pub fn todo(message : &str) -> TodoData {
+        TodoData {
+            message : message.to_string(),
+            ..TodoData::default()
+        }
+    }
+
+    pub fn daily_app(entries : Vec<TodoData>) -> App {
+        let mut app = App::default();
+        *app.engine.entries_mut(app.engine.daily_role()) = entries;
+        app
+    }
+
+    pub fn messages_of(app : &App) -> Vec<String> {
+        app.engine.get_messages().iter().map(|m| m.message.clone()).collect()
+    }
+
+    #[test]
+    fn saved_sort_order_becomes_the_default_of_the_list() {
+        let data_dir = TestDir::new("sort");
+        let urgent = |message| TodoData { priority : Priority::Urgent, ..todo(message) };
+        let mut app = daily_app(vec![todo("b"), urgent("c"), todo("a"), todo("")]);
+        app.engine.file_manager = FileManager::new(data_dir.path().to_path_buf());
+
+        app.run_command("sort! priority, alpha");
+        assert_eq!(messages_of(&app), vec!["c", "a", "b", ""]);
+        app.engine.get_messages_mut().swap(0, 2);
+        app.engine.sort_by_default();
+        assert_eq!(messages_of(&app), vec!["c", "a", "b", ""]);
+
+        app.run_command("sort size");
+        assert_eq!(app.engine.status_message.as_deref(), Some("Sort by one or more of status, priority, alphabetical, created"));
+    }
+
+    #[test]
+    fn renamed_lists_keep_their_sort_order() {
+        let data_dir = TestDir::new("rename");
+        let mut app = daily_app(vec![todo("")]);
+        app.engine.file_manager = FileManager::new(data_dir.path().to_path_buf());
+        app.engine.create_list("Groceries");
+        app.run_command("sort! alpha");
+
+        app.engine.rename_list("Shopping");
+
+        assert_eq!(app.engine.file_manager.load_sort_order("list_Shopping").unwrap(), Some(vec![SortKey::Alphabetical]));
+        assert_eq!(app.engine.file_manager.load_sort_order("list_Groceries").unwrap(), None);
+    }
+
+    #[test]
+    fn notes_are_typed_over_several_lines_and_saved_on_esc() {
+        let mut app = daily_app(vec![todo("call the bank"), todo("")]);
+
+        app.start_editing_notes();
+        for c in "fees\nagain".chars() {
+            app.notes_add_char(c);
+        }
+        app.notes_move_cursor(false);
+        app.notes_remove_char();
+        assert!(app.show_details);
+        assert_eq!(app.engine.get_messages()[0].notes, "");
+
+        app.commit_notes();
+        assert_eq!(app.engine.get_messages()[0].notes, "fees\nagan");
+        app.engine.undo();
+        assert_eq!(app.engine.get_messages()[0].notes, "");
+
+        app.engine.target_row = 1;
+        app.start_editing_notes();
+        assert!(matches!(app.input_mode(), InputMode::Normal));
+    }
+
+    #[test]
+    fn quick_add_appends_to_the_picked_list_and_keeps_the_selection() {
+        let mut app = daily_app(vec![todo("first"), todo("second"), todo("")]);
+        app.engine.target_row = 2;
+
+        app.show_quick_add();
+        for c in "milk".chars() {
+            press_in_quick_add(&mut app, KeyCode::Char(c));
+        }
+        press_in_quick_add(&mut app, KeyCode::Enter);
+        assert!(app.popup.is_none());
+        assert_eq!(messages_of(&app), vec!["first", "second", "milk", ""]);
+        assert_eq!(app.engine.target_row, 3);
+        assert!(app.engine.get_messages()[2].created_at.is_some());
+
+        app.engine.target_row = 0;
+        app.show_quick_add();
+        press_in_quick_add(&mut app, KeyCode::Tab);
+        press_in_quick_add(&mut app, KeyCode::Char('x'));
+        press_in_quick_add(&mut app, KeyCode::Enter);
+        assert_eq!(app.engine.entries(ListRole::LongTerm)[0].message, "x");
+        assert_eq!((app.engine.target_list, app.engine.target_row), (0, 0));
+
+        app.engine.undo();
+        assert!(app.engine.entries(ListRole::LongTerm)[0].message.is_empty());
+    }
+
+    fn press_in_quick_add(app : &mut App, code : KeyCode) {
+        match app.popup.take() {
+            Some(Popup::QuickAdd(quick_add)) => app.quick_add_key(quick_add, KeyEvent::from(code)),
+            _ => panic!("the quick add popup isn't open")
+        }
+    }
+
+    #[test]
+    fn visual_marks_apply_batch_operations_to_the_range() {
+        let mut app = daily_app(vec![todo("a"), todo("b"), TodoData { indent : 1, ..todo("b child") }, todo("c"), todo("")]);
+        app.engine.toggle_visual();
+        app.engine.move_cursor(MoveCursorOperation::Down);
+        app.engine.update_visual_marks();
+        assert_eq!(app.engine.marked_indices(), vec![0, 1]);
+
+        app.engine.change_priority(true);
+        assert!(app.engine.get_messages()[..2].iter().all(|m| m.priority == Priority::High && !m.marked));
+        assert_eq!(app.engine.get_messages()[3].priority, Priority::Medium);
+        assert!(app.engine.visual_anchor.is_none());
+
+        app.engine.toggle_visual();
+        app.engine.move_cursor(MoveCursorOperation::Up);
+        app.engine.update_visual_marks();
+        app.engine.move_to_other_list();
+        assert_eq!(messages_of(&app), vec!["c", ""]);
+        let long_term : Vec<(&str, usize)> = app.engine.entries(ListRole::LongTerm).iter().map(|m| (m.message.as_str(), m.indent)).collect();
+        assert_eq!(long_term, vec![("a", 0), ("b", 0), ("b child", 1), ("", 0)]);
+
+        app.engine.config.confirm_remove = false;
+        app.engine.toggle_mark();
+        app.request_remove();
+        assert_eq!(messages_of(&app), vec![""]);
+    }
+
+    #[test]
+    fn remove_asks_first_unless_turned_off() {
+        let mut app = daily_app(vec![todo("keep me"), todo("")]);
+
+        app.request_remove();
+        assert!(matches!(&app.popup, Some(Popup::ConfirmRemove(message)) if message == "keep me"));
+        assert_eq!(messages_of(&app), vec!["keep me", ""]);
+
+        app.popup = None;
+        app.engine.config.confirm_remove = false;
+        app.request_remove();
+        assert_eq!(messages_of(&app), vec![""]);
+    }
+
+    #[test]
+    fn pasted_entries_keep_their_nesting_below_the_selected_todo() {
+        let mut app = daily_app(vec![todo("first"), TodoData { indent : 1, ..todo("nested") }, todo("")]);
+        app.engine.target_row = 1;
+        let register = vec![
+            TodoData { indent : 2, status : Status::Done, ..todo("cut") },
+            TodoData { indent : 3, ..todo("cut child") },
+        ];
+
+        app.engine.paste_entries(register);
+
+        assert_eq!(messages_of(&app), vec!["first", "nested", "cut", "cut child", ""]);
+        let entries = &app.engine.active_list().entries;
+        assert_eq!((entries[2].indent, entries[3].indent), (1, 2));
+        assert_eq!(entries[2].status, Status::Done);
+        assert_eq!(register_text(&entries[2..4]), "cut\n  cut child");
+    }
+
+    #[test]
+    fn double_click_toggles_done_and_the_wheel_moves_the_selection() {
+        let mut app = daily_app(vec![todo("first"), todo("second"), todo("")]);
+        app.rendered_rows = (0..3).map(|index| RenderedRow { index, y : 5 + index as u16, height : 1, checkbox : (3, 6) }).collect();
+        let click = |row : u16| MouseEvent { kind : MouseEventKind::Down(MouseButton::Left), column : 20, row, modifiers : KeyModifiers::NONE };
+        let scroll = MouseEvent { kind : MouseEventKind::ScrollUp, column : 20, row : 0, modifiers : KeyModifiers::NONE };
+
+        app.handle_mouse(click(6));
+        assert!(app.engine.target_row == 1 && app.engine.active_list().entries[1].status == Status::Todo);
+        app.handle_mouse(click(6));
+        assert_eq!(app.engine.active_list().entries[1].status, Status::Done);
+
+        app.handle_mouse(scroll);
+        assert_eq!(app.engine.target_row, 0);
+    }
+
+    #[test]
+    fn page_keys_move_the_selection_a_screenful() {
+        let mut app = daily_app((0..10).map(|index| todo(&format!("todo {}", index))).chain([todo("")]).collect());
+        app.page_size = 4;
+
+        app.move_page(true);
+        assert_eq!(app.engine.target_row, 4);
+        app.move_page(true);
+        app.move_page(true);
+        assert_eq!(app.engine.target_row, 10);
+        app.move_page(false);
+        assert_eq!(app.engine.target_row, 6);
+    }
+
+    #[test]
+    fn vim_scheme_waits_for_the_second_d_of_dd() {
+        let mut app = App {
+            engine : Engine::new(Config::parse("[keys]\nscheme = \"vim\"\n", DEFAULT_PROFILE), FileManager::default()),
+            ..App::default()
+        };
+        let press = |c : char| KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE);
+
+        assert_eq!(app.normal_mode_action(press('d')), None);
+        assert_eq!(app.normal_mode_action(press('d')), Some(Action::Remove));
+        // A key that doesn't continue the sequence counts on its own
+        assert_eq!(app.normal_mode_action(press('g')), None);
+        assert_eq!(app.normal_mode_action(press('x')), Some(Action::ToggleDone));
+    }
+
+    #[test]
+    fn finished_focus_session_counts_a_pomodoro() {
+        let mut app = daily_app(vec![TodoData { message : "write report".to_string(), ..TodoData::default() }]);
+        app.toggle_timer();
+        app.toggle_timer();
+        assert_eq!(app.engine.get_messages()[0].pomodoros, 0);
+
+        app.toggle_timer();
+        app.timer = app.timer.map(|(started, _)| (started, Duration::ZERO));
+        assert!(app.update_timer());
+        assert_eq!(app.engine.get_messages()[0].pomodoros, 1);
+        assert!(app.timer.is_none());
+    }
+
+    #[test]
+    fn focus_timer_follows_the_todo_and_skips_the_blank_entry() {
+        let mut app = daily_app(vec![todo("review"), todo("review"), TodoData::default()]);
+        app.engine.target_row = 1;
+        app.toggle_timer();
+        app.engine.get_messages_mut()[1].message = "review the report".to_string();
+        assert_eq!(app.timer_task(), "review the report");
+        app.timer = app.timer.map(|(_, duration)| (Instant::now() - Duration::from_secs(60), duration));
+        app.toggle_timer();
+        assert_eq!(app.engine.get_messages()[0].actual_secs, 0);
+        assert!(app.engine.get_messages()[1].actual_secs >= 60);
+
+        app.engine.target_row = 2;
+        app.toggle_timer();
+        assert!(app.timer.is_none());
+    }
+
+    #[test]
+    fn tracking_moves_to_the_selected_todo_and_adds_up() {
+        let mut app = daily_app(vec![
+            TodoData { message : "write report".to_string(), ..TodoData::default() },
+            TodoData { message : "review".to_string(), actual_secs : 60, ..TodoData::default() },
+        ]);
+        app.toggle_tracking();
+        let started = app.tracking.unwrap();
+        assert_eq!(app.tracking_secs(&app.engine.get_messages()[0]), Some(0));
+        assert_eq!(app.tracking_secs(&app.engine.get_messages()[1]), None);
+
+        // Switching todo's stops the first stopwatch and starts a new one
+        app.engine.target_row = 1;
+        app.toggle_tracking();
+        assert_eq!(Some(app.tracking_todo), app.engine.get_messages()[1].id);
+        assert!(app.tracking.unwrap() >= started);
+
+        app.toggle_tracking();
+        assert!(app.tracking.is_none());
+        assert!(app.engine.get_messages()[1].actual_secs >= 60);
+        assert!(app.engine.unsaved_since.is_some());
+    }
+
+    #[test]
+    fn estimates_are_edited_in_a_prompt_and_summed_up_for_the_day() {
+        let mut app = daily_app(vec![TodoData { actual_secs : 1500, ..todo("write report") }, todo("review"), TodoData::default()]);
+        app.edit_estimate();
+        app.prompt.as_mut().unwrap().text = "1h 30m".to_string();
+        app.submit_prompt();
+        assert_eq!(app.engine.get_messages()[0].estimate_secs, 5400);
+
+        app.edit_estimate();
+        assert_eq!(app.prompt.as_ref().unwrap().text, "1h 30m");
+        app.prompt.as_mut().unwrap().text = "soon".to_string();
+        app.submit_prompt();
+        assert_eq!(app.engine.status_message.as_deref(), Some("Not a duration: soon"));
+
+        app.engine.target_row = 1;
+        app.edit_estimate();
+        app.prompt.as_mut().unwrap().text = "30".to_string();
+        app.submit_prompt();
+        assert_eq!(app.engine.daily_time_summary(), "Tracked today: 25m of 2h 00m estimated");
+    }
+
+    #[test]
+    fn tracking_follows_the_todo_and_not_its_message() {
+        let mut app = daily_app(vec![todo("review"), todo("review"), TodoData::default()]);
+        app.engine.target_row = 1;
+        app.toggle_tracking();
+        assert_eq!(app.tracking_secs(&app.engine.get_messages()[0]), None);
+        assert_eq!(app.tracking_secs(&app.engine.get_messages()[1]), Some(0));
+
+        app.tracking = Instant::now().checked_sub(Duration::from_secs(60));
+        app.engine.get_messages_mut()[1].message = "review the report".to_string();
+        app.toggle_tracking();
+        assert_eq!(app.engine.get_messages()[0].actual_secs, 0);
+        assert!(app.engine.get_messages()[1].actual_secs >= 60);
+
+        // The blank entry isn't saved, time tracked on it would be lost
+        app.engine.target_row = 2;
+        app.toggle_tracking();
+        assert!(app.tracking.is_none());
+    }
+
+    #[test]
+    fn tick_rate_comes_from_the_config_and_speeds_up_for_autosave() {
+        let config = Config::parse("tick_rate_ms = 1000\n", DEFAULT_PROFILE);
+        let mut app = App { engine : Engine::new(config, FileManager::default()), ..App::default() };
+        assert_eq!(app.tick_rate(), Duration::from_millis(1000));
+
+        app.engine.mark_changed();
+        assert!(app.tick_rate() <= AUTOSAVE_DELAY);
+    }
+
+    #[test]
+    fn status_file_lists_the_open_todos_unless_the_lists_are_encrypted() {
+        let data_dir = TestDir::new("status");
+        let path = data_dir.path().join("status");
+        let mut app = daily_app(vec![todo("write report"), TodoData { status : Status::Done, ..todo("standup") }, todo("")]);
+        app.engine.config.status_file = Some(path.to_string_lossy().into_owned());
+
+        app.engine.config.encrypt = true;
+        app.publish_status();
+        assert!(!path.exists());
+
+        app.engine.config.encrypt = false;
+        app.publish_status();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "write report\n");
+    }
+
+    #[test]
+    fn saved_shows_briefly_after_a_write() {
+        let (sender, _requests) = mpsc::channel();
+        let (answers, results) = mpsc::channel();
+        let mut app = daily_app(vec![todo("write report")]);
+        app.engine.saver = Some(SaveWorker { sender, results, handle : thread::spawn(|| {}), pending : 0 });
+        app.engine.mark_changed();
+        assert_eq!(app.engine.save_status, SaveStatus::Clean);
+
+        app.engine.unsaved_since = Instant::now().checked_sub(AUTOSAVE_DELAY);
+        app.on_tick();
+        assert_eq!(app.engine.save_status, SaveStatus::Saving);
+        answers.send(Ok(())).unwrap();
+        app.on_tick();
+        assert!(matches!(app.engine.save_status, SaveStatus::Saved(_)));
+        assert!(app.tick_rate() <= SAVED_SHOWN_FOR);
+
+        app.engine.save_status = SaveStatus::Saved(Instant::now() - SAVED_SHOWN_FOR);
+        app.on_tick();
+        assert_eq!(app.engine.save_status, SaveStatus::Clean);
+    }
+
+    #[test]
+    fn calendar_dates_know_their_weekday_and_month_length() {
+        let date = date::Date::parse("2024-01-31").unwrap();
+        assert_eq!(date.weekday(), 2);
+        assert_eq!(date.add_months(1), date::Date::parse("2024-02-29").unwrap());
+        assert_eq!(date.add_months(-1), date::Date::parse("2023-12-31").unwrap());
+        assert_eq!(date::days_in_month(2023, 2), 28);
+    }
+
+    #[test]
+    fn past_days_from_the_calendar_are_read_only() {
+        let mut app = daily_app(vec![TodoData { message : "write report".to_string(), ..TodoData::default() }]);
+        let day = date::Date::parse("1990-01-01").unwrap();
+        let calendar = Calendar { selected : day.add_days(-1), with_file : HashSet::new(), with_due : HashSet::new() };
+        app.calendar_key(calendar, KeyEvent::from(KeyCode::Right));
+        let calendar = match app.popup.take() {
+            Some(Popup::Calendar(calendar)) => calendar,
+            _ => panic!("the calendar stays open while moving")
+        };
+        assert_eq!(calendar.selected, day);
+        app.calendar_key(calendar, KeyEvent::from(KeyCode::Enter));
+
+        assert_eq!(app.engine.active_list().role, ListRole::Day(day));
+        assert!(app.engine.is_read_only());
+        assert_eq!(app.normal_mode_action(KeyEvent::from(KeyCode::Char('d'))), None);
+        assert_eq!(app.normal_mode_action(KeyEvent::from(KeyCode::Down)), Some(Action::Down));
+
+        // Closing the tab goes back to where the list was opened from
+        app.start_delete_list();
+        assert!(app.engine.lists.iter().all(|list| list.role != ListRole::Day(day)));
+    }
+
+    #[test]
+    fn history_picker_opens_the_selected_day_read_only() {
+        let mut app = daily_app(vec![TodoData::default()]);
+        let days = vec![date::Date::parse("1990-01-02").unwrap(), date::Date::parse("1990-01-01").unwrap()];
+        app.history_key(History { days : days.clone(), selected : 0 }, KeyEvent::from(KeyCode::Down));
+        let history = match app.popup.take() {
+            Some(Popup::History(history)) => history,
+            _ => panic!("the picker stays open while moving")
+        };
+        assert_eq!(history.selected, 1);
+        app.history_key(history, KeyEvent::from(KeyCode::Down));
+        let history = match app.popup.take() {
+            Some(Popup::History(history)) => history,
+            _ => panic!("the picker stays open while moving")
+        };
+        assert_eq!(history.selected, 1);
+
+        app.history_key(history, KeyEvent::from(KeyCode::Enter));
+        assert!(app.popup.is_none());
+        assert_eq!(app.engine.active_list().role, ListRole::Day(days[1]));
+        assert!(app.engine.is_read_only());
+    }
+
+    #[test]
+    fn sync_gives_uids_and_merges_the_server_tasks() {
+        let data_dir = TestDir::new("caldav_sync");
+        let mut app = daily_app(vec![todo("pay rent"), todo("send invoice"), TodoData::default()]);
+        app.engine.file_manager = FileManager::new(data_dir.path().to_path_buf());
+        let local = app.engine.synced_entries();
+        assert_eq!(local.len(), 2);
+        assert!(local.iter().all(|(uid, _)| uid.starts_with(SYNC_UID_PREFIX)));
+        assert_eq!(app.engine.synced_entries()[0].0, local[0].0);
+
+        let remote = |uid : &str, summary : &str, status| caldav::RemoteTodo {
+            uid : uid.to_string(),
+            href : format!("/tasks/{}.ics", uid),
+            summary : summary.to_string(),
+            status,
+            due : None
+        };
+        app.apply_sync(Synced {
+            caldav : Some(vec![
+                remote(&local[0].0, "pay rent", Status::Done),
+                remote("phone-1", "call mum", Status::Todo),
+                remote("phone-2", "old errand", Status::Done),
+                remote(&format!("{}gone", SYNC_UID_PREFIX), "removed here", Status::Todo),
+            ]),
+            caldav_pushed : local.iter().map(|(uid, _)| uid.clone()).collect(),
+            todoist : None,
+            todoist_pushed : vec![],
+            failed : None
+        });
+
+        assert_eq!(messages_of(&app), vec!["pay rent", "send invoice", "call mum", ""]);
+        assert_eq!(app.engine.get_messages()[0].status, Status::Done);
+        assert_eq!(app.engine.get_messages()[2].sync_uid.as_deref(), Some("phone-1"));
+        assert!(matches!(app.sync_state, SyncState::Synced(_)));
+        assert_eq!(app.engine.file_manager.load_synced_ids("caldav").unwrap().len(), 3);
+    }
+
+    #[test]
+    fn tasks_of_todos_removed_here_are_deleted_and_not_picked_up_again() {
+        let data_dir = TestDir::new("caldav_removed");
+        let mut app = daily_app(vec![todo("pay rent"), TodoData::default()]);
+        app.engine.file_manager = FileManager::new(data_dir.path().to_path_buf());
+        let remote = |uid : &str| caldav::RemoteTodo {
+            uid : uid.to_string(),
+            href : format!("/tasks/{}.ics", uid),
+            summary : uid.to_string(),
+            status : Status::Todo,
+            due : None
+        };
+        let local = app.engine.synced_entries();
+        app.apply_sync(Synced {
+            caldav : Some(vec![remote("phone-1"), remote("phone-2")]),
+            caldav_pushed : vec![local[0].0.clone()],
+            todoist : None,
+            todoist_pushed : vec![],
+            failed : None
+        });
+        assert_eq!(messages_of(&app), vec!["pay rent", "phone-1", "phone-2", ""]);
+
+        let moved = app.engine.get_messages_mut().remove(1);
+        app.engine.entries_mut(ListRole::Someday).insert(0, moved);
+        app.engine.get_messages_mut().remove(1);
+        let known = app.engine.file_manager.load_synced_ids("caldav").unwrap();
+        assert_eq!(sync::caldav_removed(&app.engine.lists, &known), vec!["phone-1", "phone-2"]);
+
+        // A server that still has the moved task doesn't bring it back
+        app.apply_sync(Synced { caldav : Some(vec![remote("phone-1")]), caldav_pushed : vec![], todoist : None, todoist_pushed : vec![], failed : None });
+        assert_eq!(messages_of(&app), vec!["pay rent", ""]);
+        assert_eq!(app.engine.file_manager.load_synced_ids("caldav").unwrap(), vec![local[0].0.clone()]);
+    }
+
+    #[test]
+    fn todoist_outcome_updates_the_long_term_list() {
+        let data_dir = TestDir::new("todoist_sync");
+        let mut app = App::default();
+        app.engine.file_manager = FileManager::new(data_dir.path().to_path_buf());
+        *app.engine.entries_mut(ListRole::LongTerm) = vec![
+            todo("learn rust"),
+            TodoData { todoist_id : Some("7".to_string()), ..todo("renew passport") },
+            TodoData::default(),
+        ];
+
+        app.apply_sync(Synced {
+            caldav : None,
+            caldav_pushed : vec![],
+            todoist : Some(todoist::Outcome {
+                created : vec![("learn rust".to_string(), "8".to_string())],
+                finished : vec!["7".to_string()],
+                added : vec![todoist::Task { id : "9".to_string(), content : "read a book".to_string(), due : None }],
+            }),
+            todoist_pushed : vec!["7".to_string(), "8".to_string()],
+            failed : None
+        });
+
+        let entries = app.engine.entries(ListRole::LongTerm);
+        assert_eq!(entries[0].todoist_id.as_deref(), Some("8"));
+        assert_eq!(entries[1].status, Status::Done);
+        assert_eq!(entries[2].message, "read a book");
+        assert!(entries[3].message.is_empty());
+        assert_eq!(app.engine.file_manager.load_synced_ids("todoist").unwrap(), vec!["7", "8", "9"]);
+    }
+
+    #[test]
+    fn todoist_tasks_of_todos_removed_or_moved_here_arent_added_again() {
+        let data_dir = TestDir::new("todoist_removed");
+        let mut app = App::default();
+        app.engine.file_manager = FileManager::new(data_dir.path().to_path_buf());
+        *app.engine.entries_mut(ListRole::LongTerm) = vec![
+            TodoData { todoist_id : Some("7".to_string()), ..todo("renew passport") },
+            TodoData { todoist_id : Some("8".to_string()), ..todo("learn rust") },
+            TodoData::default(),
+        ];
+        app.apply_sync(Synced {
+            caldav : None,
+            caldav_pushed : vec![],
+            todoist : Some(todoist::Outcome::default()),
+            todoist_pushed : vec!["7".to_string(), "8".to_string()],
+            failed : None
+        });
+
+        // One is removed, the other moved to the daily list
+        app.engine.entries_mut(ListRole::LongTerm).remove(0);
+        let moved = app.engine.entries_mut(ListRole::LongTerm).remove(0);
+        lists::insert_before_blank(app.engine.entries_mut(app.engine.daily_role()), moved);
+        let known = app.engine.file_manager.load_synced_ids("todoist").unwrap();
+        assert_eq!(sync::todoist_removed(&app.engine.lists, &known), vec!["7"]);
+
+        // The removed task was closed by the sync, the moved one is still open
+        app.apply_sync(Synced {
+            caldav : None,
+            caldav_pushed : vec![],
+            todoist : Some(todoist::Outcome {
+                added : vec![todoist::Task { id : "8".to_string(), content : "learn rust".to_string(), due : None }],
+                ..todoist::Outcome::default()
+            }),
+            todoist_pushed : vec![],
+            failed : None
+        });
+
+        assert!(app.engine.entries(ListRole::LongTerm).iter().all(|m| m.message.is_empty()));
+        assert_eq!(app.engine.entries(app.engine.daily_role()).iter().filter(|m| m.message == "learn rust").count(), 1);
+        assert_eq!(app.engine.file_manager.load_synced_ids("todoist").unwrap(), vec!["8"]);
+    }
+
+    #[test]
+    fn failed_todoist_sync_keeps_the_ids_of_the_tasks_it_created() {
+        let data_dir = TestDir::new("todoist_failed");
+        let mut app = App::default();
+        app.engine.file_manager = FileManager::new(data_dir.path().to_path_buf());
+        *app.engine.entries_mut(ListRole::LongTerm) = vec![todo("learn rust"), todo("renew passport"), TodoData::default()];
+
+        app.apply_sync(Synced {
+            caldav : None,
+            caldav_pushed : vec![],
+            todoist : Some(todoist::Outcome { created : vec![("learn rust".to_string(), "8".to_string())], ..todoist::Outcome::default() }),
+            todoist_pushed : vec!["8".to_string()],
+            failed : Some(io::Error::new(io::ErrorKind::TimedOut, "timed out"))
+        });
+
+        let entries = app.engine.entries(ListRole::LongTerm);
+        assert_eq!(entries[0].todoist_id.as_deref(), Some("8"));
+        assert_eq!(entries[1].todoist_id, None);
+        assert_eq!(app.sync_state, SyncState::Failed);
+        assert_eq!(app.engine.status_message.as_deref(), Some("Sync failed: timed out"));
+    }
+
+    #[test]
+    fn archive_command_moves_finished_todos_out_of_the_list() {
+        let data_dir = TestDir::new("archive");
+        let done = |message| TodoData { status : Status::Done, ..todo(message) };
+        let mut app = daily_app(vec![
+            done("buy milk"),
+            done("plan trip"),
+            TodoData { indent : 1, ..todo("book hotel") },
+            todo("call mum"),
+            todo(""),
+        ]);
+        app.engine.file_manager = FileManager::new(data_dir.path().to_path_buf());
+
+        app.run_command("archive");
+        assert_eq!(messages_of(&app), vec!["plan trip", "book hotel", "call mum", ""]);
+        assert_eq!(app.engine.status_message.as_deref(), Some("Archived 1 todo"));
+        let archived = std::fs::read_to_string(data_dir.path().join("archive").join(format!("{}.todo", date::Date::today().file_stem()))).unwrap();
+        assert!(archived.contains("buy milk"));
+
+        app.run_command("undo");
+        assert_eq!(app.queued_action, Some(Action::Undo));
+        app.run_command("frobnicate");
+        assert_eq!(app.engine.status_message.as_deref(), Some("Unknown command: frobnicate"));
+    }
+
+    #[test]
+    fn newly_due_announces_each_open_todo_once() {
+        let today = date::Date::today();
+        let mut app = daily_app(vec![
+            TodoData { message : "pay rent".to_string(), due : Some(today), ..TodoData::default() },
+            TodoData { message : "renew passport".to_string(), due : Some(today.add_days(-2)), ..TodoData::default() },
+            TodoData { message : "call back".to_string(), due : Some(today.add_days(1)), ..TodoData::default() },
+            TodoData { message : "sent".to_string(), due : Some(today), status : Status::Done, ..TodoData::default() },
+        ]);
+
+        assert_eq!(app.newly_due(today), vec![
+            "pay rent (due today)".to_string(),
+            format!("renew passport (overdue since {})", today.add_days(-2).format()),
+        ]);
+        assert!(app.newly_due(today).is_empty());
+        assert_eq!(app.newly_due(today.add_days(1)), vec!["call back (due today)".to_string()]);
+    }
+}
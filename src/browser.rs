@@ -0,0 +1,25 @@
+use std::io;
+use std::process::{Command, Stdio};
+use std::thread;
+
+/// Openers tried in order, the first one that is installed is used
+const OPEN_COMMANDS : [&str; 3] = ["xdg-open", "wslview", "open"];
+
+/// Opens `url` in the default browser, or the one in `$BROWSER` when it's set. The opener
+/// isn't waited for, a browser started by it may keep running in the foreground.
+pub fn open(url : &str) -> io::Result<()> {
+    let browser = std::env::var("BROWSER").ok().filter(|browser| !browser.is_empty());
+    for program in browser.iter().map(String::as_str).chain(OPEN_COMMANDS) {
+        let mut child = match Command::new(program).arg(url).stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::null()).spawn() {
+            Ok(child) => child,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(err)
+        };
+
+        // Reaped on a thread of its own so it doesn't linger as a zombie
+        thread::spawn(move || child.wait());
+        return Ok(());
+    }
+
+    Err(io::Error::new(io::ErrorKind::NotFound, "no browser opener found (xdg-open, wslview or open)"))
+}
@@ -0,0 +1,319 @@
+use std::io;
+
+use crate::date;
+use crate::http;
+use crate::{Status, TodoData};
+
+/// CalDAV task list the todo's are synced with, from the `[caldav]` section of the config
+#[derive(Clone)]
+pub struct Account {
+    /// URL of the calendar collection, e.g.
+    /// `https://cloud.example.com/remote.php/dav/calendars/me/tasks/`
+    pub url : String,
+    pub user : String,
+    pub password : String,
+}
+
+/// A todo of a synced list with the UID of its task
+pub type LocalTodo = (String, TodoData);
+
+/// A VTODO as it's stored on the server
+#[derive(Clone, PartialEq, Debug)]
+pub struct RemoteTodo {
+    pub uid : String,
+    /// Path of the task's resource, relative to the server
+    pub href : String,
+    pub summary : String,
+    pub status : Status,
+    pub due : Option<date::Date>,
+}
+
+const QUERY : &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<c:calendar-query xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+  <d:prop><d:getetag/><c:calendar-data/></d:prop>
+  <c:filter><c:comp-filter name="VCALENDAR"><c:comp-filter name="VTODO"/></c:comp-filter></c:filter>
+</c:calendar-query>
+"#;
+
+/// Pulls the tasks on the server, deletes the open ones whose UID is in `removed`, then
+/// pushes every `(uid, entry)` of `local` that differs from its copy there. A task finished
+/// on the server is pushed as finished. Returns the tasks as they were pulled but the
+/// deleted ones, so the caller can apply the same changes and pick up the new ones.
+pub fn sync(account : &Account, local : &[LocalTodo], removed : &[String]) -> io::Result<Vec<RemoteTodo>> {
+    let mut remote = pull(account)?;
+    let is_removed = |todo : &RemoteTodo| todo.status != Status::Done && removed.contains(&todo.uid);
+    for todo in remote.iter().filter(|todo| is_removed(todo)) {
+        request(account, "DELETE", &resolve(&account.url, &todo.href), &[], "")?;
+    }
+    remote.retain(|todo| !is_removed(todo));
+
+    for (uid, entry) in local {
+        let existing = remote.iter().find(|todo| todo.uid == *uid);
+        let mut entry = entry.clone();
+        if existing.is_some_and(|todo| todo.status == Status::Done) {
+            entry.status = Status::Done;
+        }
+        if existing.is_some_and(|todo| todo.summary == entry.message && todo.status == entry.status && todo.due == entry.due) {
+            continue;
+        }
+
+        let url = match existing {
+            Some(todo) => resolve(&account.url, &todo.href),
+            None => format!("{}/{}.ics", account.url.trim_end_matches('/'), uid)
+        };
+        request(account, "PUT", &url, &["Content-Type: text/calendar; charset=utf-8"], &to_ics(uid, &entry))?;
+    }
+    Ok(remote)
+}
+
+/// Every VTODO in the collection
+pub fn pull(account : &Account) -> io::Result<Vec<RemoteTodo>> {
+    let headers = ["Depth: 1", "Content-Type: application/xml; charset=utf-8"];
+    let response = request(account, "REPORT", &account.url, &headers, QUERY)?;
+    Ok(parse_multistatus(&response))
+}
+
+/// One request to the server, signed in as the account's user
+fn request(account : &Account, method : &str, url : &str, headers : &[&str], body : &str) -> io::Result<String> {
+    let mut options = vec![("user", format!("{}:{}", account.user, account.password))];
+    options.extend(headers.iter().map(|header| ("header", header.to_string())));
+    http::request(method, url, &options, body)
+}
+
+/// `href` of a server response as a full URL, they're usually paths on the server of `base`
+fn resolve(base : &str, href : &str) -> String {
+    if href.starts_with("http://") || href.starts_with("https://") {
+        return href.to_string();
+    }
+    let origin_end = base.find("://").and_then(|scheme| base[scheme + 3..].find('/').map(|path| scheme + 3 + path));
+    let origin = origin_end.map_or(base, |end| &base[..end]);
+    format!("{}/{}", origin, href.trim_start_matches('/'))
+}
+
+/// The tasks in a REPORT response, responses without a VTODO are skipped
+fn parse_multistatus(xml : &str) -> Vec<RemoteTodo> {
+    let mut todos = vec![];
+    for response in elements(xml, "response") {
+        let href = elements(response, "href").first().map(|href| unescape_xml(href.trim()));
+        let data = elements(response, "calendar-data").first().map(|data| unescape_xml(data));
+        if let (Some(href), Some(data)) = (href, data) {
+            todos.extend(parse_vtodo(&data).map(|(uid, summary, status, due)| RemoteTodo { uid, href, summary, status, due }));
+        }
+    }
+    todos
+}
+
+/// Contents of every element called `name`, whatever namespace prefix it has
+fn elements<'a>(xml : &'a str, name : &str) -> Vec<&'a str> {
+    let mut found = vec![];
+    let mut rest = xml;
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start + 1..];
+        let tag_end = match rest.find('>') {
+            Some(end) => end,
+            None => break
+        };
+        let tag = &rest[..tag_end];
+        let qualified = tag.split(|c : char| c.is_whitespace() || c == '/').next().unwrap_or("");
+        let local = qualified.rsplit(':').next().unwrap_or("");
+        if local != name || tag.ends_with('/') || qualified.starts_with('/') {
+            continue;
+        }
+
+        let content = &rest[tag_end + 1..];
+        let closing = format!("</{}>", qualified);
+        if let Some(end) = content.find(&closing) {
+            found.push(&content[..end]);
+            rest = &content[end + closing.len()..];
+        }
+    }
+    found
+}
+
+fn unescape_xml(text : &str) -> String {
+    let text = text.trim();
+    if let Some(data) = text.strip_prefix("<![CDATA[").and_then(|text| text.strip_suffix("]]>")) {
+        return data.to_string();
+    }
+
+    let mut out = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let entity = rest.find(';').map(|end| &rest[1..end]);
+        let decoded = match entity {
+            Some("lt") => Some('<'),
+            Some("gt") => Some('>'),
+            Some("amp") => Some('&'),
+            Some("quot") => Some('"'),
+            Some("apos") => Some('\''),
+            Some(code) if code.starts_with("#x") => u32::from_str_radix(&code[2..], 16).ok().and_then(char::from_u32),
+            Some(code) if code.starts_with('#') => code[1..].parse().ok().and_then(char::from_u32),
+            _ => None
+        };
+        match (decoded, entity) {
+            (Some(c), Some(entity)) => {
+                out.push(c);
+                rest = &rest[entity.len() + 2..];
+            }
+            _ => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// `(uid, summary, status, due)` of the first VTODO in an iCalendar object
+fn parse_vtodo(ics : &str) -> Option<(String, String, Status, Option<date::Date>)> {
+    // Long lines are folded onto the next ones, which start with a space or a tab
+    let unfolded = ics.replace("\r\n", "\n").replace("\n ", "").replace("\n\t", "");
+    let mut in_todo = false;
+    let mut nested = 0;
+    let (mut uid, mut summary, mut status, mut due) = (None, String::new(), Status::Todo, None);
+
+    for line in unfolded.lines() {
+        let (name, value) = match line.split_once(':') {
+            Some((name, value)) => (name.split(';').next().unwrap_or(name).to_ascii_uppercase(), value),
+            None => continue
+        };
+        match (name.as_str(), value) {
+            ("BEGIN", "VTODO") => in_todo = true,
+            ("END", "VTODO") if in_todo => return Some((uid?, summary, status, due)),
+            ("BEGIN", _) if in_todo => nested += 1,
+            ("END", _) if in_todo => nested -= 1,
+            _ if !in_todo || nested > 0 => {}
+            ("UID", value) => uid = Some(value.to_string()),
+            ("SUMMARY", value) => summary = unescape_text(value),
+            ("STATUS", "COMPLETED" | "CANCELLED") => status = Status::Done,
+            ("STATUS", "IN-PROCESS") => status = Status::InProgress,
+            ("DUE", value) => due = parse_ics_date(value),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The day of a `DATE` or `DATE-TIME` value like `20220930` or `20220930T170000Z`
+fn parse_ics_date(value : &str) -> Option<date::Date> {
+    let digits = value.get(..8)?;
+    date::Date::parse(&format!("{}-{}-{}", &digits[..4], &digits[4..6], &digits[6..]))
+}
+
+fn unescape_text(value : &str) -> String {
+    let mut out = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => out.push(' '),
+            Some(escaped) => out.push(escaped),
+            None => {}
+        }
+    }
+    out
+}
+
+fn escape_text(value : &str) -> String {
+    value.replace('\\', "\\\\").replace(';', "\\;").replace(',', "\\,").replace('\n', "\\n")
+}
+
+/// `entry` as an iCalendar object holding one VTODO
+pub fn to_ics(uid : &str, entry : &TodoData) -> String {
+    let status = match entry.status {
+        Status::Todo => "NEEDS-ACTION",
+        Status::InProgress => "IN-PROCESS",
+        Status::Done => "COMPLETED"
+    };
+
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//todo_rust//EN".to_string(),
+        "BEGIN:VTODO".to_string(),
+        format!("UID:{}", uid),
+        format!("DTSTAMP:{}", utc_stamp(date::now())),
+        format!("SUMMARY:{}", escape_text(&entry.message)),
+        format!("STATUS:{}", status),
+    ];
+    if let Some(due) = entry.due {
+        lines.push(format!("DUE;VALUE=DATE:{}", due.format().replace('-', "")));
+    }
+    lines.push("END:VTODO".to_string());
+    lines.push("END:VCALENDAR".to_string());
+
+    lines.iter().map(|line| fold(line)).collect()
+}
+
+/// `20220927T140300Z`
+fn utc_stamp(timestamp : u64) -> String {
+    let day = date::Date::from_days((timestamp / 86_400) as i64);
+    let secs = timestamp % 86_400;
+    format!("{}T{:02}{:02}{:02}Z", day.format().replace('-', ""), secs / 3600, secs % 3600 / 60, secs % 60)
+}
+
+/// Breaks `line` into lines of at most 75 bytes ending in CRLF, continuations start with a space
+fn fold(line : &str) -> String {
+    let mut out = String::new();
+    let mut length = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > 75 {
+            out.push_str("\r\n ");
+            length = 1;
+        }
+        out.push(c);
+        length += c.len_utf8();
+    }
+    out.push_str("\r\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vtodo_round_trips_through_a_report_response() {
+        let entry = TodoData {
+            message : "Call the bank; ask about fees, again".to_string(),
+            status : Status::InProgress,
+            due : date::Date::parse("2022-09-30"),
+            ..TodoData::default()
+        };
+        let ics = to_ics("todo_rust-1", &entry)
+            .replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;");
+        let xml = format!(
+            "<d:multistatus xmlns:d=\"DAV:\"><d:response><d:href>/dav/tasks/todo_rust-1.ics</d:href>\
+             <d:propstat><d:prop><d:getetag>\"1\"</d:getetag><cal:calendar-data>{}</cal:calendar-data>\
+             </d:prop></d:propstat></d:response></d:multistatus>", ics);
+
+        assert_eq!(parse_multistatus(&xml), vec![RemoteTodo {
+            uid : "todo_rust-1".to_string(),
+            href : "/dav/tasks/todo_rust-1.ics".to_string(),
+            summary : entry.message.clone(),
+            status : Status::InProgress,
+            due : entry.due,
+        }]);
+        assert_eq!(resolve("https://cloud.example.com/dav/tasks/", "/dav/tasks/todo_rust-1.ics"),
+            "https://cloud.example.com/dav/tasks/todo_rust-1.ics");
+    }
+
+    #[test]
+    fn folded_lines_stay_short_and_unfold_again() {
+        let entry = TodoData { message : "ü".repeat(60), status : Status::Done, ..TodoData::default() };
+        let ics = to_ics("a", &entry);
+
+        assert!(ics.split("\r\n").all(|line| line.len() <= 75));
+        let (_, summary, status, _) = parse_vtodo(&ics).unwrap();
+        assert_eq!(summary, entry.message);
+        assert_eq!(status, Status::Done);
+    }
+}
@@ -0,0 +1,68 @@
+use std::io;
+
+use todo_core::date;
+use todo_core::file_manager::FileManager;
+use todo_core::Status;
+
+const USAGE : &str = "usage: todo_rust [--data-dir <dir>] [--profile <name>] [add <todo> | list | done <number>]";
+
+/// Whether `args` (without the program name) start with a subcommand
+pub fn is_command(args : &[String]) -> bool {
+    matches!(args.first().map(String::as_str), Some("add" | "list" | "done"))
+}
+
+/// Runs a subcommand against today's list without starting the interface. Returns `None`
+/// when `args` (without the program name) doesn't start with a subcommand.
+pub fn run(file_manager : &FileManager, args : &[String]) -> Option<io::Result<()>> {
+    let result = match args.first().map(String::as_str) {
+        Some("add") => add(file_manager, &args[1..]),
+        Some("list") => list(file_manager),
+        Some("done") => done(file_manager, args.get(1)),
+        _ => return None
+    };
+    Some(result)
+}
+
+fn invalid(message : &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, format!("{}\n{}", message, USAGE))
+}
+
+/// `add buy milk` and `add "buy milk"` both add one todo
+fn add(file_manager : &FileManager, words : &[String]) -> io::Result<()> {
+    let message = words.join(" ");
+    if message.trim().is_empty() {
+        return Err(invalid("Nothing to add"));
+    }
+
+    let mut entries = file_manager.load_today()?;
+    todo_core::add(&mut entries, &message);
+    file_manager.save_today(&entries)?;
+    println!("{}. {}", entries.len(), message.trim());
+    Ok(())
+}
+
+/// Prints today's list numbered the way `done` expects
+fn list(file_manager : &FileManager) -> io::Result<()> {
+    for (index, entry) in file_manager.load_today()?.iter().enumerate() {
+        let marker = match entry.status {
+            Status::Todo => "[ ]",
+            Status::InProgress => "[~]",
+            Status::Done => "[x]"
+        };
+        println!("{:>3}. {}{} {}", index + 1, "  ".repeat(entry.indent), marker, entry.message);
+    }
+    Ok(())
+}
+
+fn done(file_manager : &FileManager, number : Option<&String>) -> io::Result<()> {
+    let mut entries = file_manager.load_today()?;
+    let index = match number.and_then(|number| number.parse::<usize>().ok()) {
+        Some(number) if (1..=entries.len()).contains(&number) => number - 1,
+        _ => return Err(invalid(&format!("Expected the number of a todo between 1 and {}", entries.len())))
+    };
+
+    todo_core::set_status(&mut entries, index, Status::Done, date::Date::today());
+    file_manager.save_today(&entries)?;
+    println!("Done: {}", entries[index].message);
+    Ok(())
+}
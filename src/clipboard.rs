@@ -0,0 +1,67 @@
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+/// Clipboard tools tried in order, the first one that is installed is used
+const COPY_COMMANDS : [(&str, &[&str]); 4] = [
+    ("wl-copy", &[]),
+    ("xclip", &["-selection", "clipboard"]),
+    ("xsel", &["--clipboard", "--input"]),
+    ("pbcopy", &[]),
+];
+
+const PASTE_COMMANDS : [(&str, &[&str]); 4] = [
+    ("wl-paste", &["--no-newline"]),
+    ("xclip", &["-selection", "clipboard", "-o"]),
+    ("xsel", &["--clipboard", "--output"]),
+    ("pbpaste", &[]),
+];
+
+fn no_tool() -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, "no clipboard tool found (wl-copy, xclip, xsel or pbcopy)")
+}
+
+/// Puts `text` on the system clipboard
+pub fn copy(text : &str) -> io::Result<()> {
+    for (program, args) in COPY_COMMANDS {
+        let spawned = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+        let mut child = match spawned {
+            Ok(child) => child,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(err)
+        };
+
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(text.as_bytes())?;
+        }
+        let status = child.wait()?;
+        if !status.success() {
+            return Err(io::Error::other(format!("`{}` exited with {}", program, status)));
+        }
+        return Ok(());
+    }
+
+    Err(no_tool())
+}
+
+/// Text on the system clipboard
+pub fn paste() -> io::Result<String> {
+    for (program, args) in PASTE_COMMANDS {
+        let output = match Command::new(program).args(args).stdin(Stdio::null()).stderr(Stdio::null()).output() {
+            Ok(output) => output,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(err)
+        };
+
+        if !output.status.success() {
+            return Err(io::Error::other(format!("`{}` exited with {}", program, output.status)));
+        }
+        return Ok(String::from_utf8_lossy(&output.stdout).into_owned());
+    }
+
+    Err(no_tool())
+}
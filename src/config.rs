@@ -0,0 +1,458 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+
+use crate::caldav;
+use crate::keymap::KeyMap;
+use crate::todoist;
+
+/// Name of the profile using the top level settings and the data directory itself
+pub const DEFAULT_PROFILE : &str = "default";
+
+/// Percentages of the width `details_width` and `split_width` can be set to
+pub const PANE_WIDTHS : RangeInclusive<u16> = 20..=80;
+
+/// What to do with today's file when the app is started again later that day
+#[derive(Copy, Clone, PartialEq)]
+pub enum StartupMode {
+    /// Keep working on today's existing list
+    Resume,
+    /// Move today's existing file into the archive and start over. The unfinished todo's
+    /// written before today stay: the ones carried over from earlier days and the ones
+    /// planned or snoozed into today. Only what was written today is cleared away.
+    Fresh,
+}
+
+/// How the list files are written
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum StorageFormat {
+    /// Checklist lines with the extra fields after a tab
+    Todo,
+    /// The todo.txt format, fields it has no syntax for are kept as `key:value`
+    TodoTxt,
+}
+
+/// User settings, read from `~/.config/todo_rust/config.toml`.
+///
+/// Only a small subset of TOML is understood: `[section]` headers, `key = value` pairs
+/// (strings, integers and booleans) and `#` comments. Unknown keys are ignored.
+#[derive(Clone)]
+pub struct Config {
+    /// Profile the settings were read for, the keys of its `[profile.<name>]` section
+    /// (and `[profile.<name>.caldav]` and so on) replace the top level ones
+    pub profile : String,
+    /// Every profile in the config, starting with the default one
+    pub profiles : Vec<String>,
+    /// Data directory of the profile, `data_dir` in its section. Profiles without one keep
+    /// their lists in `profiles/<name>` inside the data directory.
+    pub data_dir : Option<String>,
+    /// Maximum number of characters a single todo may contain, disabled when `None`
+    pub max_todo_length : Option<usize>,
+    /// Shell command the active list is piped through with `!`, e.g. `sort` or `fzf -m`
+    pub filter_command : Option<String>,
+    /// Length of a focus timer session started with `P`
+    pub focus_minutes : u64,
+    /// Whether a restart on the same day resumes or replaces today's list
+    pub startup : StartupMode,
+    /// Format of the list files, `storage = "todo.txt"` for todo.txt tooling
+    pub storage : StorageFormat,
+    /// Encrypt the list files with a passphrase asked for at startup
+    pub encrypt : bool,
+    /// Append a one-line summary of the daily list to `summary.log` when quitting
+    pub summary_on_quit : bool,
+    /// Draw on the alternate screen (default) or inline so the last frame stays in the scrollback
+    pub alternate_screen : bool,
+    /// While editing, Tab at the start of a todo indents it and Tab anywhere else inserts
+    /// spaces. With this turned off Tab always inserts spaces. Shift+Tab always outdents.
+    pub tab_indents : bool,
+    /// Day files older than this many days are moved into `archive/` on startup
+    pub archive_after_days : Option<u64>,
+    /// Hours `start..end` (0-24) considered work time, editing outside them shows a warning
+    pub work_hours : Option<(u32, u32)>,
+    /// Blank lines rendered after every entry of a list
+    pub item_spacing : usize,
+    /// Only draw ASCII characters, for terminals without Unicode support
+    pub ascii : bool,
+    /// Show how long ago every open todo was written, like `3d old`
+    pub show_age : bool,
+    /// Where the input box goes, `input_position = "bottom"` puts it below the list
+    pub input_at_bottom : bool,
+    /// Show the line of key hints above the input box
+    pub help_bar : bool,
+    /// Percentage of the width the notes pane takes next to the list
+    pub details_width : u16,
+    /// Percentage of the width the daily list takes in the split view
+    pub split_width : u16,
+    /// File kept up to date with the open daily todo's for status bars to read, top todo
+    /// first. Not written with `encrypt`, as it would hold the todo's in plain text.
+    pub status_file : Option<String>,
+    /// Normal mode key bindings, changed in the `[keys]` section, e.g. `quit = "x"`, with
+    /// `scheme = "vim"` adding hjkl, i/a, dd, x and gg/G. Keys like `#` have to be quoted,
+    /// otherwise they start a comment.
+    pub keymap : KeyMap,
+    /// Ask y/n before removing a todo
+    pub confirm_remove : bool,
+    /// Show a desktop notification when an open todo is due
+    pub notifications : bool,
+    /// How often the screen is refreshed while no keys are pressed, in milliseconds
+    pub tick_rate_ms : u64,
+    /// Task list synced with `S`, set with `url`, `user` and `password` in the `[caldav]` section
+    pub caldav : Option<caldav::Account>,
+    /// Project the long term list is synced with on `S`, set with `token` and `project_id`
+    /// in the `[todoist]` section
+    pub todoist : Option<todoist::Account>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            profile : DEFAULT_PROFILE.to_string(),
+            profiles : vec![DEFAULT_PROFILE.to_string()],
+            data_dir : None,
+            max_todo_length : None,
+            filter_command : None,
+            focus_minutes : 25,
+            startup : StartupMode::Resume,
+            storage : StorageFormat::Todo,
+            encrypt : false,
+            summary_on_quit : false,
+            alternate_screen : true,
+            tab_indents : true,
+            archive_after_days : None,
+            work_hours : None,
+            item_spacing : 0,
+            ascii : false,
+            show_age : false,
+            input_at_bottom : false,
+            help_bar : true,
+            details_width : 40,
+            split_width : 50,
+            status_file : None,
+            keymap : KeyMap::default(),
+            confirm_remove : true,
+            notifications : true,
+            tick_rate_ms : 250,
+            caldav : None,
+            todoist : None,
+        }
+    }
+}
+
+impl Config {
+    pub fn load(profile : &str) -> Self {
+        let contents = config_path().and_then(|path| fs::read_to_string(path).ok()).unwrap_or_default();
+        Self::parse(&contents, profile)
+    }
+
+    pub fn parse(contents : &str, profile : &str) -> Self {
+        let mut values = parse_values(contents);
+        let mut config = Self::default();
+
+        for name in profile_names(contents) {
+            if !config.profiles.contains(&name) {
+                config.profiles.push(name);
+            }
+        }
+        if profile != DEFAULT_PROFILE {
+            let prefix = format!("profile.{}.", profile);
+            let overrides : Vec<(String, String)> = values.iter()
+                .filter_map(|(key, value)| Some((key.strip_prefix(&prefix)?.to_string(), value.clone())))
+                .collect();
+            config.data_dir = overrides.iter().find(|(key, _)| key == "data_dir").map(|(_, dir)| dir.clone()).filter(|dir| !dir.is_empty());
+            values.extend(overrides);
+        }
+        config.profile = profile.to_string();
+
+        if let Some(limit) = values.get("max_todo_length").and_then(|v| v.parse::<usize>().ok()) {
+            config.max_todo_length = if limit == 0 { None } else { Some(limit) };
+        }
+
+        if let Some(command) = values.get("filter_command").filter(|v| !v.is_empty()) {
+            config.filter_command = Some(command.clone());
+        }
+
+        if let Some(minutes) = values.get("focus_minutes").and_then(|v| v.parse::<u64>().ok()).filter(|m| *m > 0) {
+            config.focus_minutes = minutes;
+        }
+
+        match values.get("startup").map(|v| v.as_str()) {
+            Some("fresh") => config.startup = StartupMode::Fresh,
+            Some("resume") => config.startup = StartupMode::Resume,
+            _ => {}
+        }
+
+        match values.get("storage").map(|v| v.as_str()) {
+            Some("todo.txt") => config.storage = StorageFormat::TodoTxt,
+            Some("todo") => config.storage = StorageFormat::Todo,
+            _ => {}
+        }
+
+        if let Some(enabled) = values.get("encrypt").and_then(|v| parse_bool(v)) {
+            config.encrypt = enabled;
+        }
+
+        if let Some(enabled) = values.get("summary_on_quit").and_then(|v| parse_bool(v)) {
+            config.summary_on_quit = enabled;
+        }
+
+        if let Some(enabled) = values.get("alternate_screen").and_then(|v| parse_bool(v)) {
+            config.alternate_screen = enabled;
+        }
+
+        if let Some(enabled) = values.get("tab_indents").and_then(|v| parse_bool(v)) {
+            config.tab_indents = enabled;
+        }
+
+        if let Some(days) = values.get("archive_after_days").and_then(|v| v.parse::<u64>().ok()) {
+            config.archive_after_days = if days == 0 { None } else { Some(days) };
+        }
+
+        let start = values.get("work_start_hour").and_then(|v| v.parse::<u32>().ok());
+        let end = values.get("work_end_hour").and_then(|v| v.parse::<u32>().ok());
+        if let (Some(start), Some(end)) = (start, end) {
+            if start < end && end <= 24 {
+                config.work_hours = Some((start, end));
+            }
+        }
+
+        if let Some(spacing) = values.get("item_spacing").and_then(|v| v.parse::<usize>().ok()) {
+            config.item_spacing = spacing;
+        }
+
+        if let Some(enabled) = values.get("ascii").and_then(|v| parse_bool(v)) {
+            config.ascii = enabled;
+        }
+
+        if let Some(enabled) = values.get("show_age").and_then(|v| parse_bool(v)) {
+            config.show_age = enabled;
+        }
+
+        match values.get("input_position").map(|v| v.as_str()) {
+            Some("bottom") => config.input_at_bottom = true,
+            Some("top") => config.input_at_bottom = false,
+            _ => {}
+        }
+
+        if let Some(enabled) = values.get("help_bar").and_then(|v| parse_bool(v)) {
+            config.help_bar = enabled;
+        }
+
+        if let Some(width) = values.get("details_width").and_then(|v| v.parse::<u16>().ok()).filter(|w| PANE_WIDTHS.contains(w)) {
+            config.details_width = width;
+        }
+
+        if let Some(width) = values.get("split_width").and_then(|v| v.parse::<u16>().ok()).filter(|w| PANE_WIDTHS.contains(w)) {
+            config.split_width = width;
+        }
+
+        if let Some(path) = values.get("status_file").filter(|v| !v.is_empty()) {
+            config.status_file = Some(path.clone());
+        }
+
+        if let Some(enabled) = values.get("confirm_remove").and_then(|v| parse_bool(v)) {
+            config.confirm_remove = enabled;
+        }
+
+        if let Some(enabled) = values.get("notifications").and_then(|v| parse_bool(v)) {
+            config.notifications = enabled;
+        }
+
+        if let Some(ms) = values.get("tick_rate_ms").and_then(|v| v.parse::<u64>().ok()).filter(|ms| *ms > 0) {
+            config.tick_rate_ms = ms;
+        }
+
+        if let Some(url) = values.get("caldav.url").filter(|v| !v.is_empty()) {
+            config.caldav = Some(caldav::Account {
+                url : url.clone(),
+                user : values.get("caldav.user").cloned().unwrap_or_default(),
+                password : values.get("caldav.password").cloned().unwrap_or_default(),
+            });
+        }
+
+        let token = values.get("todoist.token").filter(|v| !v.is_empty());
+        let project_id = values.get("todoist.project_id").filter(|v| !v.is_empty());
+        if let (Some(token), Some(project_id)) = (token, project_id) {
+            config.todoist = Some(todoist::Account { token : token.clone(), project_id : project_id.clone() });
+        }
+
+        config.keymap = KeyMap::from_config(&values);
+
+        config
+    }
+}
+
+impl Config {
+    /// Writes the layout settings into the config file, in the section of the profile unless
+    /// it's the default one. Everything else in the file is kept as it is.
+    pub fn save_layout(&self) -> io::Result<()> {
+        let path = config_path().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no home directory to keep the config in"))?;
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(err)
+        };
+
+        let section = (self.profile != DEFAULT_PROFILE).then(|| format!("profile.{}", self.profile));
+        let values = [
+            ("input_position", format!("\"{}\"", if self.input_at_bottom { "bottom" } else { "top" })),
+            ("help_bar", self.help_bar.to_string()),
+            ("details_width", self.details_width.to_string()),
+            ("split_width", self.split_width.to_string()),
+        ];
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        // Written next to it and renamed over it, so a crash can't leave half a config behind
+        let mut temporary = path.as_os_str().to_owned();
+        temporary.push(".tmp");
+        fs::write(&temporary, set_values(&contents, section.as_deref(), &values))?;
+        fs::rename(&temporary, &path)
+    }
+}
+
+/// `contents` with `key = value` for each of `values` in `section` (the top level for
+/// `None`), replacing the line of a key that's already there but keeping its comment
+fn set_values(contents : &str, section : Option<&str>, values : &[(&str, String)]) -> String {
+    let mut lines : Vec<String> = contents.lines().map(str::to_string).collect();
+    let header = |line : &str| {
+        let line = strip_comment(line).trim();
+        (line.starts_with('[') && line.ends_with(']')).then(|| line[1..line.len() - 1].trim().to_string())
+    };
+
+    // Lines `start..end` are the section's, after its header
+    let (start, end) = match section {
+        None => (0, lines.iter().position(|line| header(line).is_some()).unwrap_or(lines.len())),
+        Some(name) => match lines.iter().position(|line| header(line).as_deref() == Some(name)) {
+            Some(at) => (at + 1, lines[at + 1..].iter().position(|line| header(line).is_some()).map_or(lines.len(), |next| at + 1 + next)),
+            None => {
+                if lines.last().is_some_and(|line| !line.trim().is_empty()) {
+                    lines.push(String::new());
+                }
+                lines.push(format!("[{}]", name));
+                (lines.len(), lines.len())
+            }
+        }
+    };
+
+    let mut missing = vec![];
+    for (key, value) in values {
+        let existing = (start..end).find(|index| {
+            strip_comment(&lines[*index]).split_once('=').is_some_and(|(name, _)| name.trim() == *key)
+        });
+        match existing {
+            Some(index) => {
+                let line = &lines[index];
+                let code = strip_comment(line);
+                let comment = if code.len() < line.len() { &line[code.trim_end().len()..] } else { "" };
+                lines[index] = format!("{} = {}{}", key, value, comment);
+            }
+            None => missing.push(format!("{} = {}", key, value))
+        }
+    }
+    // New keys go below the last one of the section, before the blank lines ending it
+    let insert_at = start + lines[start..end].iter().rposition(|line| !line.trim().is_empty()).map_or(0, |last| last + 1);
+    lines.splice(insert_at..insert_at, missing);
+
+    let mut contents = lines.join("\n");
+    contents.push('\n');
+    contents
+}
+
+fn config_path() -> Option<PathBuf> {
+    let config_home = match env::var("XDG_CONFIG_HOME") {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var("HOME").ok()?).join(".config"),
+    };
+
+    Some(config_home.join("todo_rust").join("config.toml"))
+}
+
+/// Names of the `[profile.<name>]` sections, in the order they first appear
+fn profile_names(contents : &str) -> Vec<String> {
+    let mut names : Vec<String> = vec![];
+    for line in contents.lines() {
+        let line = strip_comment(line).trim();
+        let name = line.strip_prefix("[profile.")
+            .and_then(|rest| rest.strip_suffix(']'))
+            .and_then(|rest| rest.split('.').next())
+            .map(str::trim)
+            // The name becomes a directory, so anything like a path is left out
+            .filter(|name| !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_'));
+        if let Some(name) = name.filter(|name| !names.iter().any(|known| known == name)) {
+            names.push(name.to_string());
+        }
+    }
+    names
+}
+
+/// Flattens the file into `section.key -> value`, keys outside a section are stored as-is.
+fn parse_values(contents : &str) -> HashMap<String, String> {
+    let mut values = HashMap::new();
+    let mut section = String::new();
+
+    for line in contents.lines() {
+        let line = strip_comment(line).trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        if line.starts_with('[') && line.ends_with(']') {
+            section = line[1..line.len() - 1].trim().to_string();
+            continue;
+        }
+
+        if let Some((key, value)) = line.split_once('=') {
+            let key = key.trim();
+            let value = value.trim().trim_matches('"').to_string();
+            let full_key = if section.is_empty() { key.to_string() } else { format!("{}.{}", section, key) };
+            values.insert(full_key, value);
+        }
+    }
+
+    values
+}
+
+fn parse_bool(value : &str) -> Option<bool> {
+    match value {
+        "true" => Some(true),
+        "false" => Some(false),
+        _ => None
+    }
+}
+
+fn strip_comment(line : &str) -> &str {
+    let mut in_string = false;
+    for (index, c) in line.char_indices() {
+        match c {
+            '"' => in_string = !in_string,
+            '#' if !in_string => return &line[..index],
+            _ => {}
+        }
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layout_values_replace_their_lines_and_keep_the_rest() {
+        let contents = "# mine\nfocus_minutes = 30\nhelp_bar = true # hints\n\n[keys]\nquit = \"x\"\n";
+        let values = [("help_bar", "false".to_string()), ("split_width", "60".to_string())];
+
+        let top = set_values(contents, None, &values);
+        assert_eq!(top, "# mine\nfocus_minutes = 30\nhelp_bar = false # hints\nsplit_width = 60\n\n[keys]\nquit = \"x\"\n");
+        let config = Config::parse(&top, DEFAULT_PROFILE);
+        assert!(!config.help_bar && config.split_width == 60 && config.focus_minutes == 30);
+
+        let profile = set_values(contents, Some("profile.work"), &values);
+        assert!(profile.ends_with("quit = \"x\"\n\n[profile.work]\nhelp_bar = false\nsplit_width = 60\n"));
+        assert!(Config::parse(&profile, DEFAULT_PROFILE).help_bar);
+        assert!(!Config::parse(&profile, "work").help_bar);
+    }
+}
@@ -0,0 +1,220 @@
+use std::cmp;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Seconds since the unix epoch
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+/// A calendar day in the local timezone
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct Date {
+    pub year : i64,
+    pub month : u32,
+    pub day : u32,
+}
+
+impl Date {
+    pub fn today() -> Self {
+        DateTime::now().date
+    }
+
+    /// Day `days` after 1970-01-01
+    pub fn from_days(days : i64) -> Self {
+        let (year, month, day) = civil_from_days(days);
+        Date { year, month, day }
+    }
+
+    /// Number of days since 1970-01-01
+    pub fn to_days(self) -> i64 {
+        days_from_civil(self.year, self.month, self.day)
+    }
+
+    pub fn add_days(self, days : i64) -> Self {
+        Self::from_days(self.to_days() + days)
+    }
+
+    /// The same day `months` months later (or earlier when negative), moved back to the
+    /// last day of the month when it's shorter
+    pub fn add_months(self, months : i64) -> Self {
+        let index = self.year * 12 + self.month as i64 - 1 + months;
+        let (year, month) = (index.div_euclid(12), index.rem_euclid(12) as u32 + 1);
+        Date { year, month, day : cmp::min(self.day, days_in_month(year, month)) }
+    }
+
+    /// 0 for Monday through 6 for Sunday
+    pub fn weekday(self) -> u32 {
+        // 1970-01-01 was a Thursday
+        (self.to_days() + 3).rem_euclid(7) as u32
+    }
+
+    /// Parses a file stem like `2022_09_27`
+    pub fn parse_file_stem(stem : &str) -> Option<Self> {
+        Self::parse_separated(stem, '_')
+    }
+
+    /// Parses `2022-09-27`, the format written by `format`
+    pub fn parse(text : &str) -> Option<Self> {
+        Self::parse_separated(text, '-')
+    }
+
+    /// Parses a date typed by the user relative to `today`: `today`, `tomorrow`,
+    /// `+3` for three days from now, a weekday like `monday`, `mon` or `next monday` for
+    /// the first one after today, or a full `2022-09-27`
+    pub fn parse_relative(text : &str, today : Date) -> Option<Self> {
+        const WEEKDAYS : [&str; 7] = ["monday", "tuesday", "wednesday", "thursday", "friday", "saturday", "sunday"];
+        let name = text.strip_prefix("next ").unwrap_or(text).to_ascii_lowercase();
+        if let Some(weekday) = WEEKDAYS.iter().position(|day| *day == name || day[..3] == name) {
+            let days_ahead = (weekday as i64 - today.weekday() as i64 + 6).rem_euclid(7) + 1;
+            return Some(today.add_days(days_ahead));
+        }
+
+        match text {
+            "today" => Some(today),
+            "tomorrow" => Some(today.add_days(1)),
+            _ => match text.strip_prefix('+') {
+                Some(days) => days.parse::<i64>().ok().map(|days| today.add_days(days)),
+                None => Self::parse(text)
+            }
+        }
+    }
+
+    fn parse_separated(text : &str, separator : char) -> Option<Self> {
+        let mut parts = text.split(separator);
+        let year = parts.next()?.parse::<i64>().ok()?;
+        let month = parts.next()?.parse::<u32>().ok()?;
+        let day = parts.next()?.parse::<u32>().ok()?;
+        if parts.next().is_some() || !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+            return None;
+        }
+
+        let date = Date { year, month, day };
+        // Reject days that don't exist, like 2022_02_30
+        if Self::from_days(date.to_days()) != date {
+            return None;
+        }
+        Some(date)
+    }
+
+    /// `2022_09_27`, the name of the day's `.todo` file
+    pub fn file_stem(&self) -> String {
+        format!("{:04}_{:02}_{:02}", self.year, self.month, self.day)
+    }
+
+    /// `2022-09-27`
+    pub fn format(&self) -> String {
+        format!("{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+/// Number of days in `month` of `year`
+pub fn days_in_month(year : i64, month : u32) -> u32 {
+    let (next_year, next_month) = if month == 12 { (year + 1, 1) } else { (year, month + 1) };
+    (days_from_civil(next_year, next_month, 1) - days_from_civil(year, month, 1)) as u32
+}
+
+/// `1h 05m`, `25m` or `<1m`
+pub fn format_duration(secs : u32) -> String {
+    let minutes = secs / 60;
+    match (minutes / 60, minutes % 60) {
+        (0, 0) => "<1m".to_string(),
+        (0, minutes) => format!("{}m", minutes),
+        (hours, minutes) => format!("{}h {:02}m", hours, minutes)
+    }
+}
+
+/// Seconds of a duration like `1h 05m` as written by `format_duration`, `1h30m`, `90m`, `2h`
+/// or a bare number of minutes
+pub fn parse_duration(text : &str) -> Option<u32> {
+    let text : String = text.split_whitespace().collect();
+    if let Ok(minutes) = text.parse::<u32>() {
+        return minutes.checked_mul(60);
+    }
+    let (hours, rest) = match text.split_once('h') {
+        Some((hours, rest)) => (hours.parse::<u32>().ok()?, rest),
+        None => (0, text.as_str())
+    };
+    let minutes = match rest {
+        "" if !text.is_empty() => 0,
+        _ => rest.strip_suffix('m')?.parse::<u32>().ok()?
+    };
+    hours.checked_mul(60)?.checked_add(minutes)?.checked_mul(60)
+}
+
+/// A point in time broken down in the local timezone
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct DateTime {
+    pub date : Date,
+    pub hour : u32,
+    pub minute : u32,
+}
+
+impl DateTime {
+    pub fn now() -> Self {
+        Self::from_timestamp(now())
+    }
+
+    pub fn from_timestamp(timestamp : u64) -> Self {
+        let local = timestamp as i64 + local_offset_secs(timestamp);
+        let secs_of_day = local.rem_euclid(86_400);
+
+        DateTime {
+            date : Date::from_days(local.div_euclid(86_400)),
+            hour : (secs_of_day / 3600) as u32,
+            minute : (secs_of_day % 3600 / 60) as u32,
+        }
+    }
+
+    /// `2022-09-27 14:03`
+    pub fn format(&self) -> String {
+        format!("{} {:02}:{:02}", self.date.format(), self.hour, self.minute)
+    }
+}
+
+/// Offset of the local timezone from UTC at `timestamp`
+#[cfg(unix)]
+fn local_offset_secs(timestamp : u64) -> i64 {
+    let time = timestamp as libc::time_t;
+    let mut tm : libc::tm = unsafe { std::mem::zeroed() };
+    let result = unsafe { libc::localtime_r(&time, &mut tm) };
+    if result.is_null() {
+        return 0;
+    }
+    tm.tm_gmtoff as i64
+}
+
+#[cfg(not(unix))]
+fn local_offset_secs(_timestamp : u64) -> i64 {
+    0
+}
+
+/// Converts days since 1970-01-01 into a (year, month, day) triple.
+/// Howard Hinnant's `civil_from_days` algorithm.
+fn civil_from_days(days : i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (year, month, day)
+}
+
+/// Inverse of `civil_from_days`
+fn days_from_civil(year : i64, month : u32, day : u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let month = month as i64;
+    let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+
+    era * 146_097 + doe - 719_468
+}
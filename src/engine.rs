@@ -0,0 +1,2569 @@
+//! A todo app without the interface: the open lists, the entry selected in them, the line
+//! being typed, undo and saving. Every operation a frontend binds to a key is a method of
+//! `Engine`, the frontend only draws it and feeds it keys.
+//!
+//! A tool that only wants to change the lists opens an engine on a data directory and
+//! works on the lists by role and index, no cursor or terminal needed:
+//!
+//! ```no_run
+//! use todo_core::engine::Engine;
+//! use todo_core::lists::ListRole;
+//!
+//! let mut engine = Engine::open("/home/me/.todo_rust")?;
+//! let daily = engine.daily_role();
+//! let index = engine.add(daily, "Buy milk");
+//! engine.toggle(daily, index);
+//! engine.move_todo(daily, index, 0);
+//! for todo in engine.list(ListRole::LongTerm) {
+//!     println!("{}", todo.message);
+//! }
+//! engine.remove(daily, 0);
+//! engine.save()?;
+//! # Ok::<(), todo_core::file_manager::Error>(())
+//! ```
+
+use std::cmp;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::config::Config;
+use crate::file_manager::{self, FileManager};
+use crate::keymap::Action;
+use crate::lists::{self, built_in_lists, write_lists, ListRole, ListView, SaveStatus, SaveWorker, TodoList};
+use crate::sort::{self, SortKey};
+use crate::sync::{self, Synced};
+use crate::undo::{self, Snapshot};
+use crate::{child_count, date, is_folded_away, progress, sort_by_priority, tags, text, Recurrence, Status, TodoData};
+
+/// Number of spaces a Tab inserts while editing
+const TAB_WIDTH : usize = 4;
+/// Quiet time after the last change before the lists are written to disk
+pub const AUTOSAVE_DELAY : Duration = Duration::from_millis(500);
+/// How long "saved" stays in the status bar after a write
+pub const SAVED_SHOWN_FOR : Duration = Duration::from_secs(2);
+
+pub enum MoveCursorOperation {
+    Right,
+    Left,
+    Up,
+    Down,
+    /// In front of the first character of the line
+    LineStart,
+    /// Behind the last character while editing, on it otherwise
+    LineEnd,
+}
+
+/// The open lists and everything done to them
+pub struct Engine {
+    /// Current value of the input box
+    pub input : String,
+    /// An entry is being typed into `input`, `new_line` writes it back
+    pub editing : bool,
+    /// A search is being typed into `search`, the list only shows its matches meanwhile
+    pub searching : bool,
+    /// Every open list in tab bar order, the built-in lists come first
+    pub lists : Vec<TodoList>,
+    /// Index into `lists` of the list being shown and written to
+    pub target_list : usize,
+    /// Id given to the next list that is added
+    next_list_id : usize,
+    pub target_row : i32,
+    pub target_column : i32,
+    /// Index of the first list entry that is rendered, updated while drawing
+    pub list_offset : usize,
+    /// User settings loaded on startup
+    pub config : Config,
+    /// One-off feedback shown in place of the help line until the next key press
+    pub status_message : Option<String>,
+    /// Only entries carrying this (lowercased) tag are shown when set
+    pub tag_filter : Option<String>,
+    /// Last search query, `n`/`N` jump between its matches
+    pub search : Option<String>,
+    history : undo::History,
+    /// Entry currently being typed into, a run of typing on one entry is a single undo step
+    typing_row : Option<(usize, i32)>,
+    /// Time of the latest change that hasn't been written to disk yet
+    pub unsaved_since : Option<Instant>,
+    pub save_status : SaveStatus,
+    /// Entries copied or cut last, pasted back with their status and priority
+    pub register : Vec<TodoData>,
+    /// The register was cut, the next paste moves its entries back with their ids
+    register_cut : bool,
+    /// Row `v` was pressed on, the entries from there to the cursor are marked
+    pub visual_anchor : Option<usize>,
+    /// Autosaves run on this thread once the interface is up, without one they're
+    /// written right away
+    pub saver : Option<SaveWorker>,
+    /// Some list couldn't be read at startup. Saving is turned off so the file isn't
+    /// overwritten with the part that did load.
+    pub load_failed : bool,
+    pub file_manager : FileManager
+}
+
+impl Default for Engine {
+    fn default() -> Engine {
+        Engine::new(Config::default(), FileManager::default())
+    }
+}
+
+impl Engine {
+    /// An engine with the built-in lists of today, still empty, see `load_lists`
+    pub fn new(config : Config, file_manager : FileManager) -> Engine {
+        let mut engine = Engine {
+            input : String::new(),
+            editing : false,
+            searching : false,
+            lists : vec![],
+            target_list : 0,
+            next_list_id : 0,
+            target_row : 0,
+            target_column : 0,
+            list_offset : 0,
+            config,
+            status_message : None,
+            tag_filter : None,
+            search : None,
+            history : undo::History::default(),
+            typing_row : None,
+            unsaved_since : None,
+            save_status : SaveStatus::Clean,
+            register : vec![],
+            register_cut : false,
+            visual_anchor : None,
+            saver : None,
+            load_failed : false,
+            file_manager
+        };
+
+        for (name, role) in built_in_lists(date::Date::today()) {
+            engine.add_list(name, role, vec![]);
+        }
+        engine
+    }
+
+    /// Opens the lists in `data_dir` with the default settings the way the app starts: the
+    /// directory is created when it's missing and yesterday's open todo's roll over
+    pub fn open(data_dir : impl Into<PathBuf>) -> file_manager::Result<Engine> {
+        let config = Config::default();
+        let file_manager = FileManager::new(data_dir.into());
+        file_manager.initialize(&config)?;
+        let mut engine = Engine::new(config, file_manager);
+        engine.load_lists()?;
+        Ok(engine)
+    }
+
+    /// Entries of the built-in list with `role`, without the blank entry a frontend types into
+    pub fn list(&self, role : ListRole) -> &[TodoData] {
+        let entries = self.entries(role);
+        match entries.last() {
+            Some(last) if last.message.is_empty() => &entries[..entries.len() - 1],
+            _ => entries
+        }
+    }
+
+    /// Adds a todo reading `message` at the end of the list with `role`, returns its index
+    pub fn add(&mut self, role : ListRole, message : &str) -> usize {
+        let index = self.save_snapshot_of(role);
+        let entry = TodoData { message : message.trim().to_string(), created_at : Some(date::now()), ..TodoData::default() };
+        lists::insert_before_blank(&mut self.lists[index].entries, entry);
+        self.list(role).len() - 1
+    }
+
+    /// Takes the entry at `index` out of the list with `role`, see `todo_core::remove`
+    pub fn remove(&mut self, role : ListRole, index : usize) -> TodoData {
+        let list = self.save_snapshot_of(role);
+        let removed = crate::remove(&mut self.lists[list].entries, index);
+        self.clamp_row();
+        self.clamp_column();
+        removed
+    }
+
+    /// Flips the entry at `index` of the list with `role` between done and not done, see
+    /// `todo_core::toggle`
+    pub fn toggle(&mut self, role : ListRole, index : usize) {
+        let list = self.save_snapshot_of(role);
+        crate::toggle(&mut self.lists[list].entries, index, date::Date::today());
+    }
+
+    /// Moves the entry at `from` of the list with `role` to `to`, the ones in between shift
+    /// over by one
+    pub fn move_todo(&mut self, role : ListRole, from : usize, to : usize) {
+        let list = self.save_snapshot_of(role);
+        let entry = self.lists[list].entries.remove(from);
+        self.lists[list].entries.insert(to, entry);
+    }
+
+    /// Writes every list to the data directory now, like `save_lists`, and drops the
+    /// pending autosave
+    pub fn save(&mut self) -> file_manager::Result<()> {
+        self.save_lists()?;
+        self.unsaved_since = None;
+        Ok(())
+    }
+
+    pub fn active_list(&self) -> &TodoList {
+        &self.lists[self.target_list]
+    }
+
+    pub fn get_messages(&self) -> Vec<TodoData> {
+        self.active_list().entries.clone()
+    }
+
+    pub fn get_messages_mut(&mut self) -> &mut Vec<TodoData> {
+        &mut self.lists[self.target_list].entries
+    }
+
+    /// Index of the built-in list with `role`
+    fn role_index(&self, role : ListRole) -> usize {
+        lists::role_index(&self.lists, role)
+    }
+
+    /// Day the daily list belongs to, today unless the date changed since it was loaded
+    fn daily_date(&self) -> date::Date {
+        lists::daily_date(&self.lists)
+    }
+
+    pub fn daily_role(&self) -> ListRole {
+        ListRole::Daily(self.daily_date())
+    }
+
+    /// Entries of the built-in list with `role`
+    pub fn entries(&self, role : ListRole) -> &Vec<TodoData> {
+        &self.lists[self.role_index(role)].entries
+    }
+
+    pub fn entries_mut(&mut self, role : ListRole) -> &mut Vec<TodoData> {
+        let index = self.role_index(role);
+        &mut self.lists[index].entries
+    }
+
+    pub fn list_index(&self, id : usize) -> Option<usize> {
+        lists::list_index(&self.lists, id)
+    }
+
+    /// Appends a list to the tab bar and returns its index. Every list ends with a blank
+    /// entry to type into.
+    fn add_list(&mut self, name : &str, role : ListRole, mut entries : Vec<TodoData>) -> usize {
+        entries.push(TodoData::default());
+        self.lists.push(TodoList {
+            id : self.next_list_id,
+            name : name.to_string(),
+            role,
+            entries,
+            view : ListView::default()
+        });
+        self.next_list_id += 1;
+        self.lists.len() - 1
+    }
+
+    /// Whether the active list is the journal, an append-only log instead of todo's
+    pub fn is_journal(&self) -> bool {
+        self.active_list().role == ListRole::Journal
+    }
+
+    /// Committed journal entries can't be edited, removed or checked off
+    pub fn is_current_entry_locked(&self) -> bool {
+        self.is_journal() && self.get_messages()[self.target_row as usize].created_at.is_some()
+    }
+
+    /// Loads the selected entry into the line editor. Typing only changes the editor's copy,
+    /// `new_line` writes it back and Esc (`cancel_edit`) throws it away.
+    pub fn start_editing(&mut self) {
+        self.typing_row = None;
+        // A journal only ever grows at the end
+        if self.is_journal() {
+            self.target_row = self.get_messages().len() as i32 - 1;
+        }
+
+        self.editing = true;
+        self.input = self.get_current_message();
+        self.clamp_column();
+    }
+
+    /// Writes the line editor's text into the entry being edited, as one undo step
+    pub fn commit_edit(&mut self) {
+        let target_index = self.target_row as usize;
+        if self.get_messages()[target_index].message == self.input || self.is_current_entry_locked() {
+            return;
+        }
+
+        self.save_typing_snapshot();
+        let input = self.input.clone();
+        // Journal entries get theirs once they're committed with Enter
+        let first_written = !self.is_journal() && !input.is_empty();
+        let entry = &mut self.get_messages_mut()[target_index];
+        entry.message = input;
+        if first_written && entry.created_at.is_none() {
+            entry.created_at = Some(date::now());
+        }
+    }
+
+    /// Leaves the line editor without touching the entry
+    pub fn cancel_edit(&mut self) {
+        self.editing = false;
+        self.input = self.get_current_message();
+        self.clamp_column();
+    }
+
+    /// Replaces the notes of the selected entry, as one undo step
+    pub fn set_notes(&mut self, notes : String) {
+        let target_index = self.target_row as usize;
+        if self.get_messages()[target_index].notes != notes {
+            self.save_snapshot();
+            self.get_messages_mut()[target_index].notes = notes;
+        }
+    }
+
+    pub fn get_current_message(&self) -> String {
+        self.get_messages()[self.target_row as usize].message.clone()
+    }
+
+    pub fn push_message(&mut self, new_entry : TodoData) {
+        self.input = String::new();
+        self.get_messages_mut().push(new_entry);
+    }
+
+    pub fn add_char(&mut self, new_char : char) {
+        if self.is_at_length_limit() || self.is_current_entry_locked() {
+            return;
+        }
+
+        let input_index = text::byte_offset(&self.input, self.target_column as usize);
+        self.input.insert(input_index, new_char);
+
+        // A combining character merges into the grapheme before it, so count the
+        // clusters up to the inserted char rather than blindly moving one to the right
+        let inserted_end = input_index + new_char.len_utf8();
+        self.target_column = text::grapheme_count(&self.input[..inserted_end]) as i32;
+    }
+
+    /// Number of characters (not bytes) in the input box
+    pub fn input_length(&self) -> usize {
+        self.input.chars().count()
+    }
+
+    pub fn is_at_length_limit(&self) -> bool {
+        match self.config.max_todo_length {
+            Some(limit) => self.input_length() >= limit,
+            None => false
+        }
+    }
+
+    /// True once the input reaches 80% of the configured limit
+    pub fn is_near_length_limit(&self) -> bool {
+        match self.config.max_todo_length {
+            Some(limit) => self.input_length() * 5 >= limit * 4,
+            None => false
+        }
+    }
+
+    pub fn remove_char(&mut self) {
+        if self.input.is_empty() || self.is_current_entry_locked() {
+            return;
+        }
+
+        if self.target_column <= 0 {
+            return;
+        }
+
+        if let Some((start, end)) = text::grapheme_range(&self.input, (self.target_column - 1) as usize) {
+            self.input.replace_range(start..end, "");
+        }
+        self.target_column -=1;
+    }
+
+    pub fn clamp_row(&mut self)
+    {
+        self.target_row = num::clamp(self.target_row, 0, self.get_messages().len() as i32 - 1);
+    }
+
+    /// Keeps the cursor on a character of the selected entry, or behind the last one while
+    /// editing so there's somewhere to append
+    pub fn clamp_column(&mut self)
+    {
+        let last_column = if self.editing {
+            text::grapheme_count(&self.input) as i32
+        } else {
+            cmp::max(text::grapheme_count(&self.get_current_message()) as i32 - 1, 0)
+        };
+        self.target_column = num::clamp(self.target_column, 0, last_column);
+    }
+
+    pub fn move_cursor(&mut self, move_operation : MoveCursorOperation) {
+        let changes_row = matches!(move_operation, MoveCursorOperation::Up | MoveCursorOperation::Down);
+        if changes_row && self.editing {
+            self.commit_edit();
+        }
+        match move_operation {
+            MoveCursorOperation::Down => {
+                let visible = self.visible_indices();
+                let position = visible.iter().position(|index| *index == self.target_row as usize);
+                if let Some(next) = position.and_then(|position| visible.get(position + 1)) {
+                    self.target_row = *next as i32;
+                }
+            }
+            MoveCursorOperation::Up => {
+                let visible = self.visible_indices();
+                let position = visible.iter().position(|index| *index == self.target_row as usize);
+                if let Some(previous) = position.and_then(|position| position.checked_sub(1)) {
+                    self.target_row = visible[previous] as i32;
+                }
+            }
+            MoveCursorOperation::Left => {
+                self.target_column -= 1;
+            }
+            MoveCursorOperation::Right => {
+                self.target_column += 1;
+            }
+            MoveCursorOperation::LineStart => {
+                self.target_column = 0;
+            }
+            MoveCursorOperation::LineEnd => {
+                // Clamped to the line's last column below
+                self.target_column = i32::MAX;
+            }
+        }
+        
+        self.clamp_row();
+        if changes_row {
+            self.input = self.get_current_message();
+        }
+        self.clamp_column();
+    }
+
+    /// Records the edited entry and moves on to a new blank entry when it was the last one.
+    /// Both are one undo step with the typing, and nothing is recorded when nothing changed.
+    pub fn new_line(&mut self)
+    {
+        let journal_entry = self.is_journal() && !self.is_current_entry_locked();
+        if journal_entry && self.input.is_empty() {
+            self.editing = false;
+            return;
+        }
+
+        self.commit_edit();
+        let target_index = self.target_row as usize;
+        if journal_entry {
+            // Committing locks the entry, which is a change even when the text stayed the same
+            self.save_typing_snapshot();
+            self.get_messages_mut()[target_index].created_at = Some(date::now());
+        }
+
+        // Push new line to todo queue as we've finished writing current one
+        if target_index >= self.get_messages().len() - 1 {
+            self.save_typing_snapshot();
+            self.push_message(TodoData::default());
+            self.target_row += 1;
+            self.target_column = 0;
+        }
+
+        self.input = self.get_current_message();
+        self.editing = false;
+        // Typing into the entry again is a step of its own
+        self.typing_row = None;
+    }
+
+    /// Nests the selected entry one level deeper, at most one level below the entry above it
+    pub fn indent(&mut self) {
+        let target_index = self.target_row as usize;
+        if target_index == 0 || self.is_journal() {
+            return;
+        }
+
+        self.save_snapshot();
+        let cur_messages = self.get_messages_mut();
+        let max_indent = cur_messages[target_index - 1].indent + 1;
+        let entry = &mut cur_messages[target_index];
+        entry.indent = cmp::min(entry.indent + 1, max_indent);
+    }
+
+    pub fn outdent(&mut self) {
+        let target_index = self.target_row as usize;
+        if self.active_list().entries[target_index].indent == 0 || self.is_journal() {
+            return;
+        }
+
+        self.save_snapshot();
+        let entry = &mut self.get_messages_mut()[target_index];
+        entry.indent -= 1;
+    }
+
+    /// Folds or unfolds the entries nested below the selected one
+    pub fn toggle_fold(&mut self) {
+        let target_index = self.target_row as usize;
+        if child_count(&self.active_list().entries, target_index) == 0 {
+            self.status_message = Some("Nothing is nested below this todo".to_string());
+            return;
+        }
+
+        self.save_snapshot();
+        let entry = &mut self.get_messages_mut()[target_index];
+        entry.folded = !entry.folded;
+    }
+
+    /// Tab while editing: indents at the start of the line, otherwise inserts spaces
+    pub fn handle_tab(&mut self) {
+        if self.config.tab_indents && self.target_column == 0 {
+            self.indent();
+            return;
+        }
+
+        for _ in 0..TAB_WIDTH {
+            self.add_char(' ');
+        }
+    }
+
+    /// Inserts a blank todo at the top of the active list and starts editing it
+    pub fn insert_at_top(&mut self) {
+        if self.is_journal() {
+            return;
+        }
+
+        self.save_snapshot();
+        self.get_messages_mut().insert(0, TodoData::default());
+        self.target_row = 0;
+        self.target_column = 0;
+        self.input = String::new();
+        self.editing = true;
+        // Typing into the new entry is part of the same undo step
+        self.typing_row = Some((self.active_list().id, self.target_row));
+    }
+
+    /// Inserts a blank todo below the selected one and its nested entries (`below`) or right
+    /// above it, at the same level and priority so it shows up next to it, and starts editing
+    pub fn open_line(&mut self, below : bool) {
+        if self.is_journal() {
+            return;
+        }
+        if self.get_current_message().is_empty() {
+            self.start_editing();
+            return;
+        }
+
+        self.save_snapshot();
+        let target_index = self.target_row as usize;
+        let cur_messages = self.get_messages_mut();
+        let position = if below { target_index + 1 + child_count(cur_messages, target_index) } else { target_index };
+        let entry = TodoData {
+            indent : cur_messages[target_index].indent,
+            priority : cur_messages[target_index].priority,
+            ..TodoData::default()
+        };
+        cur_messages.insert(position, entry);
+
+        self.target_row = position as i32;
+        self.target_column = 0;
+        self.input = String::new();
+        self.editing = true;
+        // Typing into the new entry is part of the same undo step
+        self.typing_row = Some((self.active_list().id, self.target_row));
+    }
+
+    /// Copies the selected entry and the ones nested below it into the register, `cut` also
+    /// removes them from the list. False when there was nothing to copy.
+    pub fn yank(&mut self, cut : bool) -> bool {
+        if self.get_current_message().is_empty() || (cut && self.is_current_entry_locked()) {
+            return false;
+        }
+
+        let target_index = self.target_row as usize;
+        let end = target_index + 1 + child_count(&self.active_list().entries, target_index);
+        self.register = self.active_list().entries[target_index..end].to_vec();
+        self.register_cut = cut;
+
+        if cut {
+            self.save_snapshot();
+            self.get_messages_mut().drain(target_index..end);
+            self.clamp_row();
+            self.clamp_column();
+            self.input = self.get_current_message();
+        }
+        true
+    }
+
+    /// The register's entries for a paste. The first paste after a cut moves them back with
+    /// their ids, every other one is a copy with ids of its own, so blocking, time tracking
+    /// and syncing never find two todo's by the same id.
+    pub fn pasted_register(&mut self) -> Vec<TodoData> {
+        if std::mem::take(&mut self.register_cut) {
+            return self.register.clone();
+        }
+        self.register
+            .iter()
+            .map(|entry| TodoData { id : None, sync_uid : None, todoist_id : None, ..entry.clone() })
+            .collect()
+    }
+
+    /// Inserts `entries` below the selected entry and its nested ones, at its level. On the
+    /// blank entry they go in front of it at the top level.
+    pub fn paste_entries(&mut self, mut entries : Vec<TodoData>) {
+        self.save_snapshot();
+        let target_index = self.target_row as usize;
+        let cur_messages = self.get_messages_mut();
+        let (position, indent) = if cur_messages[target_index].message.is_empty() {
+            (target_index, 0)
+        } else {
+            (target_index + 1 + child_count(cur_messages, target_index), cur_messages[target_index].indent)
+        };
+
+        let base = entries[0].indent;
+        for entry in &mut entries {
+            entry.indent = entry.indent.saturating_sub(base) + indent;
+            entry.marked = false;
+        }
+        cur_messages.splice(position..position, entries);
+
+        self.target_row = position as i32;
+        self.target_column = 0;
+        self.input = self.get_current_message();
+    }
+
+    pub fn change_target_mode(&mut self) 
+    {
+        self.switch_to((self.target_list + 1) % self.lists.len());
+    }
+
+    /// Shows the list at `index`, where it was left off last time
+    pub fn switch_to(&mut self, index : usize) {
+        self.visual_anchor = None;
+        self.save_view();
+        self.target_list = index;
+        self.restore_view();
+    }
+
+    /// Remembers the cursor and scroll position of the active list
+    fn save_view(&mut self) {
+        self.lists[self.target_list].view = ListView {
+            row : self.target_row,
+            column : self.target_column,
+            offset : self.list_offset
+        };
+    }
+
+    fn restore_view(&mut self) {
+        let view = self.active_list().view;
+        self.target_row = view.row;
+        self.target_column = view.column;
+        self.clamp_row();
+        self.clamp_column();
+        self.list_offset = cmp::min(view.offset, self.target_row as usize);
+
+        let cur_messages = self.get_messages();
+        self.input = cur_messages[self.target_row as usize].message.clone();
+    }
+
+    pub fn remove_message(&mut self) {
+        if !self.marked_indices().is_empty() {
+            return self.remove_marked();
+        }
+        if self.is_current_entry_locked() {
+            return;
+        }
+
+        self.save_snapshot();
+        let index_to_remove = self.target_row as usize;
+        let cur_messages = self.get_messages_mut();
+        crate::remove(cur_messages, index_to_remove);
+
+        if cur_messages.is_empty() {
+            self.push_message(TodoData::default());
+        }
+
+        self.move_cursor(MoveCursorOperation::Up);
+    }
+
+    fn set_message_status(&mut self, new_status : Status)
+    {
+        if self.is_journal() {
+            return;
+        }
+
+        self.save_snapshot();
+        let target_index = self.target_row as usize;
+        crate::set_status(self.get_messages_mut(), target_index, new_status, date::Date::today());
+    }
+
+    /// Todo and in progress entries become Done, Done ones go back to Todo
+    pub fn toggle_message_status(&mut self) {
+        let new_status = self.get_messages()[self.target_row as usize].status.toggled();
+        self.set_message_status(new_status);
+    }
+
+    /// Marks the selected entry as in progress, or back to Todo when it already is
+    pub fn toggle_in_progress(&mut self) {
+        let new_status = match self.get_messages()[self.target_row as usize].status {
+            Status::InProgress => Status::Todo,
+            Status::Todo | Status::Done => Status::InProgress
+        };
+        self.set_message_status(new_status);
+    }
+
+    /// Moves the selected todo one priority up (`raise`) or down
+    pub fn change_priority(&mut self, raise : bool) {
+        if self.is_journal() {
+            return;
+        }
+
+        self.save_snapshot();
+        let mut targets = self.marked_indices();
+        if targets.is_empty() {
+            targets.push(self.target_row as usize);
+        }
+        self.visual_anchor = None;
+        for index in targets {
+            let entry = &mut self.get_messages_mut()[index];
+            entry.priority = if raise { entry.priority.raised() } else { entry.priority.lowered() };
+            entry.marked = false;
+        }
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot::of(self.active_list(), self.target_row, self.target_column)
+    }
+
+    /// Records the active list as an undo step, dropping anything that could be redone
+    fn save_snapshot(&mut self) {
+        let snapshot = self.snapshot();
+        self.push_undo(snapshot);
+    }
+
+    /// Like `save_snapshot`, for a step that also changes the lists at `indices`
+    fn save_snapshot_with(&mut self, indices : &[usize]) {
+        let mut snapshot = self.snapshot();
+        snapshot.others = indices.iter().map(|&index| (self.lists[index].id, self.lists[index].entries.clone())).collect();
+        self.push_undo(snapshot);
+    }
+
+    /// Records the list with `role` as an undo step, returns its index into `lists`
+    fn save_snapshot_of(&mut self, role : ListRole) -> usize {
+        let index = self.role_index(role);
+        if index == self.target_list {
+            self.save_snapshot();
+        } else {
+            self.save_snapshot_with(&[index]);
+        }
+        index
+    }
+
+    /// Records `snapshot` as an undo step, dropping anything that could be redone
+    fn push_undo(&mut self, snapshot : Snapshot) {
+        self.mark_changed();
+        self.typing_row = None;
+        self.history.record(snapshot);
+    }
+
+    /// Like `save_snapshot`, but only for the first keystroke typed into an entry
+    fn save_typing_snapshot(&mut self) {
+        let row = (self.active_list().id, self.target_row);
+        if self.typing_row != Some(row) {
+            self.save_snapshot();
+            self.typing_row = Some(row);
+        }
+    }
+
+    /// Shows the list an undone or redone step changed, with the cursor where the step
+    /// left it
+    fn show_restored(&mut self, restored : undo::Restored) {
+        if restored.list != self.target_list {
+            self.save_view();
+            self.target_list = restored.list;
+        }
+
+        self.mark_changed();
+        self.target_row = restored.row;
+        self.target_column = restored.column;
+        self.clamp_row();
+        self.clamp_column();
+        self.input = self.get_current_message();
+        self.typing_row = None;
+    }
+
+    pub fn undo(&mut self) {
+        // An undone cut leaves the todo's in place, pasting them again makes copies
+        self.register_cut = false;
+        match self.history.undo(&mut self.lists, (self.target_row, self.target_column)) {
+            Some(restored) => self.show_restored(restored),
+            None => self.status_message = Some("Nothing to undo".to_string())
+        }
+    }
+
+    pub fn redo(&mut self) {
+        match self.history.redo(&mut self.lists, (self.target_row, self.target_column)) {
+            Some(restored) => self.show_restored(restored),
+            None => self.status_message = Some("Nothing to redo".to_string())
+        }
+    }
+
+    /// Starts editing with the cursor one character further, so text goes after it
+    pub fn append(&mut self) {
+        let length = text::grapheme_count(&self.get_current_message()) as i32;
+        self.target_column = cmp::min(self.target_column + 1, length);
+        self.start_editing();
+    }
+
+    /// Swaps the selected entry with the one shown above it (`up`) or below it, the
+    /// cursor moves along. The trailing blank entry stays last.
+    pub fn move_entry(&mut self, up : bool) {
+        if self.is_journal() || self.get_current_message().is_empty() {
+            return;
+        }
+
+        let visible = self.visible_indices();
+        let position = match visible.iter().position(|index| *index == self.target_row as usize) {
+            Some(position) => position,
+            None => return
+        };
+        let neighbor = if up { position.checked_sub(1) } else { Some(position + 1) };
+        let neighbor = match neighbor.and_then(|neighbor| visible.get(neighbor)) {
+            Some(&neighbor) if !self.get_messages()[neighbor].message.is_empty() => neighbor,
+            _ => return
+        };
+
+        self.save_snapshot();
+        let current = self.target_row as usize;
+        crate::move_entry(self.get_messages_mut(), current, neighbor);
+        self.target_row = neighbor as i32;
+    }
+
+    pub fn select_first(&mut self) {
+        if let Some(first) = self.visible_indices().first() {
+            self.select_row(*first);
+        }
+    }
+
+    pub fn select_last(&mut self) {
+        if let Some(last) = self.visible_indices().last() {
+            self.select_row(*last);
+        }
+    }
+
+    /// Selects the entry at `index` of the active list
+    pub fn select_row(&mut self, index : usize) {
+        if self.editing {
+            self.commit_edit();
+        }
+        self.target_row = index as i32;
+        self.clamp_row();
+        self.input = self.get_current_message();
+        self.clamp_column();
+    }
+
+    pub fn toggle_mark(&mut self) {
+        if self.is_journal() {
+            return;
+        }
+
+        let target_index = self.target_row as usize;
+        let entry = &mut self.get_messages_mut()[target_index];
+        if !entry.message.is_empty() {
+            entry.marked = !entry.marked;
+        }
+    }
+
+    /// Starts marking every entry between the selected one and the cursor as it moves, or
+    /// stops and keeps the marks for the next done, remove, move or priority change
+    pub fn toggle_visual(&mut self) {
+        if self.visual_anchor.take().is_some() || self.is_journal() {
+            return;
+        }
+        self.visual_anchor = Some(self.target_row as usize);
+        self.update_visual_marks();
+    }
+
+    /// Marks exactly the entries from the visual anchor to the cursor
+    pub fn update_visual_marks(&mut self) {
+        let anchor = match self.visual_anchor {
+            Some(anchor) if anchor < self.get_messages().len() => anchor,
+            _ => {
+                self.visual_anchor = None;
+                return;
+            }
+        };
+        let row = self.target_row as usize;
+        let range = cmp::min(anchor, row)..=cmp::max(anchor, row);
+        for (index, entry) in self.get_messages_mut().iter_mut().enumerate() {
+            entry.marked = range.contains(&index) && !entry.message.is_empty();
+        }
+    }
+
+    /// Removes every marked entry, the entries nested below them stay
+    pub fn remove_marked(&mut self) {
+        self.save_snapshot();
+        self.visual_anchor = None;
+        let marked = self.marked_indices();
+        let cur_messages = self.get_messages_mut();
+        for index in marked.into_iter().rev() {
+            crate::remove(cur_messages, index);
+        }
+        if cur_messages.is_empty() {
+            self.push_message(TodoData::default());
+        }
+
+        self.clamp_row();
+        self.input = self.get_current_message();
+        self.clamp_column();
+    }
+
+    pub fn marked_indices(&self) -> Vec<usize> {
+        self.get_messages()
+            .iter()
+            .enumerate()
+            .filter(|(_, m)| m.marked)
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// Flips the status of every marked entry and clears the marks. With a mix of
+    /// statuses everything becomes Done, only an all-Done selection goes back to Todo.
+    pub fn toggle_marked_status(&mut self) {
+        self.save_snapshot();
+        self.visual_anchor = None;
+        let marked = self.marked_indices();
+        let cur_messages = self.get_messages_mut();
+        let any_todo = marked.iter().any(|index| cur_messages[*index].status != Status::Done);
+        let new_status = if any_todo { Status::Done } else { Status::Todo };
+
+        // Back to front, so inserted occurrences don't shift the entries still to do
+        for index in marked.into_iter().rev() {
+            cur_messages[index].marked = false;
+            crate::set_status(cur_messages, index, new_status, date::Date::today());
+        }
+    }
+
+    /// Sorts the active list by `keys`, keeping the trailing blank entry last and the cursor
+    /// on the entry it was on before sorting.
+    fn sort_active_list(&mut self, keys : &[SortKey]) {
+        // Journal entries stay in the order they were written
+        if self.is_journal() {
+            return;
+        }
+
+        self.save_snapshot();
+        let target_index = self.target_row as usize;
+        let order = sort::sort(self.get_messages_mut(), keys);
+        if let Some(new_row) = order.iter().position(|index| *index == target_index) {
+            self.target_row = new_row as i32;
+        }
+
+        self.input = self.get_current_message();
+        self.clamp_column();
+    }
+
+    /// Name the default ordering of the active list is saved under, day lists share the
+    /// daily one
+    fn sort_order_name(&self) -> Option<String> {
+        match self.active_list().role {
+            ListRole::Daily(_) | ListRole::Day(_) => Some("daily".to_string()),
+            ListRole::LongTerm => Some("long_term".to_string()),
+            ListRole::Someday => Some("someday".to_string()),
+            ListRole::Named => Some(format!("list_{}", self.active_list().name)),
+            ListRole::Journal => None
+        }
+    }
+
+    /// Sorts the active list A-Z (case-insensitive), keeping the trailing blank entry last
+    /// and the cursor on the entry it was on before sorting.
+    fn sort_alphabetical(&mut self) {
+        self.sort_active_list(&[SortKey::Alphabetical]);
+    }
+
+    /// Sorts the active list by its saved default ordering, A-Z when it has none
+    pub fn sort_by_default(&mut self) {
+        let saved = match self.sort_order_name() {
+            Some(name) => self.file_manager.load_sort_order(&name),
+            None => return
+        };
+        match saved {
+            Ok(Some(keys)) => self.sort_active_list(&keys),
+            Ok(None) => self.sort_alphabetical(),
+            Err(err) => self.status_message = Some(format!("Failed to read the sort order: {}", err))
+        }
+    }
+
+    /// `:sort status,priority` sorts the active list by those keys, `:sort!` also makes them
+    /// its default ordering, the one the sort key uses
+    pub fn sort_command(&mut self, text : &str, save : bool) {
+        let keys = match sort::parse_keys(text) {
+            Some(keys) => keys,
+            None => {
+                let names : Vec<&str> = SortKey::ALL.iter().map(|key| key.name()).collect();
+                self.status_message = Some(format!("Sort by one or more of {}", names.join(", ")));
+                return;
+            }
+        };
+        let name = match self.sort_order_name() {
+            Some(name) => name,
+            None => {
+                self.status_message = Some(format!("{} keeps the order it was written in", self.active_list().name));
+                return;
+            }
+        };
+        if self.is_read_only() {
+            self.status_message = Some(format!("{} is read-only", self.active_list().name));
+            return;
+        }
+
+        self.sort_active_list(&keys);
+        if save {
+            self.status_message = Some(match self.file_manager.save_sort_order(&name, &keys) {
+                Ok(()) => format!("{} is now sorted by {} on {}", self.active_list().name, sort::format_keys(&keys), self.config.keymap.key_name(Action::Sort)),
+                Err(err) => format!("Failed to save the sort order: {}", err)
+            });
+        }
+    }
+
+    /// Whether the entry at `index` of the active list passes the tag filter and the search
+    /// being typed. The selected entry and blank entries are always shown so the cursor never
+    /// gets lost.
+    fn is_visible(&self, index : usize, m : &TodoData) -> bool {
+        if index == self.target_row as usize {
+            return true;
+        }
+        if is_folded_away(&self.active_list().entries, index) {
+            return false;
+        }
+        if m.message.is_empty() {
+            return true;
+        }
+
+        let tag_matches = self.tag_filter.as_ref().is_none_or(|tag| tags::parse_tags(&m.message).contains(tag));
+        tag_matches && (!self.searching || self.matches_search(m))
+    }
+
+    /// Indices into the active list of the entries that are currently shown, in the order
+    /// they are shown: highest priority first, otherwise in list order. Indented entries
+    /// move along with the top level entry above them and the trailing blank entry stays last.
+    pub fn visible_indices(&self) -> Vec<usize> {
+        let cur_messages = self.get_messages();
+        let mut visible : Vec<usize> = cur_messages
+            .iter()
+            .enumerate()
+            .filter(|(index, m)| self.is_visible(*index, m))
+            .map(|(index, _)| index)
+            .collect();
+
+        if !self.is_journal() {
+            sort_by_priority(&cur_messages, &mut visible);
+        }
+
+        visible
+    }
+
+    /// All tags used in the active list, sorted and without duplicates
+    pub fn tag_index(&self) -> Vec<String> {
+        let mut all_tags : Vec<String> = self.get_messages()
+            .iter()
+            .flat_map(|m| tags::parse_tags(&m.message))
+            .collect();
+        all_tags.sort();
+        all_tags.dedup();
+        all_tags
+    }
+
+    /// Adds `secs` to the time spent on the todo with id `todo` of the list with id `list`
+    pub fn add_spent(&mut self, list : usize, todo : u64, secs : u32) -> Option<&mut TodoData> {
+        let list = self.list_index(list)?;
+        let position = self.lists[list].entries.iter().position(|m| m.id == Some(todo))?;
+        self.mark_changed();
+        let entry = &mut self.lists[list].entries[position];
+        entry.actual_secs += secs;
+        Some(entry)
+    }
+
+    /// Id of the selected todo, which is given one when it has none yet, so time can be tracked
+    /// on it while its message changes. `None` on the blank entry and in the journal.
+    pub fn selected_todo_id(&mut self) -> Option<u64> {
+        if self.is_journal() || self.get_current_message().is_empty() {
+            return None;
+        }
+        let next_id = self.next_todo_id();
+        let target_index = self.target_row as usize;
+        let id = &mut self.get_messages_mut()[target_index].id;
+        if let Some(id) = *id {
+            return Some(id);
+        }
+        *id = Some(next_id);
+        self.mark_changed();
+        Some(next_id)
+    }
+
+    /// Total time tracked on the daily list
+    fn daily_actual_secs(&self) -> u32 {
+        self.entries(self.daily_role()).iter().map(|m| m.actual_secs).sum()
+    }
+
+    /// `Tracked today: 25m of 1h 00m estimated`, the estimate is left out when there's none
+    pub fn daily_time_summary(&self) -> String {
+        let tracked = format!("Tracked today: {}", date::format_duration(self.daily_actual_secs()));
+        match self.entries(self.daily_role()).iter().map(|m| m.estimate_secs).sum() {
+            0 => tracked,
+            estimate => format!("{} of {} estimated", tracked, date::format_duration(estimate))
+        }
+    }
+
+    /// Selects the next visible entry after the cursor whose message starts with `letter`
+    /// (case-insensitive), wrapping around to the top of the list
+    pub fn jump_to_letter(&mut self, letter : char) {
+        let letter = letter.to_lowercase().next().unwrap_or(letter);
+        let cur_messages = self.get_messages();
+        let visible = self.visible_indices();
+        let current = self.target_row as usize;
+
+        let starts_with_letter = |index : &usize| {
+            cur_messages[*index].message.trim_start().chars().next()
+                .and_then(|c| c.to_lowercase().next()) == Some(letter)
+        };
+        let position = visible.iter().position(|index| *index == current).unwrap_or(0);
+        let next = visible[position + 1..].iter().chain(&visible[..=position]).find(|index| starts_with_letter(index));
+
+        if let Some(index) = next {
+            self.select_row(*index);
+        }
+    }
+
+    pub fn start_search(&mut self) {
+        self.search = Some(String::new());
+        self.searching = true;
+    }
+
+    pub fn search_add_char(&mut self, new_char : char) {
+        if let Some(query) = &mut self.search {
+            query.push(new_char);
+        }
+        self.select_first_match();
+    }
+
+    pub fn search_remove_char(&mut self) {
+        if let Some(query) = &mut self.search {
+            query.pop();
+        }
+        self.select_first_match();
+    }
+
+    /// Enter in the search box: keeps the query around for `n`/`N`
+    pub fn finish_search(&mut self) {
+        self.searching = false;
+        if self.search.as_ref().is_some_and(|query| query.is_empty()) {
+            self.search = None;
+            return;
+        }
+
+        let count = self.search_matches().len();
+        self.status_message = Some(format!("{} match{}", count, if count == 1 { "" } else { "es" }));
+    }
+
+    pub fn cancel_search(&mut self) {
+        self.search = None;
+        self.searching = false;
+    }
+
+    /// Case-insensitive substring match against the current query, nothing matches without one
+    fn matches_search(&self, m : &TodoData) -> bool {
+        match &self.search {
+            Some(query) if !query.is_empty() => m.message.to_lowercase().contains(&query.to_lowercase()),
+            _ => false
+        }
+    }
+
+    /// Moves the cursor off an entry that no longer matches the query being typed
+    fn select_first_match(&mut self) {
+        let cur_messages = self.get_messages();
+        if self.matches_search(&cur_messages[self.target_row as usize]) {
+            return;
+        }
+
+        let first = self.visible_indices().into_iter().find(|index| self.matches_search(&cur_messages[*index]));
+        if let Some(index) = first {
+            self.select_row(index);
+        }
+    }
+
+    /// Indices of the lists searched with `n`/`N`: the daily and long term lists, or just
+    /// the active one when it's another list
+    fn searched_lists(&self) -> Vec<usize> {
+        match self.active_list().role {
+            ListRole::Daily(_) | ListRole::LongTerm => vec![self.role_index(self.daily_role()), self.role_index(ListRole::LongTerm)],
+            _ => vec![self.target_list]
+        }
+    }
+
+    /// Every entry (list index, entry index) matching the query, in the order `n` visits them
+    fn search_matches(&self) -> Vec<(usize, usize)> {
+        let mut matches = vec![];
+        for list in self.searched_lists() {
+            let entries = self.lists[list].entries.iter().enumerate();
+            matches.extend(entries.filter(|(_, m)| self.matches_search(m)).map(|(index, _)| (list, index)));
+        }
+        matches
+    }
+
+    /// Selects the next (or with `forward` unset the previous) match, switching lists when
+    /// it's in the other one and wrapping around at the end
+    pub fn jump_to_match(&mut self, forward : bool) {
+        let matches = self.search_matches();
+        if matches.is_empty() {
+            self.status_message = Some(match &self.search {
+                Some(query) => format!("No matches for {}", query),
+                None => "No search, start one with /".to_string()
+            });
+            return;
+        }
+
+        let lists = self.searched_lists();
+        let order = |(list, index) : &(usize, usize)| (lists.iter().position(|l| l == list), *index);
+        let current = order(&(self.target_list, self.target_row as usize));
+        let next = if forward {
+            matches.iter().find(|m| order(m) > current).or(matches.first())
+        } else {
+            matches.iter().rev().find(|m| order(m) < current).or(matches.last())
+        };
+
+        if let Some(&(list, index)) = next {
+            if list != self.target_list {
+                self.switch_to(list);
+            }
+            self.select_row(index);
+        }
+    }
+
+    /// Moves the done todo's of the active list into its file in the archive directory. A done
+    /// todo with open ones nested below it stays, so they keep their place.
+    pub fn archive_done(&mut self) {
+        let stem = match self.active_list().role {
+            ListRole::Daily(day) => day.file_stem(),
+            ListRole::LongTerm => "long_term".to_string(),
+            ListRole::Someday => "someday".to_string(),
+            ListRole::Named => format!("list_{}", self.active_list().name),
+            ListRole::Day(day) if day > date::Date::today() => day.file_stem(),
+            ListRole::Journal | ListRole::Day(_) => {
+                self.status_message = Some(format!("{} can't be archived", self.active_list().name));
+                return;
+            }
+        };
+
+        let entries = &self.active_list().entries;
+        let mut ranges = vec![];
+        let mut index = 0;
+        while index < entries.len() {
+            let end = index + 1 + child_count(entries, index);
+            if entries[index..end].iter().all(|entry| entry.status == Status::Done) {
+                ranges.push((index, end));
+                index = end;
+            } else {
+                index += 1;
+            }
+        }
+        if ranges.is_empty() {
+            self.status_message = Some("Nothing is done yet".to_string());
+            return;
+        }
+
+        let archived : Vec<TodoData> = ranges.iter().flat_map(|&(start, end)| entries[start..end].to_vec()).collect();
+        if let Err(err) = self.file_manager.archive_entries(&stem, &archived) {
+            self.status_message = Some(format!("Failed to archive: {}", err));
+            return;
+        }
+
+        self.save_snapshot();
+        for &(start, end) in ranges.iter().rev() {
+            self.get_messages_mut().drain(start..end);
+        }
+        self.clamp_row();
+        self.clamp_column();
+        self.input = self.get_current_message();
+        self.status_message = Some(format!("Archived {} todo{}", archived.len(), if archived.len() == 1 { "" } else { "'s" }));
+    }
+
+    /// Blocks the selected todo until the first other one containing `text` is done, looking
+    /// in the active list before the others. The other todo gets an id if it has none yet.
+    pub fn set_blocked_by(&mut self, text : &str) {
+        let target_index = self.target_row as usize;
+        if text.is_empty() {
+            self.save_snapshot();
+            self.get_messages_mut()[target_index].blocked_by = None;
+            return;
+        }
+
+        let needle = text.to_lowercase();
+        let order = std::iter::once(self.target_list).chain((0..self.lists.len()).filter(|index| *index != self.target_list));
+        let found = order
+            .flat_map(|list| (0..self.lists[list].entries.len()).map(move |entry| (list, entry)))
+            .filter(|&(list, entry)| (list, entry) != (self.target_list, target_index))
+            .find(|&(list, entry)| {
+                let message = &self.lists[list].entries[entry].message;
+                !message.is_empty() && message.to_lowercase().contains(&needle)
+            });
+        let (list, entry) = match found {
+            Some(found) => found,
+            None => {
+                self.status_message = Some(format!("No todo matches {}", text));
+                return;
+            }
+        };
+
+        let selected = self.active_list().entries[target_index].id;
+        if selected.is_some_and(|id| self.waits_for(self.lists[list].entries[entry].blocked_by, id)) {
+            self.status_message = Some(format!("{} already waits for this todo", self.lists[list].entries[entry].message));
+            return;
+        }
+
+        // Taken before the blocker gets its id, so undo takes that back too
+        if list == self.target_list {
+            self.save_snapshot();
+        } else {
+            self.save_snapshot_with(&[list]);
+        }
+        let next_id = self.next_todo_id();
+        let blocker = &mut self.lists[list].entries[entry];
+        let id = *blocker.id.get_or_insert(next_id);
+        let message = blocker.message.clone();
+        self.get_messages_mut()[target_index].blocked_by = Some(id);
+        self.status_message = Some(format!("Waits for {}", message));
+    }
+
+    /// Whether a todo waiting for the one with id `from` ends up waiting for `id`, following
+    /// the chain of `blocked_by` through the open lists
+    fn waits_for(&self, from : Option<u64>, id : u64) -> bool {
+        let mut seen = HashSet::new();
+        let mut next = from;
+        while let Some(current) = next {
+            if current == id {
+                return true;
+            }
+            // A chain that already loops can't be followed to its end
+            if !seen.insert(current) {
+                return false;
+            }
+            next = self.lists
+                .iter()
+                .flat_map(|list| &list.entries)
+                .find(|entry| entry.id == Some(current))
+                .and_then(|entry| entry.blocked_by);
+        }
+        false
+    }
+
+    /// An id no todo has yet. They're taken from the clock so todo's in files that aren't
+    /// open, like days planned ahead, don't end up sharing one.
+    fn next_todo_id(&self) -> u64 {
+        let highest = self.lists.iter().flat_map(|list| &list.entries).filter_map(|entry| entry.id).max();
+        cmp::max(date::now(), highest.map_or(0, |id| id + 1))
+    }
+
+    /// Ids of the todo's in the open lists that aren't done, the ones blocked by them wait
+    pub fn open_blocker_ids(&self) -> HashSet<u64> {
+        self.lists
+            .iter()
+            .flat_map(|list| &list.entries)
+            .filter(|entry| entry.status != Status::Done)
+            .filter_map(|entry| entry.id)
+            .collect()
+    }
+
+    pub fn set_recurrence(&mut self, text : &str) {
+        let recurrence = if text.is_empty() {
+            None
+        } else {
+            match Recurrence::parse(text) {
+                Some(recurrence) => Some(recurrence),
+                None => {
+                    self.status_message = Some(format!("Not a repeat rule: {}", text));
+                    return;
+                }
+            }
+        };
+
+        self.save_snapshot();
+        let target_index = self.target_row as usize;
+        self.get_messages_mut()[target_index].recurrence = recurrence;
+    }
+
+    pub fn set_estimate(&mut self, text : &str) {
+        let estimate = if text.is_empty() {
+            0
+        } else {
+            match date::parse_duration(text) {
+                Some(estimate) => estimate,
+                None => {
+                    self.status_message = Some(format!("Not a duration: {}", text));
+                    return;
+                }
+            }
+        };
+
+        self.save_snapshot();
+        let target_index = self.target_row as usize;
+        self.get_messages_mut()[target_index].estimate_secs = estimate;
+    }
+
+    pub fn set_due_date(&mut self, text : &str) {
+        let due = if text.is_empty() {
+            None
+        } else {
+            match date::Date::parse_relative(text, date::Date::today()) {
+                Some(due) => Some(due),
+                None => {
+                    self.status_message = Some(format!("Not a date: {}", text));
+                    return;
+                }
+            }
+        };
+
+        self.save_snapshot();
+        let target_index = self.target_row as usize;
+        self.get_messages_mut()[target_index].due = due;
+    }
+
+    /// Fills the lists from the data directory
+    pub fn load_lists(&mut self) -> file_manager::Result<()> {
+        let daily = self.file_manager.load_day(self.daily_date())?.unwrap_or_default();
+        self.set_entries(self.daily_role(), daily);
+        let long_term = self.file_manager.load_long_term()?;
+        self.set_entries(ListRole::LongTerm, long_term);
+        let someday = self.file_manager.load_someday()?;
+        self.set_entries(ListRole::Someday, someday);
+        let journal = self.file_manager.load_journal()?;
+        self.set_entries(ListRole::Journal, journal);
+
+        for (name, entries) in self.file_manager.load_named_lists()? {
+            self.add_list(&name, ListRole::Named, entries);
+        }
+
+        self.restore_view();
+        Ok(())
+    }
+
+    /// Replaces the entries of a built-in list, adding the blank entry to type into
+    fn set_entries(&mut self, role : ListRole, mut entries : Vec<TodoData>) {
+        entries.push(TodoData::default());
+        *self.entries_mut(role) = entries;
+    }
+
+    /// Writes every list back to the data directory right away, after the saves still
+    /// queued on the save worker. All lists are tried, the first failure is returned.
+    pub fn save_lists(&mut self) -> file_manager::Result<()> {
+        if let Some(saver) = self.saver.take() {
+            saver.finish();
+        }
+        write_lists(&self.file_manager, &self.lists)
+    }
+
+    /// Schedules an autosave, pushed back by every further change until `AUTOSAVE_DELAY` passes
+    pub fn mark_changed(&mut self) {
+        self.unsaved_since = Some(Instant::now());
+    }
+
+    /// Time left until the pending autosave, `None` when everything is saved
+    pub fn autosave_due_in(&self) -> Option<Duration> {
+        self.unsaved_since.map(|changed| AUTOSAVE_DELAY.saturating_sub(changed.elapsed()))
+    }
+
+    /// Writes the lists once they haven't changed for `AUTOSAVE_DELAY`
+    pub fn autosave(&mut self) {
+        if self.autosave_due_in() != Some(Duration::ZERO) {
+            return;
+        }
+
+        self.unsaved_since = None;
+        if self.load_failed {
+            return;
+        }
+        if let Some(saver) = &mut self.saver {
+            if saver.sender.send(self.lists.clone()).is_ok() {
+                saver.pending += 1;
+                self.save_status = SaveStatus::Saving;
+                return;
+            }
+        }
+        self.save_status = match self.save_lists() {
+            Ok(()) => SaveStatus::Saved(Instant::now()),
+            Err(err) => SaveStatus::Failed(err.to_string())
+        };
+    }
+
+    /// Blocks until the save worker wrote everything sent to it, so a queued save can't
+    /// bring back a file that is about to be renamed, moved or written directly
+    fn wait_for_saves(&mut self) {
+        if let Some(saver) = &mut self.saver {
+            while saver.pending > 0 {
+                match saver.results.recv() {
+                    Ok(result) => self.save_status = saver.answered(result),
+                    Err(_) => break
+                }
+            }
+        }
+    }
+
+    /// Shows the outcome of the saves the save worker finished
+    pub fn poll_saves(&mut self) {
+        if let Some(saver) = &mut self.saver {
+            while let Ok(result) = saver.results.try_recv() {
+                self.save_status = saver.answered(result);
+            }
+        }
+    }
+
+    /// Time left until "saved" disappears from the status bar
+    pub fn saved_shown_for(&self) -> Option<Duration> {
+        match self.save_status {
+            SaveStatus::Saved(at) => Some(SAVED_SHOWN_FOR.saturating_sub(at.elapsed())),
+            _ => None
+        }
+    }
+
+    /// Clears "saved" once it was shown for `SAVED_SHOWN_FOR`
+    pub fn fade_saved(&mut self) {
+        if self.saved_shown_for() == Some(Duration::ZERO) {
+            self.save_status = SaveStatus::Clean;
+        }
+    }
+
+    /// Names double as file names, so they can't contain path separators or clash with
+    /// another list on a case-insensitive file system
+    fn validate_list_name(&self, name : &str, skip : Option<usize>) -> Result<(), String> {
+        if name.is_empty() {
+            return Err("A list needs a name".to_string());
+        }
+        if !FileManager::is_valid_list_name(name) {
+            return Err("List names can't contain / or \\ or start with a dot".to_string());
+        }
+
+        let taken = self.lists
+            .iter()
+            .enumerate()
+            .any(|(index, list)| Some(index) != skip && list.name.to_lowercase() == name.to_lowercase());
+        if taken {
+            return Err(format!("There already is a list called {}", name));
+        }
+        Ok(())
+    }
+
+    /// `name`, or `name 2`, `name 3`, ... when that's taken
+    fn unique_list_name(&self, name : &str) -> String {
+        let mut candidate = name.to_string();
+        let mut number = 2;
+        while self.validate_list_name(&candidate, None).is_err() {
+            candidate = format!("{} {}", name, number);
+            number += 1;
+        }
+        candidate
+    }
+
+    pub fn create_list(&mut self, name : &str) {
+        if let Err(err) = self.validate_list_name(name, None) {
+            self.status_message = Some(err);
+            return;
+        }
+
+        let index = self.add_list(name, ListRole::Named, vec![]);
+        self.switch_to(index);
+        if let Err(err) = self.file_manager.save_named_list(name, &[]) {
+            self.status_message = Some(format!("Failed to save {}: {}", name, err));
+        }
+    }
+
+    /// Only lists created by the user can be renamed or deleted
+    pub fn is_named_list(&mut self) -> bool {
+        if self.active_list().role == ListRole::Named {
+            return true;
+        }
+        self.status_message = Some(format!("{} is a built-in list", self.active_list().name));
+        false
+    }
+
+    pub fn rename_list(&mut self, name : &str) {
+        if name == self.active_list().name {
+            return;
+        }
+        if let Err(err) = self.validate_list_name(name, Some(self.target_list)) {
+            self.status_message = Some(err);
+            return;
+        }
+
+        self.wait_for_saves();
+        let old_name = self.active_list().name.clone();
+        if let Err(err) = self.file_manager.rename_named_list(&old_name, name) {
+            self.status_message = Some(format!("Failed to rename list: {}", err));
+            return;
+        }
+        self.lists[self.target_list].name = name.to_string();
+        // The list keeps its default ordering
+        if let Err(err) = self.file_manager.rename_sort_order(&format!("list_{}", old_name), &format!("list_{}", name)) {
+            self.status_message = Some(format!("Failed to keep the sort order: {}", err));
+        }
+    }
+
+    /// Moves the active list into the closed stash and shows the list before it
+    pub fn delete_list(&mut self) {
+        self.wait_for_saves();
+        let list = self.active_list();
+        if let Err(err) = self.file_manager.close_named_list(&list.name, &list.entries) {
+            self.status_message = Some(format!("Failed to delete list: {}", err));
+            return;
+        }
+
+        let removed = self.lists.remove(self.target_list);
+        self.target_list -= 1;
+        self.restore_view();
+        self.status_message = Some(format!("Deleted {}, U reopens it", removed.name));
+    }
+
+    /// Brings back the most recently deleted list
+    pub fn reopen_list(&mut self) {
+        let reopened = self.file_manager.reopen_closed_list(|name| self.unique_list_name(name));
+        match reopened {
+            Ok(Some((name, entries))) => {
+                let index = self.add_list(&name, ListRole::Named, entries);
+                self.switch_to(index);
+            }
+            Ok(None) => self.status_message = Some("No closed lists to reopen".to_string()),
+            Err(err) => self.status_message = Some(format!("Failed to reopen list: {}", err))
+        }
+    }
+
+    pub fn purge_closed_lists(&mut self) {
+        self.status_message = Some(match self.file_manager.purge_closed_lists() {
+            Ok(count) => format!("Deleted {} closed list{}", count, if count == 1 { "" } else { "s" }),
+            Err(err) => format!("Failed to delete closed lists: {}", err)
+        });
+    }
+
+    /// Moves the selected entry and the ones nested below it in front of the trailing blank
+    /// entry of the built-in list with `target`. False when the blank entry or a committed
+    /// journal entry is selected.
+    pub fn move_selected_to(&mut self, target : ListRole) -> bool {
+        if self.get_current_message().is_empty() || self.is_current_entry_locked() {
+            return false;
+        }
+
+        self.save_snapshot_with(&[self.role_index(target)]);
+        let start = self.target_row as usize;
+        let end = start + 1 + child_count(&self.active_list().entries, start);
+        let block : Vec<TodoData> = self.get_messages_mut().drain(start..end).collect();
+        self.clamp_row();
+        self.clamp_column();
+        self.input = self.get_current_message();
+
+        let base = block[0].indent;
+        for entry in block {
+            self.insert_before_blank(target, TodoData { indent : entry.indent - base, marked : false, ..entry });
+        }
+        true
+    }
+
+    /// Inserts `entry` in front of the trailing blank entry of the built-in list with `role`
+    fn insert_before_blank(&mut self, role : ListRole, entry : TodoData) {
+        lists::insert_before_blank(self.entries_mut(role), entry);
+    }
+
+    /// Moves the selected todo and the ones nested below it from the daily list to the end
+    /// of the long term list, or from any other list to the daily one
+    pub fn move_to_other_list(&mut self) {
+        let target = match self.active_list().role {
+            ListRole::Journal => return,
+            ListRole::Daily(_) => ListRole::LongTerm,
+            _ => self.daily_role()
+        };
+        // The marked entries, or the selected one, each with the ones nested below it
+        let mut starts = self.marked_indices();
+        if starts.is_empty() {
+            if self.get_current_message().is_empty() {
+                return;
+            }
+            starts.push(self.target_row as usize);
+        }
+        let mut ranges : Vec<(usize, usize)> = vec![];
+        for start in starts {
+            if ranges.last().is_some_and(|(_, end)| start < *end) {
+                continue;
+            }
+            ranges.push((start, start + 1 + child_count(&self.active_list().entries, start)));
+        }
+
+        self.save_snapshot_with(&[self.role_index(target)]);
+        self.visual_anchor = None;
+        let mut moved = vec![];
+        for &(start, end) in ranges.iter().rev() {
+            let block : Vec<TodoData> = self.get_messages_mut().drain(start..end).collect();
+            moved.insert(0, block);
+        }
+        self.clamp_row();
+        self.clamp_column();
+        self.input = self.get_current_message();
+
+        for block in moved {
+            let base = block[0].indent;
+            for mut entry in block {
+                entry.indent -= base;
+                entry.marked = false;
+                self.insert_before_blank(target, entry);
+            }
+        }
+        self.status_message = Some(format!("Moved to {}", self.role_name(target)));
+    }
+
+    /// Name of the built-in list with `role` as shown in the tab bar
+    fn role_name(&self, role : ListRole) -> String {
+        self.lists[self.role_index(role)].name.clone()
+    }
+
+    pub fn promote_someday(&mut self, target : ListRole) {
+        if self.move_selected_to(target) {
+            self.save_someday();
+        }
+    }
+
+    pub fn save_someday(&mut self) {
+        self.wait_for_saves();
+        if let Err(err) = self.file_manager.save_someday(self.entries(ListRole::Someday)) {
+            self.status_message = Some(format!("Failed to save someday list: {}", err));
+        }
+    }
+
+    pub fn export_active_list(&mut self, path : &Path) {
+        self.status_message = Some(match self.file_manager.write_list(path, &self.get_messages()) {
+            Ok(()) => format!("Wrote {}", path.display()),
+            Err(err) => format!("Failed to write {}: {}", path.display(), err)
+        });
+    }
+
+    /// Adds a todo reading `message` in front of the blank entry of the list at `index`,
+    /// leaving the selection on the entry it was on
+    pub fn quick_add(&mut self, index : usize, message : &str) {
+        let list = &self.lists[index];
+        let row = if index == self.target_list { self.target_row } else { list.view.row };
+        let snapshot = Snapshot::of(list, row, 0);
+        self.push_undo(snapshot);
+
+        let entries = &mut self.lists[index].entries;
+        let position = if entries.last().is_some_and(|m| m.message.is_empty()) { entries.len() - 1 } else { entries.len() };
+        entries.insert(position, TodoData { message : message.trim().to_string(), created_at : Some(date::now()), ..TodoData::default() });
+        if index == self.target_list && self.target_row as usize >= position {
+            self.target_row += 1;
+        }
+        self.status_message = Some(format!("Added to {}", self.lists[index].name));
+    }
+
+    /// `(uid, entry)` of every todo in the daily, long term and named lists, giving the
+    /// ones that weren't synced before a UID
+    pub fn synced_entries(&mut self) -> Vec<(String, TodoData)> {
+        let (synced, assigned) = sync::caldav_entries(&mut self.lists);
+        if assigned > 0 {
+            self.mark_changed();
+        }
+        synced
+    }
+
+    /// Merges what the services answered into the lists and remembers the tasks they know
+    /// of, so the ones removed here aren't picked up again. False when one of them failed.
+    pub fn apply_sync(&mut self, synced : Synced) -> bool {
+        let (mut added, mut finished) = (0, 0);
+        let mut failed = synced.failed.map(|err| err.to_string());
+        if let Some(remote) = synced.caldav {
+            let (caldav_added, caldav_finished) = sync::apply_caldav(&mut self.lists, remote, date::Date::today());
+            added += caldav_added;
+            finished += caldav_finished;
+            let known = sync::caldav_known(&self.lists, synced.caldav_pushed);
+            if let Err(err) = self.file_manager.save_synced_ids("caldav", &known) {
+                failed.get_or_insert(err.to_string());
+            }
+        }
+        if let Some(outcome) = synced.todoist {
+            let (todoist_added, todoist_finished) = sync::apply_todoist(&mut self.lists, outcome, date::Date::today());
+            added += todoist_added;
+            finished += todoist_finished;
+            let known = sync::todoist_known(&self.lists, synced.todoist_pushed);
+            if let Err(err) = self.file_manager.save_synced_ids("todoist", &known) {
+                failed.get_or_insert(err.to_string());
+            }
+        }
+
+        if added + finished > 0 {
+            self.mark_changed();
+        }
+        if let Some(err) = failed {
+            self.status_message = Some(format!("Sync failed: {}", err));
+            return false;
+        }
+        self.status_message = Some(format!("Synced, {} added and {} finished elsewhere", added, finished));
+        true
+    }
+
+    /// Shows the list of `day` in its own tab, today is the daily list
+    pub fn open_day(&mut self, day : date::Date) {
+        let today = date::Date::today();
+        if day == today {
+            return self.switch_to(self.role_index(self.daily_role()));
+        }
+        if let Some(index) = self.lists.iter().position(|list| list.role == ListRole::Day(day)) {
+            return self.switch_to(index);
+        }
+
+        match self.file_manager.load_any_day(day) {
+            Ok(entries) => {
+                let index = self.add_list(&day.format(), ListRole::Day(day), entries.unwrap_or_default());
+                self.switch_to(index);
+                if day < today {
+                    self.status_message = Some(format!("{} is read-only", day.format()));
+                }
+            }
+            Err(err) => self.status_message = Some(format!("Failed to load {}: {}", day.format(), err))
+        }
+    }
+
+    /// Opens the day `text` names, like `tomorrow` or `+3`. A day ahead gets its file right away.
+    pub fn plan_day(&mut self, text : &str) {
+        let day = match date::Date::parse_relative(text, date::Date::today()) {
+            Some(day) => day,
+            None => {
+                self.status_message = Some(format!("Not a date: {}", text));
+                return;
+            }
+        };
+
+        if day > date::Date::today() {
+            if let Err(err) = self.file_manager.create_day(day) {
+                self.status_message = Some(format!("Failed to create {}: {}", day.format(), err));
+                return;
+            }
+        }
+        self.open_day(day);
+    }
+
+    /// Moves the selected daily todo and the ones nested below it to the day `text` names.
+    /// The day is opened in a tab when it isn't yet, so it's written with the
+    /// other lists and undo takes the todo's back out of it.
+    pub fn snooze(&mut self, text : &str) {
+        let today = date::Date::today();
+        let day = match date::Date::parse_relative(text, today) {
+            Some(day) if day > today => day,
+            Some(_) => {
+                self.status_message = Some("Snooze until a day after today".to_string());
+                return;
+            }
+            None => {
+                self.status_message = Some(format!("Not a date: {}", text));
+                return;
+            }
+        };
+        if self.load_failed {
+            self.status_message = Some("Saving is off since a list failed to load, nothing was snoozed".to_string());
+            return;
+        }
+
+        let start = self.target_row as usize;
+        let end = start + 1 + child_count(&self.active_list().entries, start);
+        let base = self.active_list().entries[start].indent;
+        let snoozed : Vec<TodoData> = self.active_list().entries[start..end]
+            .iter()
+            .map(|entry| TodoData { indent : entry.indent - base, marked : false, ..entry.clone() })
+            .collect();
+
+        let index = match self.lists.iter().position(|list| list.role == ListRole::Day(day)) {
+            Some(index) => index,
+            None => match self.file_manager.load_day(day) {
+                Ok(entries) => self.add_list(&day.format(), ListRole::Day(day), entries.unwrap_or_default()),
+                Err(err) => {
+                    self.status_message = Some(format!("Failed to snooze until {}: {}", day.format(), err));
+                    return;
+                }
+            }
+        };
+        self.save_snapshot_with(&[index]);
+        let entries = &mut self.lists[index].entries;
+        let position = if entries.last().is_some_and(|m| m.message.is_empty()) { entries.len() - 1 } else { entries.len() };
+        entries.splice(position..position, snoozed);
+
+        self.visual_anchor = None;
+        self.get_messages_mut().drain(start..end);
+        self.clamp_row();
+        self.clamp_column();
+        self.input = self.get_current_message();
+        self.status_message = Some(format!("Snoozed until {}", day.format()));
+    }
+
+    /// Starts the next day once the date changed while the app was open: the lists are
+    /// written, the daily one still to the day it was loaded for, then rolled over like on
+    /// startup and the daily list loaded again for today. A tab of the new day planned
+    /// ahead is closed, its todo's are on the daily list now. Waits while something is being
+    /// typed, and undo doesn't reach back into the day before.
+    pub fn start_new_day(&mut self) {
+        let today = date::Date::today();
+        if self.daily_date() >= today || self.load_failed || self.editing || self.searching {
+            return;
+        }
+
+        self.wait_for_saves();
+        let started = write_lists(&self.file_manager, &self.lists)
+            .and_then(|()| self.file_manager.start_day(&self.config))
+            .and_then(|()| self.file_manager.load_today());
+        let entries = match started {
+            Ok(entries) => entries,
+            Err(err) => {
+                self.status_message = Some(format!("Failed to start {}: {}", today.format(), err));
+                return;
+            }
+        };
+
+        self.save_view();
+        let active = self.active_list().id;
+        let daily = self.role_index(self.daily_role());
+        self.lists[daily].role = ListRole::Daily(today);
+        self.set_entries(ListRole::Daily(today), entries);
+        self.lists.retain(|list| list.role != ListRole::Day(today));
+        self.target_list = self.list_index(active).unwrap_or(self.role_index(ListRole::Daily(today)));
+        self.restore_view();
+
+        self.unsaved_since = None;
+        self.history.clear();
+        self.typing_row = None;
+        self.visual_anchor = None;
+        self.status_message = Some(format!("Started {}", today.format()));
+    }
+
+    /// Past days opened from the calendar can only be looked at
+    pub fn is_read_only(&self) -> bool {
+        matches!(self.active_list().role, ListRole::Day(day) if day < date::Date::today())
+    }
+
+    /// Closes the tab of a day opened from the calendar, writing it first when it's ahead
+    pub fn close_day(&mut self) {
+        self.wait_for_saves();
+        let list = self.lists.remove(self.target_list);
+        self.target_list -= 1;
+        self.restore_view();
+        if let Err(err) = write_lists(&self.file_manager, &[list]) {
+            self.status_message = Some(format!("Failed to save the day: {}", err));
+        }
+    }
+
+    /// (done, total) over the daily list, ignoring blank entries
+    pub fn daily_progress(&self) -> (usize, usize) {
+        progress(self.entries(self.daily_role()))
+    }
+
+    /// Messages of the active list without the trailing blank entry, one per line
+    pub fn messages_as_lines(&self) -> Vec<String> {
+        self.get_messages()
+            .iter()
+            .filter(|m| !m.message.is_empty())
+            .map(|m| m.message.clone())
+            .collect()
+    }
+
+    /// Rebuilds the active list from the output of an external filter. Lines matching an
+    /// existing entry keep its status, unknown lines become new todo's.
+    pub fn apply_filtered_lines(&mut self, lines : Vec<String>) {
+        // Committed journal entries can't be reordered, dropped or replaced
+        if self.is_journal() {
+            return;
+        }
+
+        self.save_snapshot();
+        let mut remaining = self.get_messages();
+        let mut new_messages : Vec<TodoData> = lines
+            .into_iter()
+            .filter(|line| !line.is_empty())
+            .map(|line| match remaining.iter().position(|m| m.message == line) {
+                Some(index) => remaining.remove(index),
+                None => TodoData { message : line, created_at : Some(date::now()), ..TodoData::default() }
+            })
+            .collect();
+        new_messages.push(TodoData::default());
+
+        *self.get_messages_mut() = new_messages;
+        self.target_row = 0;
+        self.target_column = 0;
+        self.input = self.get_current_message();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+    use std::path::PathBuf;
+    use std::sync::mpsc;
+    use std::thread;
+
+    use crate::test_dir::TestDir;
+    use crate::Priority;
+
+    fn todo(message : &str) -> TodoData {
+        TodoData {
+            message : message.to_string(),
+            ..TodoData::default()
+        }
+    }
+
+    fn daily_engine(entries : Vec<TodoData>) -> Engine {
+        let mut engine = Engine::default();
+        *engine.entries_mut(engine.daily_role()) = entries;
+        engine
+    }
+
+    fn messages_of(engine : &Engine) -> Vec<String> {
+        engine.get_messages().iter().map(|m| m.message.clone()).collect()
+    }
+
+    #[test]
+    fn sort_alphabetical_orders_case_insensitive_and_keeps_blank_last() {
+        let mut engine = daily_engine(vec![todo("banana"), todo("Apple"), todo("cherry"), todo("")]);
+
+        engine.sort_alphabetical();
+
+        assert_eq!(messages_of(&engine), vec!["Apple", "banana", "cherry", ""]);
+        // Cursor follows the entry that was selected ("banana")
+        assert_eq!(engine.target_row, 1);
+        assert_eq!(engine.input, "banana");
+    }
+
+    #[test]
+    fn outdenting_a_top_level_todo_leaves_no_undo_step() {
+        let mut engine = daily_engine(vec![todo("a"), TodoData { indent : 1, ..todo("b") }, todo("")]);
+
+        engine.outdent();
+        assert_eq!(engine.history.undo_steps(), 0);
+
+        engine.target_row = 1;
+        engine.outdent();
+        assert_eq!((engine.get_messages()[1].indent, engine.history.undo_steps()), (0, 1));
+    }
+
+    #[test]
+    fn undo_restores_removed_entry_and_redo_removes_it_again() {
+        let mut engine = daily_engine(vec![todo("first"), todo("second"), todo("")]);
+        engine.target_row = 1;
+
+        engine.remove_message();
+        assert_eq!(messages_of(&engine), vec!["first", ""]);
+
+        engine.undo();
+        assert_eq!(messages_of(&engine), vec!["first", "second", ""]);
+        assert_eq!(engine.target_row, 1);
+
+        engine.redo();
+        assert_eq!(messages_of(&engine), vec!["first", ""]);
+    }
+
+    #[test]
+    fn undo_reverts_a_run_of_typing_at_once() {
+        let mut engine = editing_engine("", 0);
+        engine.start_editing();
+        for c in "milk".chars() {
+            engine.add_char(c);
+        }
+        engine.commit_edit();
+
+        engine.undo();
+
+        assert_eq!(messages_of(&engine), vec!["", ""]);
+        assert!(engine.history.redo_steps() == 1 && engine.history.undo_steps() == 0);
+    }
+
+    #[test]
+    fn changes_are_saved_only_after_a_pause() {
+        let mut engine = daily_engine(vec![todo("first"), todo("")]);
+        assert_eq!(engine.autosave_due_in(), None);
+
+        engine.remove_message();
+        let due = engine.autosave_due_in().unwrap();
+        assert!(due > Duration::ZERO && due <= AUTOSAVE_DELAY);
+
+        // Not due yet, nothing is written
+        engine.autosave();
+        assert!(engine.unsaved_since.is_some() && engine.save_status == SaveStatus::Clean);
+    }
+
+    #[test]
+    fn blocked_todos_wait_until_the_other_one_is_done() {
+        let mut engine = daily_engine(vec![todo("send the report"), todo("")]);
+        engine.entries_mut(ListRole::LongTerm).insert(0, todo("Collect the numbers"));
+
+        engine.set_blocked_by("numbers");
+        assert_eq!(engine.status_message.as_deref(), Some("Waits for Collect the numbers"));
+        let id = engine.entries(ListRole::LongTerm)[0].id;
+        assert!(id.is_some());
+        assert_eq!(engine.get_messages()[0].blocked_by, id);
+        assert!(engine.open_blocker_ids().contains(&id.unwrap()));
+
+        engine.entries_mut(ListRole::LongTerm)[0].status = Status::Done;
+        assert!(engine.open_blocker_ids().is_empty());
+
+        engine.set_blocked_by("nothing like it");
+        assert_eq!(engine.status_message.as_deref(), Some("No todo matches nothing like it"));
+        engine.set_blocked_by("");
+        assert_eq!(engine.get_messages()[0].blocked_by, None);
+    }
+
+    #[test]
+    fn undoing_a_block_also_takes_back_the_id_the_blocker_got() {
+        let mut engine = daily_engine(vec![todo("send the report"), todo("")]);
+        engine.entries_mut(ListRole::LongTerm).insert(0, todo("Collect the numbers"));
+
+        engine.set_blocked_by("numbers");
+        engine.undo();
+
+        assert_eq!(engine.get_messages()[0].blocked_by, None);
+        assert_eq!(engine.entries(ListRole::LongTerm)[0].id, None);
+    }
+
+    #[test]
+    fn a_todo_cant_wait_for_one_that_waits_for_it() {
+        let mut engine = daily_engine(vec![todo("write"), todo("review"), todo("print"), todo("")]);
+        engine.set_blocked_by("review");
+        engine.target_row = 1;
+        engine.set_blocked_by("print");
+
+        engine.target_row = 2;
+        engine.set_blocked_by("write");
+
+        assert_eq!(engine.status_message.as_deref(), Some("write already waits for this todo"));
+        assert_eq!(engine.get_messages()[2].blocked_by, None);
+        assert_eq!(engine.history.undo_steps(), 2);
+    }
+
+    #[test]
+    fn open_line_and_insert_at_top_undo_in_one_step() {
+        let mut engine = daily_engine(vec![todo("parent"), TodoData { indent : 1, ..todo("child") }, todo("next"), todo("")]);
+
+        engine.open_line(true);
+        for c in "new".chars() {
+            engine.add_char(c);
+        }
+        engine.commit_edit();
+        assert_eq!(messages_of(&engine), vec!["parent", "child", "new", "next", ""]);
+        assert_eq!(engine.target_row, 2);
+
+        engine.undo();
+        assert_eq!(messages_of(&engine), vec!["parent", "child", "next", ""]);
+
+        engine.insert_at_top();
+        for c in "top".chars() {
+            engine.add_char(c);
+        }
+        engine.commit_edit();
+        assert_eq!(messages_of(&engine), vec!["top", "parent", "child", "next", ""]);
+        engine.undo();
+        assert_eq!(messages_of(&engine), vec!["parent", "child", "next", ""]);
+    }
+
+    #[test]
+    fn pasted_copies_get_ids_of_their_own_unless_they_were_cut() {
+        let mut engine = daily_engine(vec![todo("")]);
+        engine.register = vec![TodoData { id : Some(7), sync_uid : Some("todo_rust-7".to_string()), todoist_id : Some("42".to_string()), ..todo("blocker") }];
+        let ids = |entries : Vec<TodoData>| entries.into_iter().map(|m| (m.id, m.sync_uid, m.todoist_id)).collect::<Vec<_>>();
+
+        assert_eq!(ids(engine.pasted_register()), vec![(None, None, None)]);
+        engine.register_cut = true;
+        assert_eq!(ids(engine.pasted_register()), vec![(Some(7), Some("todo_rust-7".to_string()), Some("42".to_string()))]);
+        assert_eq!(ids(engine.pasted_register()), vec![(None, None, None)]);
+        engine.register_cut = true;
+        engine.undo();
+        assert_eq!(ids(engine.pasted_register()), vec![(None, None, None)]);
+    }
+
+    #[test]
+    fn move_to_other_list_takes_nested_todos_along_and_keeps_status() {
+        let mut engine = daily_engine(vec![
+            TodoData { indent : 1, status : Status::InProgress, ..todo("parent") },
+            TodoData { indent : 2, ..todo("child") },
+            todo("stays"),
+            todo(""),
+        ]);
+
+        engine.move_to_other_list();
+
+        assert_eq!(messages_of(&engine), vec!["stays", ""]);
+        let long_term = engine.entries(ListRole::LongTerm);
+        assert_eq!(long_term.iter().map(|m| m.message.as_str()).collect::<Vec<_>>(), vec!["parent", "child", ""]);
+        assert_eq!((long_term[0].indent, long_term[1].indent), (0, 1));
+        assert_eq!(long_term[0].status, Status::InProgress);
+
+        // Undo puts both lists back, redo moves the todo's again
+        engine.undo();
+        assert_eq!(messages_of(&engine), vec!["parent", "child", "stays", ""]);
+        assert_eq!(engine.entries(ListRole::LongTerm).len(), 1);
+        engine.redo();
+        assert_eq!(messages_of(&engine), vec!["stays", ""]);
+        assert_eq!(engine.entries(ListRole::LongTerm).len(), 3);
+    }
+
+    #[test]
+    fn moving_the_selection_to_someday_takes_nested_todos_along_and_can_be_undone() {
+        let mut engine = daily_engine(vec![todo("parent"), TodoData { indent : 1, ..todo("child") }, todo("stays"), todo("")]);
+
+        assert!(engine.move_selected_to(ListRole::Someday));
+        assert_eq!(messages_of(&engine), vec!["stays", ""]);
+        let someday : Vec<(&str, usize)> = engine.entries(ListRole::Someday).iter().map(|m| (m.message.as_str(), m.indent)).collect();
+        assert_eq!(&someday[someday.len() - 3..], &[("parent", 0), ("child", 1), ("", 0)]);
+
+        engine.undo();
+        assert_eq!(messages_of(&engine), vec!["parent", "child", "stays", ""]);
+        assert!(engine.entries(ListRole::Someday).iter().all(|m| m.message != "parent"));
+    }
+
+    #[test]
+    fn visible_indices_list_higher_priorities_first_with_their_children() {
+        let engine = daily_engine(vec![
+            todo("plain"),
+            TodoData { priority : Priority::Urgent, ..todo("urgent") },
+            TodoData { indent : 1, ..todo("urgent child") },
+            TodoData { priority : Priority::Low, ..todo("low") },
+            todo(""),
+        ]);
+
+        assert_eq!(engine.visible_indices(), vec![1, 2, 0, 3, 4]);
+    }
+
+    #[test]
+    fn folded_entries_hide_everything_nested_below_them() {
+        let mut engine = daily_engine(vec![
+            TodoData { folded : true, ..todo("parent") },
+            TodoData { indent : 1, ..todo("child") },
+            TodoData { indent : 2, ..todo("grandchild") },
+            todo("sibling"),
+            todo(""),
+        ]);
+
+        assert_eq!(engine.visible_indices(), vec![0, 3, 4]);
+
+        engine.toggle_fold();
+        assert_eq!(engine.visible_indices(), vec![0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn jump_to_match_continues_into_the_long_term_list_and_wraps() {
+        let mut engine = daily_engine(vec![todo("Buy milk"), todo("call mom"), todo("")]);
+        *engine.entries_mut(ListRole::LongTerm) = vec![todo("Learn to make cheese from MILK"), todo("")];
+        engine.search = Some("milk".to_string());
+
+        engine.jump_to_match(true);
+        assert!(engine.active_list().role == ListRole::LongTerm && engine.target_row == 0);
+
+        engine.jump_to_match(true);
+        assert!(matches!(engine.active_list().role, ListRole::Daily(_)) && engine.target_row == 0);
+    }
+
+    #[test]
+    fn list_names_must_be_unique_and_usable_as_file_names() {
+        let mut engine = Engine::default();
+        engine.add_list("Groceries", ListRole::Named, vec![]);
+
+        assert!(engine.validate_list_name("groceries", None).is_err());
+        assert!(engine.validate_list_name("Groceries", Some(engine.lists.len() - 1)).is_ok());
+        assert!(engine.validate_list_name("work/home", None).is_err());
+        assert!(engine.validate_list_name("", None).is_err());
+        assert_eq!(engine.unique_list_name("Groceries"), "Groceries 2");
+        assert_eq!(engine.unique_list_name("Books"), "Books");
+    }
+
+    #[test]
+    fn move_entry_swaps_with_neighbor_and_keeps_blank_last() {
+        let mut engine = daily_engine(vec![todo("first"), todo("second"), todo("")]);
+
+        engine.move_entry(false);
+        assert_eq!(messages_of(&engine), vec!["second", "first", ""]);
+        assert_eq!(engine.target_row, 1);
+
+        engine.move_entry(false);
+        assert_eq!(messages_of(&engine), vec!["second", "first", ""]);
+    }
+
+    fn editing_engine(message : &str, column : i32) -> Engine {
+        Engine {
+            input : message.to_string(),
+            editing : true,
+            target_column : column,
+            ..daily_engine(vec![todo(message), todo("")])
+        }
+    }
+
+    #[test]
+    fn remove_char_deletes_whole_combining_sequence() {
+        // "e" + COMBINING ACUTE ACCENT renders as a single "é"
+        let mut engine = editing_engine("cafe\u{301}!", 4);
+
+        engine.remove_char();
+
+        assert_eq!(engine.input, "caf!");
+        assert_eq!(engine.target_column, 3);
+    }
+
+    #[test]
+    fn remove_char_deletes_whole_zwj_emoji() {
+        let family = "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}";
+        let mut engine = editing_engine(&format!("a{}b", family), 2);
+
+        engine.remove_char();
+
+        assert_eq!(engine.input, "ab");
+        assert_eq!(engine.target_column, 1);
+    }
+
+    #[test]
+    fn add_char_inserts_after_grapheme_clusters() {
+        let flag = "\u{1F1E7}\u{1F1EA}";
+        let mut engine = editing_engine(&format!("{}x", flag), 1);
+
+        engine.add_char('y');
+
+        assert_eq!(engine.input, format!("{}yx", flag));
+        assert_eq!(engine.target_column, 2);
+    }
+
+    #[test]
+    fn add_char_combining_mark_joins_previous_grapheme() {
+        let mut engine = editing_engine("e", 1);
+
+        engine.add_char('\u{301}');
+
+        assert_eq!(engine.input, "e\u{301}");
+        assert_eq!(engine.target_column, 1);
+    }
+
+    #[test]
+    fn editing_mid_list_writes_back_on_enter_and_esc_discards() {
+        let mut engine = daily_engine(vec![todo("first"), todo("second"), todo("")]);
+        engine.target_row = 1;
+        engine.input = "stale".to_string();
+        engine.target_column = 3;
+
+        engine.start_editing();
+        assert_eq!(engine.input, "second");
+        engine.append();
+        for _ in 0..5 {
+            engine.move_cursor(MoveCursorOperation::Right);
+        }
+        engine.add_char('!');
+        assert_eq!((engine.input.as_str(), engine.get_current_message().as_str()), ("second!", "second"));
+        engine.new_line();
+        assert_eq!(messages_of(&engine), vec!["first", "second!", ""]);
+
+        engine.start_editing();
+        engine.remove_char();
+        engine.add_char('?');
+        engine.cancel_edit();
+        assert_eq!(messages_of(&engine), vec!["first", "second!", ""]);
+        assert_eq!(engine.input, "second!");
+    }
+
+    #[test]
+    fn enter_is_one_undo_step_with_the_typing_and_none_when_nothing_changed() {
+        let mut engine = daily_engine(vec![todo("")]);
+
+        engine.start_editing();
+        for c in "milk".chars() {
+            engine.add_char(c);
+        }
+        engine.new_line();
+        assert_eq!(messages_of(&engine), vec!["milk", ""]);
+        assert_eq!(engine.history.undo_steps(), 1);
+
+        engine.target_row = 0;
+        engine.start_editing();
+        engine.new_line();
+        assert_eq!(engine.history.undo_steps(), 1);
+        assert_eq!(engine.history.redo_steps(), 0);
+
+        engine.undo();
+        assert_eq!(messages_of(&engine), vec![""]);
+    }
+
+    #[test]
+    fn move_cursor_steps_over_grapheme_clusters() {
+        let family = "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}";
+        let mut engine = editing_engine(&format!("{}ab", family), 0);
+
+        engine.move_cursor(MoveCursorOperation::Right);
+        assert_eq!(engine.target_column, 1);
+        engine.move_cursor(MoveCursorOperation::Right);
+        engine.move_cursor(MoveCursorOperation::Right);
+        engine.move_cursor(MoveCursorOperation::Right);
+        // Clamped behind the last of the three visible characters
+        assert_eq!(engine.target_column, 3);
+
+        engine.move_cursor(MoveCursorOperation::LineStart);
+        assert_eq!(engine.target_column, 0);
+        engine.move_cursor(MoveCursorOperation::LineEnd);
+        engine.add_char('c');
+        assert_eq!(engine.input, format!("{}abc", family));
+    }
+
+    #[test]
+    fn change_target_mode_clamps_column_to_new_line() {
+        let mut engine = Engine {
+            input : "a fairly long daily todo".to_string(),
+            target_column : 15,
+            ..daily_engine(vec![todo("a fairly long daily todo"), todo("")])
+        };
+        *engine.entries_mut(ListRole::LongTerm) = vec![todo("ab"), todo("")];
+        // The long term line got shorter since the cursor was last there
+        engine.lists[1].view = ListView { row : 0, column : 10, offset : 0 };
+
+        engine.change_target_mode();
+
+        assert!(engine.active_list().role == ListRole::LongTerm);
+        assert_eq!(engine.input, "ab");
+        assert_eq!(engine.target_column, 1);
+
+        // Editing right away must not index past the end of the shorter line
+        engine.add_char('!');
+        assert_eq!(engine.input, "a!b");
+    }
+
+    #[test]
+    fn autosave_hands_the_lists_to_the_save_worker() {
+        let (sender, requests) = mpsc::channel();
+        let (answers, results) = mpsc::channel();
+        let mut engine = daily_engine(vec![TodoData { message : "write report".to_string(), ..TodoData::default() }]);
+        engine.saver = Some(SaveWorker { sender, results, handle : thread::spawn(|| {}), pending : 0 });
+        engine.unsaved_since = Instant::now().checked_sub(AUTOSAVE_DELAY);
+
+        engine.autosave();
+        assert_eq!(engine.save_status, SaveStatus::Saving);
+        let lists : Vec<TodoList> = requests.try_recv().unwrap();
+        assert_eq!(lists[0].entries[0].message, "write report");
+
+        answers.send(Err(file_manager::Error { path : PathBuf::from("data/long_term.todo"), source : io::Error::other("disk full") })).unwrap();
+        engine.poll_saves();
+        assert_eq!(engine.save_status, SaveStatus::Failed("data/long_term.todo: disk full".to_string()));
+    }
+
+    #[test]
+    fn planning_a_day_ahead_creates_its_file() {
+        let data_dir = TestDir::new("plan");
+        let mut engine = daily_engine(vec![TodoData::default()]);
+        engine.file_manager = FileManager::new(data_dir.path().to_path_buf());
+        let tomorrow = date::Date::today().add_days(1);
+
+        engine.plan_day("tomorrow");
+
+        assert_eq!(engine.active_list().role, ListRole::Day(tomorrow));
+        assert!(!engine.is_read_only());
+        assert_eq!(engine.file_manager.days_with_files().unwrap(), vec![tomorrow]);
+        engine.plan_day("someday");
+        assert_eq!(engine.status_message.as_deref(), Some("Not a date: someday"));
+    }
+
+    #[test]
+    fn saving_after_midnight_writes_the_day_the_list_was_loaded_for_and_rolls_over_once() {
+        let data_dir = TestDir::new("midnight");
+        let today = date::Date::today();
+        let yesterday = today.add_days(-1);
+        let mut engine = daily_engine(vec![todo("Call the bank"), TodoData { status : Status::Done, ..todo("Buy milk") }, todo("")]);
+        engine.file_manager = FileManager::new(data_dir.path().to_path_buf());
+        let daily = engine.role_index(engine.daily_role());
+        engine.lists[daily].role = ListRole::Daily(yesterday);
+        let planned = engine.add_list(&today.format(), ListRole::Day(today), vec![todo("Planned last night")]);
+        engine.switch_to(planned);
+
+        // A save that happens before the next tick still goes to yesterday's file
+        engine.save_lists().unwrap();
+        assert_eq!(engine.file_manager.load_day(yesterday).unwrap().unwrap().len(), 2);
+        assert_eq!(messages_of_file(&engine.file_manager, today), vec!["Planned last night"]);
+
+        engine.start_new_day();
+
+        assert_eq!(engine.daily_role(), ListRole::Daily(today));
+        assert!(matches!(engine.active_list().role, ListRole::Daily(_)));
+        assert!(engine.lists.iter().all(|list| list.role != ListRole::Day(today)));
+        assert_eq!(messages_of(&engine), vec!["Planned last night", "Call the bank", ""]);
+        engine.save_lists().unwrap();
+        engine.start_new_day();
+        assert_eq!(messages_of_file(&engine.file_manager, today), vec!["Planned last night", "Call the bank"]);
+        assert!(engine.file_manager.load_day(yesterday).unwrap().is_none());
+    }
+
+    fn messages_of_file(file_manager : &FileManager, day : date::Date) -> Vec<String> {
+        file_manager.load_day(day).unwrap().unwrap_or_default().into_iter().map(|m| m.message).collect()
+    }
+
+    #[test]
+    fn journal_entries_and_their_times_survive_a_reload() {
+        let data_dir = TestDir::new("journal");
+        let mut engine = daily_engine(vec![todo("")]);
+        engine.file_manager = FileManager::new(data_dir.path().to_path_buf());
+        engine.switch_to(engine.role_index(ListRole::Journal));
+        engine.start_editing();
+        for c in "standup went long".chars() {
+            engine.add_char(c);
+        }
+        engine.new_line();
+        let written = engine.get_messages()[0].created_at;
+        assert!(written.is_some());
+        engine.save_lists().unwrap();
+
+        let mut reloaded = daily_engine(vec![]);
+        reloaded.file_manager = FileManager::new(data_dir.path().to_path_buf());
+        reloaded.load_lists().unwrap();
+        let journal = reloaded.entries(ListRole::Journal);
+        assert_eq!(journal.iter().map(|m| m.message.as_str()).collect::<Vec<_>>(), vec!["standup went long", ""]);
+        assert_eq!(journal[0].created_at, written);
+    }
+
+    #[test]
+    fn filter_output_leaves_the_journal_alone() {
+        let mut engine = Engine::default();
+        engine.switch_to(engine.role_index(ListRole::Journal));
+        *engine.get_messages_mut() = vec![TodoData { created_at : Some(1664280000), ..todo("standup") }, todo("")];
+
+        engine.apply_filtered_lines(vec!["replaced".to_string()]);
+        assert_eq!(messages_of(&engine), vec!["standup", ""]);
+        assert_eq!(engine.history.undo_steps(), 0);
+    }
+
+    #[test]
+    fn snoozing_moves_the_todo_into_a_later_day_file() {
+        let data_dir = TestDir::new("snooze");
+        let mut engine = daily_engine(vec![todo("call the bank"), TodoData { indent : 1, ..todo("find the number") }, todo("stay"), todo("")]);
+        engine.file_manager = FileManager::new(data_dir.path().to_path_buf());
+        let today = date::Date::today();
+
+        engine.snooze("today");
+        assert_eq!(engine.status_message.as_deref(), Some("Snooze until a day after today"));
+        engine.snooze("next monday");
+
+        let monday = date::Date::parse_relative("mon", today).unwrap();
+        assert_eq!(monday.weekday(), 0);
+        assert!((1..=7).contains(&(monday.to_days() - today.to_days())));
+        assert_eq!(messages_of(&engine), vec!["stay", ""]);
+        engine.save_lists().unwrap();
+        let snoozed = engine.file_manager.load_day(monday).unwrap().unwrap();
+        assert_eq!(snoozed.iter().map(|m| (m.message.as_str(), m.indent)).collect::<Vec<_>>(),
+            vec![("call the bank", 0), ("find the number", 1)]);
+
+        engine.undo();
+        assert_eq!(messages_of(&engine), vec!["call the bank", "find the number", "stay", ""]);
+        engine.save_lists().unwrap();
+        assert!(engine.file_manager.load_day(monday).unwrap().unwrap_or_default().iter().all(|m| m.message.is_empty()));
+    }
+
+    #[test]
+    fn opened_engine_changes_lists_by_role_and_saves_them() {
+        let data_dir = TestDir::new("open");
+        let mut engine = Engine::open(data_dir.path()).unwrap();
+        let daily = engine.daily_role();
+
+        assert_eq!(engine.add(daily, " Buy milk "), 0);
+        assert_eq!(engine.add(daily, "Call mum"), 1);
+        engine.add(ListRole::LongTerm, "Learn Rust");
+        engine.toggle(daily, 1);
+        engine.move_todo(daily, 1, 0);
+        let names = |engine : &Engine, role| engine.list(role).iter().map(|m| m.message.clone()).collect::<Vec<_>>();
+        assert_eq!(names(&engine, daily), vec!["Call mum", "Buy milk"]);
+        assert_eq!(engine.list(daily)[0].status, Status::Done);
+        // The blank entry to type into stays last
+        assert!(engine.entries(daily).last().unwrap().message.is_empty());
+
+        assert_eq!(engine.remove(daily, 0).message, "Call mum");
+        engine.undo();
+        assert_eq!(names(&engine, daily), vec!["Call mum", "Buy milk"]);
+        engine.save().unwrap();
+
+        let reopened = Engine::open(data_dir.path()).unwrap();
+        assert_eq!(names(&reopened, daily), vec!["Call mum", "Buy milk"]);
+        assert_eq!(names(&reopened, ListRole::LongTerm), vec!["Learn Rust"]);
+    }
+}
@@ -1,48 +1,1712 @@
-use std::fs::File;
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io;
 use std::io::prelude::*;
 use std::fs;
+use std::path::{Path, PathBuf};
 
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+
+use crate::config::{Config, StartupMode, StorageFormat, DEFAULT_PROFILE};
+use crate::date;
+use crate::json;
+use crate::sort::{self, SortKey};
+use crate::{Priority, Recurrence, Status, TodoData};
+
+/// Where the lists were kept before the data directory could be configured, relative to
+/// the working directory. Still used when it exists, so existing lists aren't left behind.
+const LEGACY_DATA_DIRECTORY : &str = "./data";
+/// Environment variable naming the data directory, overridden by `--data-dir`
+const DATA_DIR_VARIABLE : &str = "TODO_RUST_DATA_DIR";
+const ARCHIVE_DIRECTORY : &str = "archive";
+const ROLLOVER_LOG : &str = "rollover.log";
+const SUMMARY_LOG : &str = "summary.log";
+const SOMEDAY_FILE : &str = "someday.todo";
+const JOURNAL_FILE : &str = "journal.todo";
+const LONG_TERM_FILE : &str = "long_term.todo";
+/// Default ordering of the lists that have one, a `<list> = <keys>` line each
+const SORT_ORDERS_FILE : &str = "sort_orders";
+/// Ids of the tasks each service was last synced with, a `<service> <id>` line each
+const SYNCED_FILE : &str = "synced";
+/// Lists created by the user, one `<name>.todo` each
+const LISTS_DIRECTORY : &str = "lists";
+/// Deleted lists, kept until purged so they can be reopened
+const CLOSED_DIRECTORY : &str = "closed";
+/// Data directories of the profiles other than the default one, one per profile
+const PROFILES_DIRECTORY : &str = "profiles";
+/// Spaces per indentation level in a `.todo` file
+const INDENT_WIDTH : usize = 2;
+/// Salt of the passphrase and a value sealed with the key, to tell a wrong passphrase apart
+const KEY_FILE : &str = "encryption.key";
+const KEY_MAGIC : &[u8] = b"todo_rust key 1\n";
+const KEY_CHECK : &[u8] = b"todo_rust";
+const SALT_LEN : usize = 16;
+/// PBKDF2 rounds for a newly chosen passphrase, the key file records the count it was made with
+const KEY_ITERATIONS : u32 = 200_000;
+/// Start of every encrypted list file, followed by the sealed contents
+const ENCRYPTED_MAGIC : &[u8] = b"todo_rust encrypted 1\n";
+/// Bytes of the random nonce in front of every sealed message
+const NONCE_LEN : usize = 12;
+
+/// A file operation that failed, together with the file it failed on
+#[derive(Debug)]
+pub struct Error {
+    pub path : PathBuf,
+    pub source : io::Error,
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.source)
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(err : Error) -> io::Error {
+        io::Error::new(err.source.kind(), err.to_string())
+    }
+}
+
+/// Attaches the path an io operation worked on to its error
+trait OnPath<T> {
+    fn on(self, path : &Path) -> Result<T>;
+}
+
+impl<T> OnPath<T> for io::Result<T> {
+    fn on(self, path : &Path) -> Result<T> {
+        self.map_err(|source| Error { path : path.to_path_buf(), source })
+    }
+}
+
+/// What happened to the previous day's list when a new day started
+pub struct RolloverSummary {
+    /// The day that was started
+    pub date : String,
+    pub carried_over : usize,
+    pub completed : usize,
+    /// Number of earlier day files moved into the archive
+    pub archived : usize,
+}
+
+#[derive(Clone)]
 pub struct FileManager {
-    pub data : String
+    data_dir : PathBuf,
+    format : StorageFormat,
+    /// Set once the data directory was unlocked, list files are encrypted with it
+    key : Option<Key>,
+}
+
+impl Default for FileManager {
+    fn default() -> Self {
+        Self::new(PathBuf::from(LEGACY_DATA_DIRECTORY))
+    }
 }
 
 impl FileManager {
-    pub fn new() -> Self {
+    pub fn new(data_dir : PathBuf) -> Self {
         Self {
-            data : String::default()
+            data_dir,
+            format : StorageFormat::Todo,
+            key : None
         }
     }
 
-    pub fn initialize(&self) {
-        // TODO[rsmekens]: read all files from specific directory
+    /// The lists of `config`'s profile. The default profile uses `data_dir` itself, the
+    /// others their own `data_dir` setting or `profiles/<name>` inside it.
+    pub fn for_profile(data_dir : &Path, config : &Config) -> Self {
+        let directory = match &config.data_dir {
+            Some(dir) => Self::expand_path(dir),
+            None if config.profile == DEFAULT_PROFILE => data_dir.to_path_buf(),
+            None => data_dir.join(PROFILES_DIRECTORY).join(&config.profile)
+        };
+        Self::new(directory).with_format(config.storage)
+    }
+
+    /// Reads and writes the list files in `format` from now on
+    pub fn with_format(self, format : StorageFormat) -> Self {
+        Self { format, ..self }
+    }
+
+    /// Whether a passphrase was chosen for the data directory yet
+    pub fn has_passphrase(&self) -> bool {
+        self.data_dir.join(KEY_FILE).exists()
+    }
+
+    /// A copy that encrypts the list files it writes, `None` when `passphrase` is wrong. The
+    /// first passphrase given is the one the data directory is locked with from then on.
+    /// Files that were written before encryption was turned on are still read.
+    pub fn unlock(&self, passphrase : &str) -> Result<Option<FileManager>> {
+        let path = self.data_dir.join(KEY_FILE);
+        let stored = match fs::read(&path) {
+            Ok(stored) => stored,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                fs::create_dir_all(&self.data_dir).on(&self.data_dir)?;
+                let salt = random_bytes(SALT_LEN).on(&path)?;
+                let key = derive_key(passphrase, &salt, KEY_ITERATIONS);
+                let mut contents = KEY_MAGIC.to_vec();
+                contents.extend_from_slice(&KEY_ITERATIONS.to_le_bytes());
+                contents.extend_from_slice(&salt);
+                contents.extend(seal(&key, KEY_CHECK).on(&path)?);
+                self.write_atomically(&path, contents)?;
+                return Ok(Some(Self { key : Some(key), ..self.clone() }));
+            }
+            Err(err) => return Err(err).on(&path)
+        };
+
+        let malformed = || io::Error::new(io::ErrorKind::InvalidData, "not a key file");
+        let rest = stored.strip_prefix(KEY_MAGIC).filter(|rest| rest.len() > 4 + SALT_LEN).ok_or_else(malformed).on(&path)?;
+        let (iterations, rest) = rest.split_at(4);
+        let (salt, check) = rest.split_at(SALT_LEN);
+        let iterations = u32::from_le_bytes(iterations.try_into().expect("split off four bytes"));
+        let key = derive_key(passphrase, salt, iterations);
+        if open(&key, check).as_deref() != Some(KEY_CHECK) {
+            return Ok(None);
+        }
+        Ok(Some(Self { key : Some(key), ..self.clone() }))
+    }
+
+    /// The data directory: `flag` from `--data-dir`, then `$TODO_RUST_DATA_DIR`, then
+    /// `./data` when it exists, then `$XDG_DATA_HOME/todo_rust` (`~/.local/share/todo_rust`)
+    pub fn resolve_data_dir(flag : Option<&str>) -> PathBuf {
+        if let Some(dir) = flag.filter(|dir| !dir.is_empty()) {
+            return Self::expand_path(dir);
+        }
+        match std::env::var(DATA_DIR_VARIABLE) {
+            Ok(dir) if !dir.is_empty() => return Self::expand_path(&dir),
+            _ => {}
+        }
+        if Path::new(LEGACY_DATA_DIRECTORY).is_dir() {
+            return PathBuf::from(LEGACY_DATA_DIRECTORY);
+        }
+
+        let data_home = match (std::env::var("XDG_DATA_HOME"), std::env::var("HOME")) {
+            (Ok(dir), _) if !dir.is_empty() => PathBuf::from(dir),
+            (_, Ok(home)) => Path::new(&home).join(".local").join("share"),
+            // Without a home directory the working directory is all there is
+            _ => return PathBuf::from(LEGACY_DATA_DIRECTORY)
+        };
+        data_home.join("todo_rust")
+    }
+
+    /// Creates the data directory on the first run, then archives and rolls over day files.
+    /// With `archive_after_days` the rolled over files stay in the data directory until
+    /// they're old enough to be archived.
+    pub fn initialize(&self, config : &Config) -> Result<()> {
+        fs::create_dir_all(&self.data_dir).on(&self.data_dir)?;
+
+        if config.startup == StartupMode::Fresh {
+            self.archive_today()?;
+        }
 
-        self.load_files();
+        self.start_day(config)
     }
 
-    fn load_files(&self) {
-        let paths = fs::read_dir("./data").unwrap();
+    /// Rolls the earlier day files over into today's and archives the ones old enough, run
+    /// on startup and when the date changes while the app is open
+    pub fn start_day(&self, config : &Config) -> Result<()> {
+        self.roll_over(config.archive_after_days.is_some())?;
 
-        for path in  paths {
-            println!("Name: {}", path.unwrap().path().display());
+        if let Some(days) = config.archive_after_days {
+            self.archive_older_than(days)?;
         }
 
-        let file_to_open = String::from("data/2022_09_27.todo");
-        let file_open= File::open(&file_to_open);
-        let mut file_open_result;
-        match file_open {
-            Result::Ok(val) => { 
-                file_open_result = val;
-                println!("Successfully loaded file {}", file_to_open);
+        Ok(())
+    }
+
+    /// Moves today's file (if there is one) into the archive directory so the day starts
+    /// over. Nothing is deleted, the archived copy gets a timestamp suffix. Open todo's
+    /// written before today, the ones planned or snoozed into today and the ones carried
+    /// over, stay on the new list together with the entries nested below them.
+    fn archive_today(&self) -> Result<()> {
+        let today = date::Date::today();
+        let stem = today.file_stem();
+        let path = self.data_dir.join(format!("{}.todo", stem));
+        let entries = match self.load_day(today)? {
+            Some(entries) => entries,
+            None => return Ok(())
+        };
+
+        let mut kept = vec![];
+        let mut keep = false;
+        for entry in entries {
+            if entry.indent == 0 {
+                let written_before = entry.created_at.is_some_and(|created_at| date::DateTime::from_timestamp(created_at).date < today);
+                keep = entry.status != Status::Done && written_before;
             }
-            Result::Err(err) => {
-                println!("Failed to load file! {:?}", err);
-                return;
+            if keep {
+                kept.push(entry);
             }
         }
 
-        let mut contents = String::new();
-        file_open_result.read_to_string(&mut contents).unwrap();
+        let archive = self.data_dir.join(ARCHIVE_DIRECTORY);
+        fs::create_dir_all(&archive).on(&archive)?;
+        fs::rename(&path, archive.join(format!("{}.{}.todo", stem, date::now()))).on(&path)?;
+        if kept.is_empty() {
+            return Ok(());
+        }
+        self.save_today(&kept)
+    }
+
+    /// Appends `entries` to `<stem>.todo` in the archive directory, the file is created the
+    /// first time
+    pub fn archive_entries(&self, stem : &str, entries : &[TodoData]) -> Result<()> {
+        let archive = self.data_dir.join(ARCHIVE_DIRECTORY);
+        fs::create_dir_all(&archive).on(&archive)?;
+        let path = archive.join(format!("{}.todo", stem));
+        let mut archived = match self.read_list(&path) {
+            Ok(archived) => archived,
+            Err(err) if err.source.kind() == io::ErrorKind::NotFound => vec![],
+            Err(err) => return Err(err)
+        };
+        archived.extend_from_slice(entries);
+        self.write_list_file(&path, &archived)
+    }
+
+    /// Moves every day file dated more than `days` days ago into the archive directory
+    fn archive_older_than(&self, days : u64) -> Result<()> {
+        let cutoff = date::Date::today().add_days(-(days as i64));
+        let archive = self.data_dir.join(ARCHIVE_DIRECTORY);
+
+        for (day, path) in self.day_files()? {
+            if let Some(file_name) = path.file_name() {
+                if day < cutoff {
+                    fs::create_dir_all(&archive).on(&archive)?;
+                    fs::rename(&path, archive.join(file_name)).on(&path)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Carries the unfinished todo's of every earlier day file over to the end of today's
+    /// list, together with the next occurrence of recurring todo's that were finished. The
+    /// earlier file keeps only its done entries, so it has the history of what got finished
+    /// that day. It goes into the archive directory, or stays where it is with `keep_files`.
+    /// Today's list is written before any earlier file changes, so a failure in between
+    /// can't lose a carried todo.
+    fn roll_over(&self, keep_files : bool) -> Result<()> {
+        let today = date::Date::today();
+        let mut previous : Vec<(date::Date, PathBuf)> = self.day_files()?
+            .into_iter()
+            .filter(|(day, _)| *day < today)
+            .collect();
+        if previous.is_empty() {
+            return Ok(());
+        }
+        previous.sort_by_key(|(day, _)| *day);
+
+        let mut summary = RolloverSummary {
+            date : today.format(),
+            carried_over : 0,
+            completed : 0,
+            archived : 0,
+        };
+        let mut carried = vec![];
+        let mut rolled_over = vec![];
+
+        for (day, path) in previous {
+            let (open, mut done) : (Vec<TodoData>, Vec<TodoData>) = self.read_list(&path)?
+                .into_iter()
+                .partition(|entry| entry.status != Status::Done);
+            // A file kept by an earlier rollover has nothing left to carry
+            if keep_files && open.is_empty() && done.iter().all(|entry| entry.recurrence.is_none()) {
+                continue;
+            }
+
+            for entry in &mut done {
+                if let Some(next) = entry.next_occurrence(today) {
+                    entry.recurrence = None;
+                    carried.push(next);
+                }
+            }
+
+            summary.carried_over += open.len();
+            summary.completed += done.len();
+            let days_late = (today.to_days() - day.to_days()) as u32;
+            carried.extend(open.into_iter().map(|entry| TodoData { carried_days : entry.carried_days + days_late, ..entry }));
+            rolled_over.push((day, path, done));
+        }
+        if rolled_over.is_empty() {
+            return Ok(());
+        }
+
+        let mut entries = self.load_today()?;
+        entries.extend(carried);
+        self.save_today(&entries)?;
+
+        let archive = self.data_dir.join(ARCHIVE_DIRECTORY);
+        for (day, path, done) in rolled_over {
+            if keep_files {
+                self.write_list_file(&path, &done)?;
+                continue;
+            }
+
+            fs::create_dir_all(&archive).on(&archive)?;
+            let mut archived = archive.join(format!("{}.todo", day.file_stem()));
+            if archived.exists() {
+                archived = archive.join(format!("{}.{}.todo", day.file_stem(), date::now()));
+            }
+            self.write_list_file(&archived, &done)?;
+            fs::remove_file(&path).on(&path)?;
+            summary.archived += 1;
+        }
+        self.log_rollover(&summary)
+    }
+
+    /// Day files in the data directory and the archive together with the day they belong to
+    fn files_by_day(&self) -> Result<Vec<(date::Date, PathBuf)>> {
+        let mut days = vec![];
+        for path in self.todo_files(&self.data_dir.join(ARCHIVE_DIRECTORY))? {
+            // Archived copies can carry a timestamp suffix, `2022_09_27.1664280000.todo`
+            let day = path.file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| stem.split('.').next())
+                .and_then(date::Date::parse_file_stem);
+            if let Some(day) = day {
+                days.push((day, path));
+            }
+        }
+        days.extend(self.day_files()?);
+        Ok(days)
+    }
+
+    /// Every day that has a file, in the data directory or the archive
+    pub fn days_with_files(&self) -> Result<Vec<date::Date>> {
+        Ok(self.files_by_day()?.into_iter().map(|(day, _)| day).collect())
+    }
+
+    /// Entries of `day` wherever its files are, `None` when there are none. A day archived
+    /// more than once has the entries of every copy.
+    pub fn load_any_day(&self, day : date::Date) -> Result<Option<Vec<TodoData>>> {
+        let mut entries = None;
+        for (_, path) in self.files_by_day()?.into_iter().filter(|(file_day, _)| *file_day == day) {
+            entries.get_or_insert_with(Vec::new).extend(self.read_list(&path)?);
+        }
+        Ok(entries)
+    }
+
+    /// Day files in the data directory together with the day they belong to
+    fn day_files(&self) -> Result<Vec<(date::Date, PathBuf)>> {
+        let mut days = vec![];
+        for path in self.todo_files(&self.data_dir)? {
+            let day = path.file_stem().and_then(|stem| stem.to_str()).and_then(date::Date::parse_file_stem);
+            if let Some(day) = day {
+                days.push((day, path));
+            }
+        }
+
+        Ok(days)
+    }
+
+    /// Number of todo's finished on each of the `days` days up to and including today,
+    /// oldest first. Earlier days are counted from the archive the rollover moved them into.
+    pub fn completion_history(&self, days : u32) -> Result<Vec<(date::Date, usize)>> {
+        let today = date::Date::today();
+        let first = today.add_days(1 - days as i64);
+        let mut history : Vec<(date::Date, usize)> = (0..days as i64).map(|offset| (first.add_days(offset), 0)).collect();
+
+        for (day, path) in self.files_by_day()? {
+            if (first..=today).contains(&day) {
+                let done = self.read_list(&path)?
+                    .iter()
+                    .filter(|entry| entry.status == Status::Done)
+                    .count();
+                history[(day.to_days() - first.to_days()) as usize].1 += done;
+            }
+        }
+
+        Ok(history)
+    }
+
+    /// Entries of the day file for `day`, `None` when there is no file for that day
+    pub fn load_day(&self, day : date::Date) -> Result<Option<Vec<TodoData>>> {
+        let path = self.data_dir.join(format!("{}.todo", day.file_stem()));
+        match self.read_list(&path) {
+            Ok(entries) => Ok(Some(entries)),
+            Err(err) if err.source.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err)
+        }
+    }
+
+    /// Today's daily list, empty when nothing was written today yet
+    pub fn load_today(&self) -> Result<Vec<TodoData>> {
+        Ok(self.load_day(date::Date::today())?.unwrap_or_default())
+    }
+
+    pub fn save_today(&self, entries : &[TodoData]) -> Result<()> {
+        self.save_day(date::Date::today(), entries)
+    }
+
+    /// Writes the day file of `day`, used to plan days ahead
+    pub fn save_day(&self, day : date::Date, entries : &[TodoData]) -> Result<()> {
+        self.save_file(&format!("{}.todo", day.file_stem()), entries)
+    }
+
+    /// Creates an empty day file for `day` unless it already has one, so a day planned
+    /// ahead shows up in the calendar before anything is written to it
+    pub fn create_day(&self, day : date::Date) -> Result<()> {
+        let path = self.data_dir.join(format!("{}.todo", day.file_stem()));
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(_) => Ok(()),
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => Ok(()),
+            Err(err) => Err(err).on(&path)
+        }
+    }
+
+    /// The long term list, which isn't tied to a day
+    pub fn load_long_term(&self) -> Result<Vec<TodoData>> {
+        self.load_file(LONG_TERM_FILE)
+    }
+
+    pub fn save_long_term(&self, entries : &[TodoData]) -> Result<()> {
+        self.save_file(LONG_TERM_FILE, entries)
+    }
+
+    /// Deferred "someday/maybe" todo's, empty when none were deferred yet
+    pub fn load_someday(&self) -> Result<Vec<TodoData>> {
+        self.load_file(SOMEDAY_FILE)
+    }
+
+    pub fn save_someday(&self, entries : &[TodoData]) -> Result<()> {
+        self.save_file(SOMEDAY_FILE, entries)
+    }
+
+    /// The journal's log lines, each committed one with the time it was written
+    pub fn load_journal(&self) -> Result<Vec<TodoData>> {
+        self.load_file(JOURNAL_FILE)
+    }
+
+    pub fn save_journal(&self, entries : &[TodoData]) -> Result<()> {
+        self.save_file(JOURNAL_FILE, entries)
+    }
+
+    /// Every list created by the user as `(name, entries)`, sorted by name
+    pub fn load_named_lists(&self) -> Result<Vec<(String, Vec<TodoData>)>> {
+        let mut lists = vec![];
+        for path in self.todo_files(&self.data_dir.join(LISTS_DIRECTORY))? {
+            if let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) {
+                lists.push((name.to_string(), self.read_list(&path)?));
+            }
+        }
+
+        lists.sort_by(|(a, _), (b, _)| a.cmp(b));
+        Ok(lists)
+    }
+
+    pub fn save_named_list(&self, name : &str, entries : &[TodoData]) -> Result<()> {
+        self.save_file(&format!("{}/{}.todo", LISTS_DIRECTORY, name), entries)
+    }
+
+    pub fn rename_named_list(&self, old_name : &str, new_name : &str) -> Result<()> {
+        let lists = self.data_dir.join(LISTS_DIRECTORY);
+        let old_path = lists.join(format!("{}.todo", old_name));
+        if !old_path.exists() {
+            return Ok(());
+        }
+        fs::rename(&old_path, lists.join(format!("{}.todo", new_name))).on(&old_path)
+    }
+
+    /// Moves a deleted list into the closed stash. Its file name starts with the time it
+    /// was closed so the most recently closed list can be found again. A list closed in
+    /// the same second as the last one is counted a second later, so neither is overwritten.
+    pub fn close_named_list(&self, name : &str, entries : &[TodoData]) -> Result<()> {
+        let closed = self.data_dir.join(CLOSED_DIRECTORY);
+        fs::create_dir_all(&closed).on(&closed)?;
+        let closed_at = match self.closed_lists()?.iter().map(|(closed_at, _, _)| *closed_at).max() {
+            Some(latest) if latest >= date::now() => latest + 1,
+            _ => date::now()
+        };
+        self.write_list_file(&closed.join(format!("{}_{}.todo", closed_at, name)), entries)?;
+
+        let path = self.data_dir.join(LISTS_DIRECTORY).join(format!("{}.todo", name));
+        match fs::remove_file(&path) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err).on(&path),
+            _ => Ok(())
+        }
+    }
+
+    /// Takes the most recently closed list out of the stash and moves it back among the
+    /// named lists as `new_name(name)`, `None` when the stash is empty
+    pub fn reopen_closed_list<F>(&self, new_name : F) -> Result<Option<(String, Vec<TodoData>)>>
+        where F : FnOnce(&str) -> String
+    {
+        let latest = self.closed_lists()?
+            .into_iter()
+            .max_by_key(|(closed_at, _, _)| *closed_at);
+
+        let (_, name, path) = match latest {
+            Some(latest) => latest,
+            None => return Ok(None)
+        };
+
+        let name = new_name(&name);
+        let entries = self.read_list(&path)?;
+        let lists = self.data_dir.join(LISTS_DIRECTORY);
+        fs::create_dir_all(&lists).on(&lists)?;
+        fs::rename(&path, lists.join(format!("{}.todo", name))).on(&path)?;
+        Ok(Some((name, entries)))
+    }
+
+    /// The lists in the closed stash as `(closed_at, name, path)`
+    fn closed_lists(&self) -> Result<Vec<(u64, String, PathBuf)>> {
+        let closed = self.todo_files(&self.data_dir.join(CLOSED_DIRECTORY))?;
+        Ok(closed
+            .into_iter()
+            .filter_map(|path| {
+                let stem = path.file_stem()?.to_str()?;
+                let (closed_at, name) = stem.split_once('_')?;
+                Some((closed_at.parse::<u64>().ok()?, name.to_string(), path.clone()))
+            })
+            .collect())
+    }
+
+    /// Deletes every list in the closed stash for good and returns how many there were
+    pub fn purge_closed_lists(&self) -> Result<usize> {
+        let closed = self.todo_files(&self.data_dir.join(CLOSED_DIRECTORY))?;
+        for path in &closed {
+            fs::remove_file(path).on(path)?;
+        }
+        Ok(closed.len())
+    }
+
+    /// Whether `name` can be used as the file name of a named list
+    pub fn is_valid_list_name(name : &str) -> bool {
+        !name.is_empty() && !name.contains(['/', '\\']) && !name.starts_with('.')
+    }
+
+    /// Writes every saved list to `path` as JSON and returns how many lists there were:
+    ///
+    /// ```text
+    /// {"lists": [{"kind": "daily", "todos": [{"message": "Write the report", "status": "todo", ...}]},
+    ///            {"kind": "named", "name": "Groceries", "todos": [...]}]}
+    /// ```
+    ///
+    /// `kind` is `daily` (today's list), `long_term`, `someday`, `journal` or `named`.
+    pub fn export_json(&self, path : &Path) -> Result<usize> {
+        let mut lists = vec![
+            ("daily", None, self.load_today()?),
+            ("long_term", None, self.load_long_term()?),
+            ("someday", None, self.load_someday()?),
+            ("journal", None, self.load_journal()?),
+        ];
+        for (name, entries) in self.load_named_lists()? {
+            lists.push(("named", Some(name), entries));
+        }
+
+        let lists : Vec<json::Value> = lists
+            .into_iter()
+            .map(|(kind, name, entries)| {
+                let mut fields = vec![("kind".to_string(), json::Value::String(kind.to_string()))];
+                if let Some(name) = name {
+                    fields.push(("name".to_string(), json::Value::String(name)));
+                }
+                fields.push(("todos".to_string(), json::Value::Array(entries.iter().map(entry_to_json).collect())));
+                json::Value::Object(fields)
+            })
+            .collect();
+
+        let count = lists.len();
+        let document = json::Value::Object(vec![("lists".to_string(), json::Value::Array(lists))]);
+        self.write_atomically(path, document.to_pretty_string())?;
+        Ok(count)
+    }
+
+    /// Reads a file written by `export_json` and replaces every list it contains, the
+    /// other lists are left alone. Nothing is written when the file is invalid.
+    pub fn import_json(&self, path : &Path) -> Result<usize> {
+        let invalid = |message : String| Error { path : path.to_path_buf(), source : io::Error::new(io::ErrorKind::InvalidData, message) };
+        let document = json::parse(&fs::read_to_string(path).on(path)?).map_err(invalid)?;
+        let lists = document.get("lists").and_then(json::Value::as_array).ok_or_else(|| invalid("missing \"lists\"".to_string()))?;
+
+        let mut imported = vec![];
+        for list in lists {
+            let todos = list.get("todos").and_then(json::Value::as_array).ok_or_else(|| invalid("list without \"todos\"".to_string()))?;
+            let entries = todos.iter().map(entry_from_json).collect::<Option<Vec<_>>>().ok_or_else(|| invalid("todo without a message".to_string()))?;
+            let file = match (list.get("kind").and_then(json::Value::as_str), list.get("name").and_then(json::Value::as_str)) {
+                (Some("daily"), _) => format!("{}.todo", date::Date::today().file_stem()),
+                (Some("long_term"), _) => LONG_TERM_FILE.to_string(),
+                (Some("someday"), _) => SOMEDAY_FILE.to_string(),
+                (Some("journal"), _) => JOURNAL_FILE.to_string(),
+                (Some("named"), Some(name)) if Self::is_valid_list_name(name) => format!("{}/{}.todo", LISTS_DIRECTORY, name),
+                (kind, name) => return Err(invalid(format!("unknown list {:?} {:?}", kind, name)))
+            };
+            imported.push((file, entries));
+        }
+
+        for (file, entries) in &imported {
+            self.save_file(file, entries)?;
+        }
+        Ok(imported.len())
+    }
+
+    /// `.todo` files directly inside `directory`, empty when it doesn't exist
+    fn todo_files(&self, directory : &Path) -> Result<Vec<PathBuf>> {
+        let entries = match fs::read_dir(directory) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(err) => return Err(err).on(directory)
+        };
+
+        let mut files = vec![];
+        for entry in entries {
+            let path = entry.on(directory)?.path();
+            if path.extension().is_some_and(|extension| extension == "todo") {
+                files.push(path);
+            }
+        }
+        Ok(files)
+    }
+
+    /// Entries of `name` in the data directory, empty when the file doesn't exist
+    fn load_file(&self, name : &str) -> Result<Vec<TodoData>> {
+        let path = self.data_dir.join(name);
+        match self.read_list(&path) {
+            Err(err) if err.source.kind() == io::ErrorKind::NotFound => Ok(vec![]),
+            result => result
+        }
+    }
+
+    fn save_file(&self, name : &str, entries : &[TodoData]) -> Result<()> {
+        let path = self.data_dir.join(name);
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory).on(directory)?;
+        }
+        self.write_list_file(&path, entries)
+    }
+
+    /// Writes `entries` to an arbitrary `path`, as a Markdown checklist when it ends in
+    /// `.md` and in the day file format otherwise
+    pub fn write_list(&self, path : &Path, entries : &[TodoData]) -> Result<()> {
+        if path.extension().is_some_and(|extension| extension == "md") {
+            fs::write(path, Self::to_markdown(entries)).on(path)
+        } else {
+            fs::write(path, self.format_entries(entries)).on(path)
+        }
+    }
+
+    /// GitHub-flavored task list, `- [ ]` for open and `- [x]` for done todo's. Markdown has
+    /// no in progress state, those are open with an *(in progress)* note. The estimated and
+    /// spent time follow in a note of their own, like *(estimated 1h 00m, spent 25m)*.
+    pub fn to_markdown(entries : &[TodoData]) -> String {
+        entries
+            .iter()
+            .filter(|entry| !entry.message.is_empty())
+            .map(|entry| {
+                let (marker, note) = match entry.status {
+                    Status::Todo => ("[ ]", ""),
+                    Status::InProgress => ("[ ]", " *(in progress)*"),
+                    Status::Done => ("[x]", "")
+                };
+                let mut times = vec![];
+                if entry.estimate_secs > 0 {
+                    times.push(format!("estimated {}", date::format_duration(entry.estimate_secs)));
+                }
+                if entry.actual_secs > 0 {
+                    times.push(format!("spent {}", date::format_duration(entry.actual_secs)));
+                }
+                let times = if times.is_empty() { String::new() } else { format!(" *({})*", times.join(", ")) };
+                format!("{}- {} {}{}{}\n", " ".repeat(entry.indent * INDENT_WIDTH), marker, entry.message, note, times)
+            })
+            .collect()
+    }
+
+    /// Reads a `.todo` file, one entry per line:
+    ///
+    /// ```text
+    /// [ ] Write the report
+    ///   [x] Collect the numbers<TAB>created=1664280000 spent=1500
+    /// ```
+    ///
+    /// Every two leading spaces are one level of indentation, `[ ]` marks a todo, `[~]` one
+    /// in progress and `[x]` a done one. Anything after a tab is `key=value` metadata
+    /// (`created`, `estimate`, `spent`, `pomodoros`, `carried`, `due`, `completed`, `completed_at`, `priority`, `folded`, `repeat`,
+    /// `uid`, `todoist`, `id`, `blocked_by` and `notes`, escaped by `escape_notes`), unknown
+    /// keys are skipped.
+    /// Each list lives in its own file so there are no sections. Blank lines are ignored and
+    /// lines without a marker are todo's, which keeps older `# `/`* ` files and plain notes readable.
+    pub fn parse(contents : &str) -> Vec<TodoData> {
+        contents.lines().filter_map(Self::parse_line).collect()
+    }
+
+    /// Entries of the list file at `path`, decrypted when it was written encrypted
+    fn read_list(&self, path : &Path) -> Result<Vec<TodoData>> {
+        let bytes = fs::read(path).on(path)?;
+        let bytes = match bytes.strip_prefix(ENCRYPTED_MAGIC) {
+            Some(sealed) => {
+                let key = self.key.as_ref()
+                    .ok_or_else(|| io::Error::new(io::ErrorKind::PermissionDenied, "the file is encrypted, turn on encrypt in the config"))
+                    .on(path)?;
+                open(key, sealed)
+                    .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "the file couldn't be decrypted"))
+                    .on(path)?
+            }
+            None => bytes
+        };
+        let contents = String::from_utf8(bytes).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err)).on(path)?;
+        Ok(self.parse_entries(&contents))
+    }
+
+    /// Writes `entries` to the list file at `path`, encrypted once the data directory was unlocked
+    fn write_list_file(&self, path : &Path, entries : &[TodoData]) -> Result<()> {
+        let contents = self.format_entries(entries);
+        match &self.key {
+            Some(key) => {
+                let mut bytes = ENCRYPTED_MAGIC.to_vec();
+                bytes.extend(seal(key, contents.as_bytes()).on(path)?);
+                self.write_atomically(path, bytes)
+            }
+            None => self.write_atomically(path, contents)
+        }
+    }
+
+    /// Entries of a list file in the configured format
+    fn parse_entries(&self, contents : &str) -> Vec<TodoData> {
+        match self.format {
+            StorageFormat::Todo => Self::parse(contents),
+            StorageFormat::TodoTxt => Self::parse_todo_txt(contents)
+        }
+    }
+
+    /// Contents of a list file in the configured format
+    fn format_entries(&self, entries : &[TodoData]) -> String {
+        match self.format {
+            StorageFormat::Todo => Self::serialize(entries),
+            StorageFormat::TodoTxt => Self::serialize_todo_txt(entries)
+        }
+    }
+
+    /// Reads a todo.txt file:
+    ///
+    /// ```text
+    /// (A) 2022-09-26 Call the bank +finances @phone due:2022-09-30
+    /// x 2022-09-27 2022-09-26 Collect the numbers pri:B indent:1
+    /// ```
+    ///
+    /// `x` and the completion date mark a done todo, `(A)` is urgent, `(B)` high and `(C)` or
+    /// lower low. The creation date is optional. Fields todo.txt has no syntax for are
+    /// `key:value` pairs (`status:doing`, `indent`, `estimate`, `spent`, `pomodoros`, `completed_at`, `carried`, `folded`, `rec`,
+    /// `uid`, `todoist`, `id`, `blocked_by` and `notes`), other pairs like `due:` of other tools stay in the message.
+    /// Lines in the `.todo` format are read as such, so list files don't have to be converted
+    /// when switching over, and `serialize_todo_txt` writes them for messages todo.txt can't hold.
+    pub fn parse_todo_txt(contents : &str) -> Vec<TodoData> {
+        contents.lines().filter_map(Self::parse_todo_txt_line).collect()
+    }
+
+    fn parse_todo_txt_line(line : &str) -> Option<TodoData> {
+        let text = line.trim();
+        if ["[ ]", "[x]", "[~]"].iter().any(|marker| strip_marker(text, marker).is_some()) {
+            return Self::parse_line(line);
+        }
+        if text.is_empty() {
+            return None;
+        }
+
+        let mut entry = TodoData::default();
+        // Split on single spaces, so the empty words between double spaces keep the spacing
+        let mut words = text.split(' ').peekable();
+        if words.peek() == Some(&"x") {
+            words.next();
+            entry.status = Status::Done;
+            entry.completed_on = words.peek().and_then(|word| date::Date::parse(word));
+            if entry.completed_on.is_some() {
+                words.next();
+            }
+        }
+        if let Some(priority) = words.peek().and_then(|word| parse_priority_letter(word)) {
+            entry.priority = priority;
+            words.next();
+        }
+        if let Some(created) = words.peek().and_then(|word| date::Date::parse(word)) {
+            entry.created_at = Some(created.to_days() as u64 * 86_400);
+            words.next();
+        }
+
+        let mut message = vec![];
+        for word in words {
+            let known = match word.split_once(':') {
+                Some(("due", value)) => date::Date::parse(value).map(|due| entry.due = Some(due)).is_some(),
+                Some(("rec", value)) => Recurrence::parse(value).map(|rule| entry.recurrence = Some(rule)).is_some(),
+                Some(("pri", value)) => parse_priority_letter(&format!("({})", value)).map(|priority| entry.priority = priority).is_some(),
+                Some(("status", "doing")) if entry.status == Status::Todo => {
+                    entry.status = Status::InProgress;
+                    true
+                }
+                Some(("indent", value)) => value.parse().map(|indent| entry.indent = indent).is_ok(),
+                Some(("estimate", value)) => value.parse().map(|secs| entry.estimate_secs = secs).is_ok(),
+                Some(("spent", value)) => value.parse().map(|secs| entry.actual_secs = secs).is_ok(),
+                Some(("pomodoros", value)) => value.parse().map(|count| entry.pomodoros = count).is_ok(),
+                Some(("completed_at", value)) => value.parse().map(|completed_at| entry.completed_at = Some(completed_at)).is_ok(),
+                Some(("carried", value)) => value.parse().map(|days| entry.carried_days = days).is_ok(),
+                Some(("folded", "true")) => {
+                    entry.folded = true;
+                    true
+                }
+                Some(("uid", value)) if !value.is_empty() => {
+                    entry.sync_uid = Some(value.to_string());
+                    true
+                }
+                Some(("todoist", value)) if !value.is_empty() => {
+                    entry.todoist_id = Some(value.to_string());
+                    true
+                }
+                Some(("notes", value)) if !value.is_empty() => {
+                    entry.notes = unescape_notes(value);
+                    true
+                }
+                Some(("id", value)) => value.parse().map(|id| entry.id = Some(id)).is_ok(),
+                Some(("blocked_by", value)) => value.parse().map(|id| entry.blocked_by = Some(id)).is_ok(),
+                _ => false
+            };
+            if !known {
+                message.push(word);
+            }
+        }
+        entry.message = message.join(" ");
+        Some(entry)
+    }
+
+    /// Writes `entries` in the format read by `parse_todo_txt`, blank entries are left out.
+    /// A done todo without a completion day is written as done today. Messages that would be read
+    /// back differently, like "x marks the spot" as a done todo or a typed `due:` as the due day,
+    /// are written as a `.todo` line instead.
+    pub fn serialize_todo_txt(entries : &[TodoData]) -> String {
+        entries
+            .iter()
+            .filter(|m| !m.message.is_empty())
+            .map(Self::serialize_todo_txt_entry)
+            .collect()
+    }
+
+    fn serialize_todo_txt_entry(entry : &TodoData) -> String {
+        let line = Self::todo_txt_line(entry);
+        match Self::parse_todo_txt(&line).as_slice() {
+            [parsed] if parsed.message == entry.message => line,
+            _ => Self::serialize_entry(entry)
+        }
+    }
+
+    fn todo_txt_line(entry : &TodoData) -> String {
+        let letter = match entry.priority {
+            Priority::Urgent => Some('A'),
+            Priority::High => Some('B'),
+            Priority::Medium => None,
+            Priority::Low => Some('C')
+        };
+
+        let mut words = vec![];
+        if entry.status == Status::Done {
+            words.push("x".to_string());
+            words.push(entry.completed_on.unwrap_or_else(date::Date::today).format());
+        } else if let Some(letter) = letter {
+            words.push(format!("({})", letter));
+        }
+        if let Some(created_at) = entry.created_at {
+            words.push(date::Date::from_days((created_at / 86_400) as i64).format());
+        }
+        words.push(entry.message.clone());
+
+        // Done todo's keep their priority as a pair, todo.txt drops the `(A)` on completion
+        if let (Status::Done, Some(letter)) = (entry.status, letter) {
+            words.push(format!("pri:{}", letter));
+        }
+        if entry.status == Status::InProgress {
+            words.push("status:doing".to_string());
+        }
+        if entry.indent > 0 {
+            words.push(format!("indent:{}", entry.indent));
+        }
+        if let Some(due) = entry.due {
+            words.push(format!("due:{}", due.format()));
+        }
+        if let Some(recurrence) = entry.recurrence {
+            words.push(format!("rec:{}", recurrence.format()));
+        }
+        if entry.estimate_secs > 0 {
+            words.push(format!("estimate:{}", entry.estimate_secs));
+        }
+        if entry.actual_secs > 0 {
+            words.push(format!("spent:{}", entry.actual_secs));
+        }
+        if entry.pomodoros > 0 {
+            words.push(format!("pomodoros:{}", entry.pomodoros));
+        }
+        if let Some(completed_at) = entry.completed_at.filter(|_| entry.status == Status::Done) {
+            words.push(format!("completed_at:{}", completed_at));
+        }
+        if entry.carried_days > 0 {
+            words.push(format!("carried:{}", entry.carried_days));
+        }
+        if entry.folded {
+            words.push("folded:true".to_string());
+        }
+        if let Some(uid) = &entry.sync_uid {
+            words.push(format!("uid:{}", uid));
+        }
+        if let Some(id) = &entry.todoist_id {
+            words.push(format!("todoist:{}", id));
+        }
+        if let Some(id) = entry.id {
+            words.push(format!("id:{}", id));
+        }
+        if let Some(id) = entry.blocked_by {
+            words.push(format!("blocked_by:{}", id));
+        }
+        if !entry.notes.is_empty() {
+            words.push(format!("notes:{}", escape_notes(&entry.notes)));
+        }
+
+        let mut line = words.join(" ");
+        line.push('\n');
+        line
+    }
+
+    fn parse_line(line : &str) -> Option<TodoData> {
+        let line = line.trim_end();
+        let text = line.trim_start_matches(' ');
+        if text.is_empty() {
+            return None;
+        }
+
+        let (status, rest) = if let Some(rest) = strip_marker(text, "[x]") {
+            (Status::Done, rest)
+        } else if let Some(rest) = strip_marker(text, "[~]") {
+            (Status::InProgress, rest)
+        } else if let Some(rest) = strip_marker(text, "[ ]") {
+            (Status::Todo, rest)
+        } else if let Some(rest) = text.strip_prefix("* ") {
+            (Status::Done, rest)
+        } else {
+            (Status::Todo, text.strip_prefix("# ").unwrap_or(text))
+        };
+
+        let (message, metadata) = rest.split_once('\t').unwrap_or((rest, ""));
+        let mut entry = TodoData {
+            message : unescape_message(message),
+            status,
+            indent : (line.len() - text.len()) / INDENT_WIDTH,
+            ..TodoData::default()
+        };
+
+        for field in metadata.split_whitespace() {
+            match field.split_once('=') {
+                Some(("created", value)) => entry.created_at = value.parse().ok(),
+                Some(("estimate", value)) => entry.estimate_secs = value.parse().unwrap_or(0),
+                Some(("spent", value)) => entry.actual_secs = value.parse().unwrap_or(0),
+                Some(("pomodoros", value)) => entry.pomodoros = value.parse().unwrap_or(0),
+                Some(("carried", value)) => entry.carried_days = value.parse().unwrap_or(0),
+                Some(("due", value)) => entry.due = date::Date::parse(value),
+                Some(("folded", value)) => entry.folded = value == "true",
+                Some(("repeat", value)) => entry.recurrence = Recurrence::parse(value),
+                Some(("uid", value)) => entry.sync_uid = Some(value.to_string()),
+                Some(("completed", value)) => entry.completed_on = date::Date::parse(value),
+                Some(("completed_at", value)) => entry.completed_at = value.parse().ok(),
+                Some(("todoist", value)) => entry.todoist_id = Some(value.to_string()),
+                Some(("notes", value)) => entry.notes = unescape_notes(value),
+                Some(("id", value)) => entry.id = value.parse().ok(),
+                Some(("blocked_by", value)) => entry.blocked_by = value.parse().ok(),
+                Some(("priority", "low")) => entry.priority = Priority::Low,
+                Some(("priority", "high")) => entry.priority = Priority::High,
+                Some(("priority", "urgent")) => entry.priority = Priority::Urgent,
+                _ => {}
+            }
+        }
+
+        Some(entry)
+    }
+
+    /// Writes `entries` in the format read by `parse`, blank entries are left out
+    pub fn serialize(entries : &[TodoData]) -> String {
+        entries
+            .iter()
+            .filter(|m| !m.message.is_empty())
+            .map(Self::serialize_entry)
+            .collect()
+    }
+
+    fn serialize_entry(entry : &TodoData) -> String {
+        let marker = match entry.status {
+            Status::Todo => "[ ]",
+            Status::InProgress => "[~]",
+            Status::Done => "[x]"
+        };
+        let mut line = format!("{}{} {}", " ".repeat(entry.indent * INDENT_WIDTH), marker, escape_message(&entry.message));
+
+        let mut metadata = vec![];
+        if let Some(created_at) = entry.created_at {
+            metadata.push(format!("created={}", created_at));
+        }
+        if entry.estimate_secs > 0 {
+            metadata.push(format!("estimate={}", entry.estimate_secs));
+        }
+        if entry.actual_secs > 0 {
+            metadata.push(format!("spent={}", entry.actual_secs));
+        }
+        if entry.pomodoros > 0 {
+            metadata.push(format!("pomodoros={}", entry.pomodoros));
+        }
+        if entry.carried_days > 0 {
+            metadata.push(format!("carried={}", entry.carried_days));
+        }
+        if let Some(due) = entry.due {
+            metadata.push(format!("due={}", due.format()));
+        }
+        if entry.folded {
+            metadata.push("folded=true".to_string());
+        }
+        if let Some(recurrence) = entry.recurrence {
+            metadata.push(format!("repeat={}", recurrence.format()));
+        }
+        if let Some(completed_on) = entry.completed_on.filter(|_| entry.status == Status::Done) {
+            metadata.push(format!("completed={}", completed_on.format()));
+        }
+        if let Some(completed_at) = entry.completed_at.filter(|_| entry.status == Status::Done) {
+            metadata.push(format!("completed_at={}", completed_at));
+        }
+        if let Some(uid) = &entry.sync_uid {
+            metadata.push(format!("uid={}", uid));
+        }
+        if let Some(id) = &entry.todoist_id {
+            metadata.push(format!("todoist={}", id));
+        }
+        if let Some(id) = entry.id {
+            metadata.push(format!("id={}", id));
+        }
+        if let Some(id) = entry.blocked_by {
+            metadata.push(format!("blocked_by={}", id));
+        }
+        if !entry.notes.is_empty() {
+            metadata.push(format!("notes={}", escape_notes(&entry.notes)));
+        }
+        match entry.priority {
+            Priority::Low => metadata.push("priority=low".to_string()),
+            Priority::Medium => {}
+            Priority::High => metadata.push("priority=high".to_string()),
+            Priority::Urgent => metadata.push("priority=urgent".to_string())
+        }
+        if !metadata.is_empty() {
+            line.push('\t');
+            line.push_str(&metadata.join(" "));
+        }
+
+        line.push('\n');
+        line
+    }
+
+    /// Replaces `path` with `contents` by writing a temporary file next to it and renaming
+    /// it over the original, so readers never see a half-written file
+    pub fn write_atomically(&self, path : &Path, contents : impl AsRef<[u8]>) -> Result<()> {
+        let mut temporary = path.as_os_str().to_owned();
+        temporary.push(".tmp");
+        fs::write(&temporary, contents).on(Path::new(&temporary))?;
+        fs::rename(&temporary, path).on(path)
+    }
+
+    /// Resolves a user-typed path, expanding a leading `~/` to the home directory
+    pub fn expand_path(path : &str) -> PathBuf {
+        match (path.strip_prefix("~/"), std::env::var("HOME")) {
+            (Some(rest), Ok(home)) => Path::new(&home).join(rest),
+            _ => PathBuf::from(path)
+        }
+    }
+
+    /// Appends a single line describing `summary` to the rollover log
+    pub fn log_rollover(&self, summary : &RolloverSummary) -> Result<()> {
+        let path = self.data_dir.join(ROLLOVER_LOG);
+        let mut log = OpenOptions::new().create(true).append(true).open(&path).on(&path)?;
+
+        writeln!(log, "{}: carried over {}, completed {}, archived {}",
+            summary.date, summary.carried_over, summary.completed, summary.archived).on(&path)
+    }
+
+    /// Appends `summary` as a line to the daily summary log
+    pub fn append_summary(&self, summary : &str) -> Result<()> {
+        let path = self.data_dir.join(SUMMARY_LOG);
+        let mut log = OpenOptions::new().create(true).append(true).open(&path).on(&path)?;
+
+        writeln!(log, "{}", summary).on(&path)
+    }
+
+    /// The default sort keys saved for `list`, like `long_term` or `list_<name>`
+    pub fn load_sort_order(&self, list : &str) -> Result<Option<Vec<SortKey>>> {
+        Ok(self.read_sort_orders()?.into_iter().find(|(name, _)| name == list).map(|(_, keys)| keys))
+    }
+
+    /// Saves `keys` as the default ordering of `list`, replacing the one it had
+    pub fn save_sort_order(&self, list : &str, keys : &[SortKey]) -> Result<()> {
+        let mut orders = self.read_sort_orders()?;
+        orders.retain(|(name, _)| name != list);
+        orders.push((list.to_string(), keys.to_vec()));
+        self.write_sort_orders(&orders)
+    }
+
+    /// Moves the default ordering saved for `old_list` over to `new_list`, for a renamed list
+    pub fn rename_sort_order(&self, old_list : &str, new_list : &str) -> Result<()> {
+        match self.load_sort_order(old_list)? {
+            Some(keys) => {
+                self.save_sort_order(new_list, &keys)?;
+                self.remove_sort_order(old_list)
+            }
+            None => Ok(())
+        }
+    }
+
+    fn remove_sort_order(&self, list : &str) -> Result<()> {
+        let mut orders = self.read_sort_orders()?;
+        orders.retain(|(name, _)| name != list);
+        self.write_sort_orders(&orders)
+    }
+
+    fn write_sort_orders(&self, orders : &[(String, Vec<SortKey>)]) -> Result<()> {
+        let contents : String = orders.iter()
+            .map(|(name, keys)| format!("{} = {}\n", name, sort::format_keys(keys)))
+            .collect();
+        self.write_atomically(&self.data_dir.join(SORT_ORDERS_FILE), contents)
+    }
+
+    fn read_sort_orders(&self) -> Result<Vec<(String, Vec<SortKey>)>> {
+        let path = self.data_dir.join(SORT_ORDERS_FILE);
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(err).on(&path)
+        };
+        Ok(contents.lines()
+            .filter_map(|line| line.split_once('='))
+            .filter_map(|(name, keys)| Some((name.trim().to_string(), sort::parse_keys(keys)?)))
+            .collect())
+    }
+
+    /// Ids of the tasks `service`, like `caldav` or `todoist`, was last synced with
+    pub fn load_synced_ids(&self, service : &str) -> Result<Vec<String>> {
+        Ok(self.read_synced_ids()?.into_iter().filter(|(name, _)| name == service).map(|(_, id)| id).collect())
+    }
+
+    /// Replaces the ids saved for `service`, keeping the other services'
+    pub fn save_synced_ids(&self, service : &str, ids : &[String]) -> Result<()> {
+        let mut synced = self.read_synced_ids()?;
+        synced.retain(|(name, _)| name != service);
+        synced.extend(ids.iter().map(|id| (service.to_string(), id.clone())));
+        let contents : String = synced.iter().map(|(name, id)| format!("{} {}\n", name, id)).collect();
+        self.write_atomically(&self.data_dir.join(SYNCED_FILE), contents)
+    }
+
+    fn read_synced_ids(&self) -> Result<Vec<(String, String)>> {
+        let path = self.data_dir.join(SYNCED_FILE);
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(err).on(&path)
+        };
+        Ok(contents.lines()
+            .filter_map(|line| line.split_once(' '))
+            .map(|(name, id)| (name.to_string(), id.to_string()))
+            .collect())
+    }
+
+    /// Returns the full rollover log, empty if no rollover happened yet
+    pub fn read_rollover_log(&self) -> Result<String> {
+        let path = self.data_dir.join(ROLLOVER_LOG);
+        match fs::read_to_string(&path) {
+            Ok(contents) => Ok(contents),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(String::new()),
+            Err(err) => Err(err).on(&path)
+        }
+    }
+}
+
+/// Priority of a todo.txt `(A)`, urgent down to low from `(C)` on
+fn parse_priority_letter(word : &str) -> Option<Priority> {
+    match word.strip_prefix('(')?.strip_suffix(')')? {
+        "A" => Some(Priority::Urgent),
+        "B" => Some(Priority::High),
+        letter if letter.len() == 1 && letter.chars().all(|c| c.is_ascii_uppercase()) => Some(Priority::Low),
+        _ => None
+    }
+}
+
+/// `notes` as a single word of metadata, with backslashes, line breaks, tabs and spaces
+/// written as `\\`, `\n`, `\t` and `\s`
+fn escape_notes(notes : &str) -> String {
+    notes.replace('\\', "\\\\").replace('\n', "\\n").replace('\t', "\\t").replace(' ', "\\s")
+}
+
+/// `message` with backslashes, line breaks and tabs written as `\\`, `\n` and `\t`, a tab
+/// would start the metadata and a line break the next entry
+fn escape_message(message : &str) -> String {
+    message.replace('\\', "\\\\").replace('\n', "\\n").replace('\t', "\\t")
+}
+
+/// Reverses `escape_message`. Any other backslash is kept, like the ones in files written
+/// before messages were escaped.
+fn unescape_message(text : &str) -> String {
+    let mut message = String::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            message.push(c);
+            continue;
+        }
+        let unescaped = match chars.next_if(|next| matches!(next, 'n' | 't' | '\\')) {
+            Some('n') => '\n',
+            Some('t') => '\t',
+            _ => '\\'
+        };
+        message.push(unescaped);
+    }
+    message
+}
+
+fn unescape_notes(word : &str) -> String {
+    let mut notes = String::new();
+    let mut chars = word.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            notes.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => notes.push('\n'),
+            Some('t') => notes.push('\t'),
+            Some('s') => notes.push(' '),
+            Some(escaped) => notes.push(escaped),
+            None => {}
+        }
+    }
+    notes
+}
+
+fn entry_to_json(entry : &TodoData) -> json::Value {
+    let status = match entry.status {
+        Status::Todo => "todo",
+        Status::InProgress => "in_progress",
+        Status::Done => "done"
+    };
+    let priority = match entry.priority {
+        Priority::Low => "low",
+        Priority::Medium => "medium",
+        Priority::High => "high",
+        Priority::Urgent => "urgent"
+    };
+
+    json::Value::Object(vec![
+        ("message".to_string(), json::Value::String(entry.message.clone())),
+        ("notes".to_string(), json::Value::String(entry.notes.clone())),
+        ("status".to_string(), json::Value::String(status.to_string())),
+        ("indent".to_string(), json::Value::Number(entry.indent as f64)),
+        ("created_at".to_string(), entry.created_at.map_or(json::Value::Null, |created_at| json::Value::Number(created_at as f64))),
+        ("estimate_secs".to_string(), json::Value::Number(entry.estimate_secs as f64)),
+        ("spent_secs".to_string(), json::Value::Number(entry.actual_secs as f64)),
+        ("pomodoros".to_string(), json::Value::Number(entry.pomodoros as f64)),
+        ("carried_days".to_string(), json::Value::Number(entry.carried_days as f64)),
+        ("priority".to_string(), json::Value::String(priority.to_string())),
+        ("due".to_string(), entry.due.map_or(json::Value::Null, |due| json::Value::String(due.format()))),
+        ("folded".to_string(), json::Value::Bool(entry.folded)),
+        ("repeat".to_string(), entry.recurrence.map_or(json::Value::Null, |recurrence| json::Value::String(recurrence.format()))),
+        ("completed".to_string(), entry.completed_on.map_or(json::Value::Null, |completed_on| json::Value::String(completed_on.format()))),
+        ("completed_at".to_string(), entry.completed_at.map_or(json::Value::Null, |completed_at| json::Value::Number(completed_at as f64))),
+        ("uid".to_string(), entry.sync_uid.clone().map_or(json::Value::Null, json::Value::String)),
+        ("todoist_id".to_string(), entry.todoist_id.clone().map_or(json::Value::Null, json::Value::String)),
+        ("id".to_string(), entry.id.map_or(json::Value::Null, |id| json::Value::Number(id as f64))),
+        ("blocked_by".to_string(), entry.blocked_by.map_or(json::Value::Null, |id| json::Value::Number(id as f64))),
+    ])
+}
+
+/// Only `message` is required, missing or unknown fields fall back to the defaults
+fn entry_from_json(value : &json::Value) -> Option<TodoData> {
+    let field = |key : &str| value.get(key);
+    Some(TodoData {
+        message : field("message")?.as_str()?.to_string(),
+        notes : field("notes").and_then(json::Value::as_str).unwrap_or("").to_string(),
+        status : match field("status").and_then(json::Value::as_str) {
+            Some("in_progress") => Status::InProgress,
+            Some("done") => Status::Done,
+            _ => Status::Todo
+        },
+        indent : field("indent").and_then(json::Value::as_u64).unwrap_or(0) as usize,
+        created_at : field("created_at").and_then(json::Value::as_u64),
+        estimate_secs : field("estimate_secs").and_then(json::Value::as_u64).unwrap_or(0) as u32,
+        actual_secs : field("spent_secs").and_then(json::Value::as_u64).unwrap_or(0) as u32,
+        pomodoros : field("pomodoros").and_then(json::Value::as_u64).unwrap_or(0) as u32,
+        carried_days : field("carried_days").and_then(json::Value::as_u64).unwrap_or(0) as u32,
+        priority : match field("priority").and_then(json::Value::as_str) {
+            Some("low") => Priority::Low,
+            Some("high") => Priority::High,
+            Some("urgent") => Priority::Urgent,
+            _ => Priority::Medium
+        },
+        due : field("due").and_then(json::Value::as_str).and_then(date::Date::parse),
+        folded : field("folded").and_then(json::Value::as_bool).unwrap_or(false),
+        recurrence : field("repeat").and_then(json::Value::as_str).and_then(Recurrence::parse),
+        completed_on : field("completed").and_then(json::Value::as_str).and_then(date::Date::parse),
+        completed_at : field("completed_at").and_then(json::Value::as_u64),
+        sync_uid : field("uid").and_then(json::Value::as_str).map(str::to_string),
+        todoist_id : field("todoist_id").and_then(json::Value::as_str).map(str::to_string),
+        id : field("id").and_then(json::Value::as_u64),
+        blocked_by : field("blocked_by").and_then(json::Value::as_u64),
+        ..TodoData::default()
+    })
+}
+
+/// `len` bytes from the operating system's random source
+fn random_bytes(len : usize) -> io::Result<Vec<u8>> {
+    let mut bytes = vec![0; len];
+    getrandom::getrandom(&mut bytes).map_err(|err| io::Error::other(err.to_string()))?;
+    Ok(bytes)
+}
+
+/// Encrypts and authenticates `plaintext` with ChaCha20-Poly1305 under a fresh random nonce,
+/// returned as `nonce || ciphertext || tag`
+fn seal(key : &Key, plaintext : &[u8]) -> io::Result<Vec<u8>> {
+    let mut sealed = random_bytes(NONCE_LEN)?;
+    let ciphertext = ChaCha20Poly1305::new(key)
+        .encrypt(Nonce::from_slice(&sealed), plaintext)
+        .map_err(|_| io::Error::other("the contents couldn't be encrypted"))?;
+    sealed.extend(ciphertext);
+    Ok(sealed)
+}
+
+/// The plaintext of a message made by `seal`, `None` when it was made with another key
+/// or was changed since
+fn open(key : &Key, sealed : &[u8]) -> Option<Vec<u8>> {
+    if sealed.len() < NONCE_LEN {
+        return None;
+    }
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+    ChaCha20Poly1305::new(key).decrypt(Nonce::from_slice(nonce), ciphertext).ok()
+}
+
+/// Derives a key from `passphrase` with PBKDF2-HMAC-SHA256
+fn derive_key(passphrase : &str, salt : &[u8], iterations : u32) -> Key {
+    let mut key = Key::default();
+    pbkdf2::pbkdf2_hmac::<sha2::Sha256>(passphrase.as_bytes(), salt, iterations, &mut key);
+    key
+}
+
+/// `text` after a leading status `marker` and the space following it
+fn strip_marker<'a>(text : &'a str, marker : &str) -> Option<&'a str> {
+    text.strip_prefix(marker).map(|rest| rest.strip_prefix(' ').unwrap_or(rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TestDir;
+
+    fn entry(message : &str, status : Status, indent : usize) -> TodoData {
+        TodoData { message : message.to_string(), status, indent, ..TodoData::default() }
+    }
+
+    #[test]
+    fn errors_name_the_file() {
+        let err = fs::read_to_string("./no/such/dir/x.todo").on(Path::new("./no/such/dir/x.todo")).unwrap_err();
+        assert_eq!(err.source.kind(), io::ErrorKind::NotFound);
+        assert!(err.to_string().starts_with("./no/such/dir/x.todo: "));
+        assert_eq!(io::Error::from(err).kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn serialize_round_trips() {
+        let entries = vec![
+            entry("Write the report", Status::InProgress, 0),
+            TodoData { created_at : Some(1664280000), completed_at : Some(1664290000), estimate_secs : 1800, actual_secs : 1500, pomodoros : 1, carried_days : 3, folded : true, todoist_id : Some("2995104339".to_string()), ..entry("Collect the numbers", Status::Done, 1) },
+            TodoData { priority : Priority::Urgent, due : date::Date::parse("2022-09-30"), recurrence : Some(Recurrence::Days(3)), sync_uid : Some("todo_rust-1".to_string()), ..entry("[x] looks like a marker #tag", Status::Todo, 2) },
+            TodoData { notes : "Ask for\n\tthe 2021 c:\\ backup=yes".to_string(), id : Some(1664280000), blocked_by : Some(7), ..entry("With notes", Status::Todo, 0) },
+        ];
+
+        let parsed = FileManager::parse(&FileManager::serialize(&entries));
+
+        assert_eq!(parsed.len(), entries.len());
+        for (parsed, original) in parsed.iter().zip(&entries) {
+            assert_eq!(parsed.message, original.message);
+            assert_eq!(parsed.status, original.status);
+            assert_eq!(parsed.indent, original.indent);
+            assert_eq!((parsed.created_at, parsed.completed_at), (original.created_at, original.completed_at));
+            assert_eq!((parsed.estimate_secs, parsed.actual_secs), (original.estimate_secs, original.actual_secs));
+            assert_eq!((parsed.pomodoros, parsed.carried_days), (original.pomodoros, original.carried_days));
+            assert_eq!(parsed.priority, original.priority);
+            assert_eq!(parsed.due, original.due);
+            assert_eq!(parsed.folded, original.folded);
+            assert_eq!(parsed.recurrence, original.recurrence);
+            assert_eq!(parsed.sync_uid, original.sync_uid);
+            assert_eq!(parsed.todoist_id, original.todoist_id);
+            assert_eq!(parsed.notes, original.notes);
+            assert_eq!((parsed.id, parsed.blocked_by), (original.id, original.blocked_by));
+        }
+    }
+
+    #[test]
+    fn tabs_line_breaks_and_backslashes_in_messages_round_trip() {
+        let messages = ["call\tbob about it", "line one\nline two", "C:\\temp\\new \\n", "ends with \\"];
+        let entries : Vec<TodoData> = messages.iter().map(|message| entry(message, Status::Todo, 0)).collect();
+
+        let text = FileManager::serialize(&entries);
+        assert_eq!(text.lines().count(), messages.len());
+        let parsed = FileManager::parse(&text);
+        assert_eq!(parsed.iter().map(|m| m.message.as_str()).collect::<Vec<_>>(), messages);
+        // Files written before messages were escaped keep their backslashes
+        assert_eq!(FileManager::parse("[ ] C:\\dir\\x\n")[0].message, "C:\\dir\\x");
+    }
+
+    #[test]
+    fn todo_txt_round_trips_and_reads_other_tools() {
+        let entries = vec![
+            TodoData { priority : Priority::Urgent, due : date::Date::parse("2022-09-30"), ..entry("Call the bank +finances @phone", Status::Todo, 0) },
+            TodoData { priority : Priority::High, completed_on : date::Date::parse("2022-09-27"), created_at : Some(1664150400), ..entry("Collect the numbers", Status::Done, 1) },
+            TodoData { recurrence : Some(Recurrence::Weekly), estimate_secs : 600, actual_secs : 60, sync_uid : Some("todo_rust-1".to_string()), notes : "every\ncorner".to_string(), ..entry("Water the plants", Status::InProgress, 0) },
+        ];
+
+        let text = FileManager::serialize_todo_txt(&entries);
+        assert!(text.starts_with("(A) Call the bank +finances @phone due:2022-09-30\nx 2022-09-27 2022-09-26 Collect the numbers pri:B indent:1\n"));
+        let parsed = FileManager::parse_todo_txt(&text);
+        for (parsed, original) in parsed.iter().zip(&entries) {
+            assert_eq!(parsed.message, original.message);
+            assert_eq!(parsed.status, original.status);
+            assert_eq!(parsed.indent, original.indent);
+            assert_eq!(parsed.priority, original.priority);
+            assert_eq!(parsed.due, original.due);
+            assert_eq!(parsed.completed_on, original.completed_on);
+            assert_eq!(parsed.created_at, original.created_at);
+            assert_eq!(parsed.recurrence, original.recurrence);
+            assert_eq!((parsed.estimate_secs, parsed.actual_secs), (original.estimate_secs, original.actual_secs));
+            assert_eq!(parsed.sync_uid, original.sync_uid);
+            assert_eq!(parsed.notes, original.notes);
+        }
+
+        let parsed = FileManager::parse_todo_txt("x Buy milk t:2022-09-01\n(D) Read\n  [~] Old line\n");
+        assert_eq!(parsed[0].message, "Buy milk t:2022-09-01");
+        assert_eq!(parsed[0].status, Status::Done);
+        assert_eq!(parsed[1].priority, Priority::Low);
+        assert_eq!((parsed[2].status, parsed[2].indent), (Status::InProgress, 1));
+    }
+
+    #[test]
+    fn todo_txt_keeps_messages_that_look_like_its_syntax() {
+        let entries = vec![
+            entry("x marks  the spot", Status::Todo, 0),
+            entry("2022-10-01 deadline due:2022-12-01", Status::Todo, 0),
+            entry("(A) not a priority", Status::Todo, 1),
+            entry("[ ] brackets", Status::Todo, 0),
+            entry("call\tbob", Status::Todo, 0),
+            TodoData { created_at : Some(1664150400), ..entry("x after the date", Status::Done, 0) },
+        ];
+
+        let text = FileManager::serialize_todo_txt(&entries);
+        assert!(text.starts_with("[ ] x marks  the spot\n"));
+        assert!(text.ends_with(" 2022-09-26 x after the date\n"));
+        let parsed = FileManager::parse_todo_txt(&text);
+        assert_eq!(parsed.len(), entries.len());
+        for (parsed, original) in parsed.iter().zip(&entries) {
+            assert_eq!(parsed.message, original.message);
+            assert_eq!(parsed.status, original.status);
+            assert_eq!(parsed.indent, original.indent);
+            assert_eq!(parsed.priority, original.priority);
+            assert_eq!(parsed.due, original.due);
+            assert_eq!(parsed.created_at, original.created_at);
+        }
+
+        assert_eq!(FileManager::parse_todo_txt("(B) two  spaces due:2022-09-30\n")[0].message, "two  spaces");
+    }
+
+    #[test]
+    fn profiles_override_the_top_level_settings_and_keep_their_own_lists() {
+        let contents = "focus_minutes = 30\n[caldav]\nurl = \"https://home.example.com/tasks/\"\n\
+            [profile.work]\nfocus_minutes = 50\n[profile.work.caldav]\nurl = \"https://work.example.com/tasks/\"\n\
+            [profile.personal]\n[profile.../etc]\n";
+        let base = Path::new("/data/todo_rust");
+
+        let default = Config::parse(contents, DEFAULT_PROFILE);
+        assert_eq!(default.profiles, vec!["default", "work", "personal"]);
+        assert_eq!(default.focus_minutes, 30);
+        assert_eq!(FileManager::for_profile(base, &default).data_dir, base);
+
+        let work = Config::parse(contents, "work");
+        assert_eq!(work.focus_minutes, 50);
+        assert_eq!(work.caldav.as_ref().unwrap().url, "https://work.example.com/tasks/");
+        assert_eq!(FileManager::for_profile(base, &work).data_dir, base.join("profiles").join("work"));
+
+        let personal = Config::parse(&contents.replace("[profile.personal]\n", "[profile.personal]\ndata_dir = \"/elsewhere\"\n"), "personal");
+        assert_eq!(personal.focus_minutes, 30);
+        assert_eq!(FileManager::for_profile(base, &personal).data_dir, PathBuf::from("/elsewhere"));
+    }
+
+    #[test]
+    fn unlocked_lists_are_encrypted_and_need_the_passphrase() {
+        let data_dir = TestDir::new("crypt");
+        let locked = FileManager::new(data_dir.path().to_path_buf());
+        locked.save_long_term(&[entry("Plain", Status::Todo, 0)]).unwrap();
+        let unlocked = locked.unlock("correct horse").unwrap().unwrap();
+        assert_eq!(unlocked.load_long_term().unwrap()[0].message, "Plain");
+
+        unlocked.save_long_term(&[entry("Secret", Status::Done, 0)]).unwrap();
+        let raw = fs::read(data_dir.path().join(LONG_TERM_FILE)).unwrap();
+        assert!(raw.starts_with(ENCRYPTED_MAGIC) && !raw.windows(6).any(|window| window == b"Secret"));
+        assert!(locked.unlock("wrong").unwrap().is_none());
+        assert!(locked.load_long_term().is_err_and(|err| err.source.kind() == io::ErrorKind::PermissionDenied));
+        let again = locked.unlock("correct horse").unwrap().unwrap();
+        assert_eq!(again.load_long_term().unwrap()[0].message, "Secret");
+    }
+
+    #[test]
+    fn keys_are_derived_as_before_and_sealed_lists_detect_changes() {
+        // RFC 7914, section 11, so data directories locked before keep their passphrase
+        assert_eq!(format!("{:02x?}", derive_key("passwd", b"salt", 1).as_slice()).replace([',', ' ', '[', ']'], ""),
+            "55ac046e56e3089fec1691c22544b605f94185216dde0465e68b9d57c20dacbc");
+
+        let key = derive_key("correct horse", b"0123456789abcdef", 1);
+        let sealed = seal(&key, b"[ ] Buy milk\n").unwrap();
+        assert_eq!(open(&key, &sealed).as_deref(), Some(&b"[ ] Buy milk\n"[..]));
+        let mut changed = sealed.clone();
+        changed[NONCE_LEN] ^= 1;
+        assert_eq!(open(&key, &changed), None);
+        assert_eq!(open(&derive_key("wrong", b"0123456789abcdef", 1), &sealed), None);
+    }
+
+    #[test]
+    fn roll_over_counts_the_days_a_todo_was_carried() {
+        let data_dir = TestDir::new("carry");
+        let file_manager = FileManager::new(data_dir.path().to_path_buf());
+        let today = date::Date::today();
+        file_manager.save_day(today.add_days(-2), &[
+            TodoData { carried_days : 1, ..entry("Call the bank", Status::Todo, 0) },
+            entry("Buy milk", Status::Done, 0),
+        ]).unwrap();
+
+        file_manager.roll_over(false).unwrap();
+
+        let entries = file_manager.load_today().unwrap();
+        assert_eq!(entries.iter().map(|m| (m.message.as_str(), m.carried_days)).collect::<Vec<_>>(), vec![("Call the bank", 3)]);
+    }
+
+    #[test]
+    fn rolled_over_files_wait_for_archive_after_days() {
+        let data_dir = TestDir::new("archive");
+        let file_manager = FileManager::new(data_dir.path().to_path_buf());
+        let config = Config { archive_after_days : Some(3), ..Config::default() };
+        let today = date::Date::today();
+        file_manager.save_day(today.add_days(-5), &[entry("Renew passport", Status::Todo, 0)]).unwrap();
+        file_manager.save_day(today.add_days(-1), &[
+            entry("Call the bank", Status::Todo, 0),
+            entry("Buy milk", Status::Done, 0),
+        ]).unwrap();
+
+        file_manager.initialize(&config).unwrap();
+        // Starting again the same day carries nothing twice
+        file_manager.initialize(&config).unwrap();
+
+        let messages = |entries : Vec<TodoData>| entries.into_iter().map(|m| m.message).collect::<Vec<_>>();
+        assert_eq!(messages(file_manager.load_today().unwrap()), vec!["Renew passport", "Call the bank"]);
+        let days : Vec<date::Date> = file_manager.day_files().unwrap().into_iter().map(|(day, _)| day).collect();
+        assert!(days.contains(&today.add_days(-1)) && !days.contains(&today.add_days(-5)));
+        assert_eq!(messages(file_manager.read_list(&data_dir.path().join(format!("{}.todo", today.add_days(-1).file_stem()))).unwrap()), vec!["Buy milk"]);
+        assert!(data_dir.path().join(ARCHIVE_DIRECTORY).join(format!("{}.todo", today.add_days(-5).file_stem())).exists());
+    }
+
+    #[test]
+    fn fresh_start_keeps_the_open_todos_planned_for_today() {
+        let data_dir = TestDir::new("fresh");
+        let file_manager = FileManager::new(data_dir.path().to_path_buf());
+        let config = Config { startup : StartupMode::Fresh, ..Config::default() };
+        let yesterday = date::now() - 2 * 86_400;
+        file_manager.save_today(&[
+            TodoData { created_at : Some(yesterday), ..entry("Snoozed", Status::Todo, 0) },
+            TodoData { created_at : Some(date::now()), ..entry("Its step", Status::Done, 1) },
+            TodoData { created_at : Some(yesterday), ..entry("Planned but done", Status::Done, 0) },
+            TodoData { created_at : Some(date::now()), ..entry("Written today", Status::Todo, 0) },
+        ]).unwrap();
+
+        file_manager.initialize(&config).unwrap();
+
+        let messages : Vec<String> = file_manager.load_today().unwrap().into_iter().map(|m| m.message).collect();
+        assert_eq!(messages, vec!["Snoozed", "Its step"]);
+        let archived = file_manager.todo_files(&data_dir.path().join(ARCHIVE_DIRECTORY)).unwrap();
+        assert_eq!(file_manager.read_list(&archived[0]).unwrap().len(), 4);
+    }
+
+    #[test]
+    fn lists_closed_within_a_second_are_all_kept() {
+        let data_dir = TestDir::new("closed");
+        let file_manager = FileManager::new(data_dir.path().to_path_buf());
+        file_manager.close_named_list("Groceries", &[entry("Milk", Status::Todo, 0)]).unwrap();
+        let (name, _) = file_manager.reopen_closed_list(|name| name.to_string()).unwrap().unwrap();
+        file_manager.close_named_list(&name, &[entry("Milk", Status::Done, 0)]).unwrap();
+        file_manager.close_named_list("Books", &[entry("Dune", Status::Todo, 0)]).unwrap();
+        file_manager.close_named_list("Books", &[entry("Emma", Status::Todo, 0)]).unwrap();
+
+        let reopened = |file_manager : &FileManager| {
+            let (name, entries) = file_manager.reopen_closed_list(|name| name.to_string()).unwrap().unwrap();
+            (name, entries[0].message.clone())
+        };
+        assert_eq!(reopened(&file_manager), ("Books".to_string(), "Emma".to_string()));
+        assert_eq!(reopened(&file_manager), ("Books".to_string(), "Dune".to_string()));
+        assert_eq!(reopened(&file_manager), ("Groceries".to_string(), "Milk".to_string()));
+        assert!(file_manager.reopen_closed_list(|name| name.to_string()).unwrap().is_none());
+    }
+
+    #[test]
+    fn json_entries_round_trip() {
+        let original = TodoData {
+            created_at : Some(1664280000),
+            estimate_secs : 3600,
+            actual_secs : 1500,
+            pomodoros : 2,
+            priority : Priority::High,
+            due : date::Date::parse("2022-09-30"),
+            folded : true,
+            recurrence : Some(Recurrence::Daily),
+            notes : "Both\nof them".to_string(),
+            ..entry("Say \"hi\"", Status::InProgress, 1)
+        };
+
+        let parsed = entry_from_json(&json::parse(&entry_to_json(&original).to_pretty_string()).unwrap()).unwrap();
+
+        assert_eq!(parsed.message, original.message);
+        assert_eq!(parsed.status, original.status);
+        assert_eq!(parsed.indent, original.indent);
+        assert_eq!(parsed.created_at, original.created_at);
+        assert_eq!((parsed.estimate_secs, parsed.actual_secs), (original.estimate_secs, original.actual_secs));
+        assert_eq!(parsed.pomodoros, original.pomodoros);
+        assert_eq!(parsed.priority, original.priority);
+        assert_eq!(parsed.due, original.due);
+        assert!(parsed.folded);
+        assert_eq!(parsed.recurrence, original.recurrence);
+        assert_eq!(parsed.notes, original.notes);
+    }
+
+    #[test]
+    fn to_markdown_writes_a_nested_task_list() {
+        let entries = vec![
+            entry("Standup", Status::Done, 0),
+            entry("Review #42", Status::InProgress, 1),
+            entry("", Status::Todo, 0),
+            TodoData { estimate_secs : 3600, actual_secs : 1500, ..entry("Write docs", Status::Todo, 0) },
+        ];
+
+        assert_eq!(
+            FileManager::to_markdown(&entries),
+            "- [x] Standup\n  - [ ] Review #42 *(in progress)*\n- [ ] Write docs *(estimated 1h 00m, spent 25m)*\n");
+    }
+
+    #[test]
+    fn serialize_skips_blank_entries() {
+        let entries = vec![entry("Buy milk", Status::Done, 0), TodoData::default()];
+        assert_eq!(FileManager::serialize(&entries), "[x] Buy milk\n");
+    }
+
+    #[test]
+    fn parse_reads_legacy_and_plain_lines() {
+        let parsed = FileManager::parse("* done\n# todo\n\nplain note \n");
 
-        println!("Contents from file: \n{}", contents);
+        let lines : Vec<(&str, bool)> = parsed.iter().map(|m| (m.message.as_str(), m.status == Status::Done)).collect();
+        assert_eq!(lines, vec![("done", true), ("todo", false), ("plain note", false)]);
     }
 }
\ No newline at end of file
@@ -0,0 +1,44 @@
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+/// Runs one request through curl and returns the response body. `options` are curl config
+/// entries like `("user", "me:secret")` or `("header", "Depth: 1")`. They and the body are
+/// handed over on stdin, so secrets don't show up in the process list.
+pub fn request(method : &str, url : &str, options : &[(&str, String)], body : &str) -> io::Result<String> {
+    let spawned = Command::new("curl")
+        .args(["--silent", "--show-error", "--fail", "--config", "-", "--request", method, url])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn();
+    let mut child = match spawned {
+        Ok(child) => child,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "curl is needed to sync"));
+        }
+        Err(err) => return Err(err)
+    };
+
+    if let Some(mut stdin) = child.stdin.take() {
+        let mut config = String::new();
+        for (name, value) in options {
+            config.push_str(&format!("{} = {}\n", name, quote(value)));
+        }
+        if !body.is_empty() {
+            config.push_str(&format!("data-binary = {}\n", quote(body)));
+        }
+        stdin.write_all(config.as_bytes())?;
+    }
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        let message = String::from_utf8_lossy(&output.stderr);
+        return Err(io::Error::other(format!("{} {}: {}", method, url, message.trim())));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// A string value of a curl config file
+fn quote(text : &str) -> String {
+    let escaped = text.replace('\\', "\\\\").replace('"', "\\\"").replace('\r', "\\r").replace('\n', "\\n");
+    format!("\"{}\"", escaped)
+}
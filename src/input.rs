@@ -0,0 +1,741 @@
+//! The event loop: reading keys and the mouse from the terminal and running the action
+//! they're bound to, with a panic in one shown as an error instead of ending the app
+
+use crossterm::event::{self, Event, KeyCode};
+use std::{
+    cell::{Cell, RefCell},
+    io::{self, Write},
+    panic::{self, AssertUnwindSafe},
+    process::{Command, Stdio},
+    thread,
+    time::Duration,
+};
+use tui::{backend::Backend, Terminal};
+
+use todo_core::TodoData;
+use todo_core::engine::MoveCursorOperation;
+use todo_core::keymap::Action;
+use todo_core::lists::SaveWorker;
+
+use crate::{enter_terminal, leave_terminal, notify};
+use crate::app::{App, InputMode, Popup, PromptKind};
+use crate::ui::ui;
+
+/// Percent of the width a pane grows or shrinks by
+const PANE_STEP : i32 = 5;
+
+thread_local! {
+    /// Set while `run_guarded` runs an action, its panics turn into an error popup
+    static CATCHING_PANICS : Cell<bool> = const { Cell::new(false) };
+    /// Message and location of the panic `run_guarded` caught last
+    static CAUGHT_PANIC : RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Leaves the terminal before a panic is printed, so the message can be read and the shell
+/// isn't left in raw mode. Panics `run_guarded` catches are only written down for its popup.
+pub fn install_panic_hook(alternate_screen : bool) {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        if CATCHING_PANICS.with(Cell::get) {
+            let location = info.location().map_or(String::new(), |location| format!(" ({}:{})", location.file(), location.line()));
+            let message = format!("{}{}", panic_message(info.payload()), location);
+            CAUGHT_PANIC.with(|caught| *caught.borrow_mut() = Some(message));
+            return;
+        }
+        // The interface keeps running when one of the worker threads panics
+        if thread::current().name() == Some("main") {
+            let _ = leave_terminal(alternate_screen);
+        }
+        default_hook(info);
+    }));
+}
+
+fn panic_message(payload : &(dyn std::any::Any + Send)) -> String {
+    match (payload.downcast_ref::<&str>(), payload.downcast_ref::<String>()) {
+        (Some(message), _) => message.to_string(),
+        (_, Some(message)) => message.clone(),
+        _ => "unknown error".to_string()
+    }
+}
+
+/// Runs `action` like `run_action`, but a panic in it shows an error popup instead of
+/// taking the app and the unsaved changes down. The lists stay as the action left them,
+/// with the cursor put back on an entry.
+fn run_guarded<B: Backend>(terminal : &mut Terminal<B>, app : &mut App, action : Action) -> io::Result<bool> {
+    CATCHING_PANICS.with(|catching| catching.set(true));
+    let result = panic::catch_unwind(AssertUnwindSafe(|| run_action(terminal, app, action)));
+    CATCHING_PANICS.with(|catching| catching.set(false));
+
+    let payload = match result {
+        Ok(result) => return result,
+        Err(payload) => payload
+    };
+    let message = CAUGHT_PANIC.with(|caught| caught.borrow_mut().take()).unwrap_or_else(|| panic_message(payload.as_ref()));
+    if app.engine.get_messages().is_empty() {
+        app.engine.push_message(TodoData::default());
+    }
+    app.mode = InputMode::Normal;
+    app.engine.editing = false;
+    app.engine.searching = false;
+    app.engine.clamp_row();
+    app.engine.input = app.engine.get_current_message();
+    app.engine.clamp_column();
+    app.popup = Some(Popup::Error(message));
+    Ok(false)
+}
+
+/// Takes the terminal back after a child process used it
+fn resume_terminal<B: Backend>(terminal: &mut Terminal<B>, alternate_screen : bool) -> io::Result<()> {
+    enter_terminal(alternate_screen)?;
+    terminal.clear()
+}
+
+/// Runs `command` through the shell with `lines` on stdin and returns its stdout lines.
+/// A non-zero exit status is treated as an error so a cancelled filter changes nothing.
+fn run_external_filter(command : &str, lines : &[String]) -> io::Result<Vec<String>> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+
+    // Written from another thread while the output is read, a filter that writes before it
+    // read everything would otherwise block on a full pipe with us blocked writing to it
+    let writer = child.stdin.take().map(|mut stdin| {
+        let lines = lines.to_vec();
+        thread::spawn(move || {
+            for line in lines {
+                // A filter that doesn't read all of its input closes the pipe early
+                if writeln!(stdin, "{}", line).is_err() {
+                    break;
+                }
+            }
+        })
+    });
+
+    let output = child.wait_with_output()?;
+    if let Some(writer) = writer {
+        let _ = writer.join();
+    }
+    if !output.status.success() {
+        return Err(io::Error::other(format!("`{}` exited with {}", command, output.status)));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).lines().map(|line| line.to_string()).collect())
+}
+
+fn pipe_through_filter<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> io::Result<()> {
+    let command = match &app.engine.config.filter_command {
+        Some(command) => command.clone(),
+        None => {
+            app.engine.status_message = Some("No filter_command configured".to_string());
+            return Ok(());
+        }
+    };
+    if app.engine.is_journal() {
+        app.engine.status_message = Some("The journal can't be filtered".to_string());
+        return Ok(());
+    }
+
+    leave_terminal(app.engine.config.alternate_screen)?;
+    let result = run_external_filter(&command, &app.engine.messages_as_lines());
+    resume_terminal(terminal, app.engine.config.alternate_screen)?;
+
+    match result {
+        Ok(lines) => app.engine.apply_filtered_lines(lines),
+        Err(err) => app.engine.status_message = Some(format!("Filter failed: {}", err))
+    }
+
+    Ok(())
+}
+
+/// Where the interface gets its input from, the terminal or a script in tests
+pub trait EventSource {
+    /// The next event, `None` when nothing happened for `timeout`
+    fn next_event(&mut self, timeout : Duration) -> io::Result<Option<Event>>;
+}
+
+/// Keys, mouse events and resizes of the terminal
+pub struct TerminalEvents;
+
+impl EventSource for TerminalEvents {
+    fn next_event(&mut self, timeout : Duration) -> io::Result<Option<Event>> {
+        if !event::poll(timeout)? {
+            return Ok(None);
+        }
+        event::read().map(Some)
+    }
+}
+
+/// Does what a normal mode `action` is for, returns whether the interface should close
+fn run_action<B: Backend>(terminal: &mut Terminal<B>, app: &mut App, action : Action) -> io::Result<bool> {
+    match action {
+        Action::Edit => {
+            app.engine.start_editing();
+        }
+        Action::Append => {
+            app.engine.append();
+        }
+        Action::Details => {
+            app.show_details = !app.show_details;
+        }
+        Action::EditNotes => {
+            app.start_editing_notes();
+        }
+        Action::OpenLink => {
+            app.open_link();
+        }
+        Action::QuickAdd => {
+            app.show_quick_add();
+        }
+        Action::Snooze => {
+            app.start_snooze();
+        }
+        Action::BlockedBy => {
+            app.edit_blocked_by();
+        }
+        Action::Help => {
+            app.popup = Some(Popup::Help(0));
+        }
+        Action::Command => {
+            app.open_prompt(PromptKind::Command);
+        }
+        Action::SplitView => {
+            app.toggle_split_view();
+        }
+        Action::SwitchPane => {
+            app.switch_pane();
+        }
+        Action::MoveInput => {
+            app.engine.config.input_at_bottom = !app.engine.config.input_at_bottom;
+            app.save_layout();
+        }
+        Action::HelpBar => {
+            app.engine.config.help_bar = !app.engine.config.help_bar;
+            app.save_layout();
+        }
+        Action::GrowPane => {
+            app.resize_pane(PANE_STEP);
+        }
+        Action::ShrinkPane => {
+            app.resize_pane(-PANE_STEP);
+        }
+        Action::ToggleDone => {
+            app.engine.toggle_message_status();
+        }
+        Action::Top => {
+            app.engine.select_first();
+        }
+        Action::Bottom => {
+            app.engine.select_last();
+        }
+        Action::PageUp => {
+            app.move_page(false);
+        }
+        Action::PageDown => {
+            app.move_page(true);
+        }
+        Action::Indent => {
+            app.engine.indent();
+        }
+        Action::Outdent => {
+            app.engine.outdent();
+        }
+        Action::Fold => {
+            app.engine.toggle_fold();
+        }
+        Action::NewList => {
+            app.open_prompt(PromptKind::NewListName);
+        }
+        Action::RenameList => {
+            app.start_rename_list();
+        }
+        Action::DeleteList => {
+            app.start_delete_list();
+        }
+        Action::ReopenList => {
+            app.engine.reopen_list();
+        }
+        Action::PurgeClosed => {
+            app.open_prompt(PromptKind::ConfirmPurgeClosed);
+        }
+        Action::MoveUp => {
+            app.engine.move_entry(true);
+        }
+        Action::MoveDown => {
+            app.engine.move_entry(false);
+        }
+        Action::Quit => {
+            return Ok(true);
+        }
+        Action::SwitchList => {
+            app.engine.change_target_mode();
+        }
+        Action::Redo => {
+            app.engine.redo();
+        }
+        Action::Remove => {
+            app.request_remove();
+        }
+        Action::Undo => {
+            app.engine.undo();
+        }
+        Action::RaisePriority => {
+            app.engine.change_priority(true);
+        }
+        Action::LowerPriority => {
+            app.engine.change_priority(false);
+        }
+        Action::DueDate => {
+            app.edit_due_date();
+        }
+        Action::Repeat => {
+            app.edit_recurrence();
+        }
+        Action::Estimate => {
+            app.edit_estimate();
+        }
+        Action::Search => {
+            app.engine.start_search();
+        }
+        Action::NextMatch => {
+            app.engine.jump_to_match(true);
+        }
+        Action::PreviousMatch => {
+            app.engine.jump_to_match(false);
+        }
+        Action::Done => {
+            if app.engine.marked_indices().is_empty() {
+                app.engine.toggle_message_status();
+            } else {
+                app.engine.toggle_marked_status();
+            }
+        }
+        Action::InProgress => {
+            app.engine.toggle_in_progress();
+        }
+        Action::Mark => {
+            app.engine.toggle_mark();
+        }
+        Action::Visual => {
+            app.engine.toggle_visual();
+        }
+        Action::InsertTop => {
+            app.engine.insert_at_top();
+        }
+        Action::MoveToOtherList => {
+            app.engine.move_to_other_list();
+        }
+        Action::Yank => {
+            app.yank(false);
+        }
+        Action::Cut => {
+            app.yank(true);
+        }
+        Action::Paste => {
+            app.paste();
+        }
+        Action::OpenBelow => {
+            app.engine.open_line(true);
+        }
+        Action::OpenAbove => {
+            app.engine.open_line(false);
+        }
+        Action::Sort => {
+            app.engine.sort_by_default();
+        }
+        Action::Filter => {
+            pipe_through_filter(terminal, app)?;
+        }
+        Action::FocusTimer => {
+            app.toggle_timer();
+        }
+        Action::TrackTime => {
+            app.toggle_tracking();
+        }
+        Action::Someday => {
+            app.someday_action();
+        }
+        Action::TrackedTime => {
+            app.engine.status_message = Some(app.engine.daily_time_summary());
+        }
+        Action::TagFilter => {
+            app.toggle_tag_filter();
+        }
+        Action::NextTag => {
+            app.move_tag_cursor(1);
+        }
+        Action::PreviousTag => {
+            app.move_tag_cursor(-1);
+        }
+        Action::DayDiff => {
+            app.show_day_diff();
+        }
+        Action::Stats => {
+            app.show_stats();
+        }
+        Action::Calendar => {
+            app.show_calendar();
+        }
+        Action::Sync => {
+            app.sync();
+        }
+        Action::History => {
+            app.show_history();
+        }
+        Action::Profiles => {
+            app.show_profiles();
+        }
+        Action::PlanDay => {
+            app.open_prompt(PromptKind::PlanDay);
+            if let Some(prompt) = &mut app.prompt {
+                prompt.text = "tomorrow".to_string();
+            }
+        }
+        Action::Export => {
+            app.open_prompt(PromptKind::ExportPath);
+        }
+        Action::Jump => {
+            app.mode = InputMode::Jumping;
+        }
+        Action::Up => {
+            app.engine.move_cursor(MoveCursorOperation::Up);
+        }
+        Action::Down => {
+            app.engine.move_cursor(MoveCursorOperation::Down);
+        }
+        Action::Left => {
+            app.engine.move_cursor(MoveCursorOperation::Left);
+        }
+        Action::Right => {
+            app.engine.move_cursor(MoveCursorOperation::Right);
+        }
+    }
+    Ok(false)
+}
+
+pub fn run_app<B: Backend, E: EventSource>(terminal: &mut Terminal<B>, app: &mut App, events : &mut E) -> io::Result<()> {
+    if app.engine.config.notifications {
+        app.notifier = Some(notify::spawn());
+    }
+    app.engine.saver = Some(SaveWorker::spawn(app.engine.file_manager.clone()));
+
+    loop {
+        if app.on_tick() {
+            // Ring the terminal bell
+            print!("\x07");
+            io::stdout().flush()?;
+        }
+
+        app.engine.update_visual_marks();
+        terminal.draw(|f| ui(f, app))?;
+
+        let event = match events.next_event(app.tick_rate())? {
+            Some(event) => event,
+            None => continue
+        };
+        if let Event::Mouse(mouse) = event {
+            if app.popup.is_none() && !matches!(app.input_mode(), InputMode::Notes) {
+                app.handle_mouse(mouse);
+            }
+            continue;
+        }
+
+        if let Event::Key(key) = event {
+            app.engine.status_message = None;
+
+            if let Some(popup) = app.popup.take() {
+                match popup {
+                    Popup::ConfirmRemove(_) if key.code == KeyCode::Char('y') => app.engine.remove_message(),
+                    Popup::Calendar(calendar) => app.calendar_key(calendar, key),
+                    Popup::History(history) => app.history_key(history, key),
+                    Popup::Profiles(profiles) => app.profiles_key(profiles, key),
+                    Popup::QuickAdd(quick_add) => app.quick_add_key(quick_add, key),
+                    Popup::Help(scroll) => app.help_key(scroll, key),
+                    _ => {}
+                }
+                if app.switch_to.is_some() {
+                    return Ok(());
+                }
+                continue;
+            }
+
+            match app.input_mode() {
+                InputMode::Normal => {
+                    if let Some(action) = app.normal_mode_action(key) {
+                        if run_guarded(terminal, app, action)? {
+                            return Ok(());
+                        }
+                    }
+                }
+
+                InputMode::Editing => match key.code {
+                    KeyCode::Enter => {
+                        app.engine.new_line();
+                    }
+                    KeyCode::Tab => {
+                        app.engine.handle_tab();
+                    }
+                    KeyCode::BackTab => {
+                        app.engine.outdent();
+                    }
+                    KeyCode::Char(c) => {
+                        app.engine.add_char(c);
+                    }
+                    KeyCode::Backspace => {
+                        app.engine.remove_char();
+                    }
+                    KeyCode::Esc => {
+                        if app.work_hours_warning().is_some() {
+                            app.work_hours_dismissed = true;
+                        }
+                        app.engine.cancel_edit();
+                    }
+                    KeyCode::Up => {
+                        app.engine.move_cursor(MoveCursorOperation::Up);
+                    }
+                    KeyCode::Down => {
+                        app.engine.move_cursor(MoveCursorOperation::Down);
+                    }
+                    KeyCode::Left => {
+                        app.engine.move_cursor(MoveCursorOperation::Left);
+                    }
+                    KeyCode::Right => {
+                        app.engine.move_cursor(MoveCursorOperation::Right);
+                    }
+                    KeyCode::Home => {
+                        app.engine.move_cursor(MoveCursorOperation::LineStart);
+                    }
+                    KeyCode::End => {
+                        app.engine.move_cursor(MoveCursorOperation::LineEnd);
+                    }
+                    _ => {}
+                },
+
+                InputMode::Notes => match key.code {
+                    KeyCode::Esc => {
+                        app.commit_notes();
+                    }
+                    KeyCode::Enter => {
+                        app.notes_add_char('\n');
+                    }
+                    KeyCode::Char(c) => {
+                        app.notes_add_char(c);
+                    }
+                    KeyCode::Backspace => {
+                        app.notes_remove_char();
+                    }
+                    KeyCode::Left => {
+                        app.notes_move_cursor(false);
+                    }
+                    KeyCode::Right => {
+                        app.notes_move_cursor(true);
+                    }
+                    _ => {}
+                },
+
+                InputMode::Jumping => match key.code {
+                    KeyCode::Esc | KeyCode::Enter => {
+                        app.mode = InputMode::Normal;
+                    }
+                    KeyCode::Char(c) => {
+                        app.engine.jump_to_letter(c);
+                    }
+                    _ => {}
+                },
+
+                InputMode::Searching => match key.code {
+                    KeyCode::Enter => {
+                        app.engine.finish_search();
+                    }
+                    KeyCode::Esc => {
+                        app.engine.cancel_search();
+                    }
+                    KeyCode::Backspace => {
+                        app.engine.search_remove_char();
+                    }
+                    KeyCode::Char(c) => {
+                        app.engine.search_add_char(c);
+                    }
+                    _ => {}
+                },
+
+                InputMode::Prompting => match key.code {
+                    KeyCode::Enter => {
+                        app.submit_prompt();
+                    }
+                    KeyCode::Esc => {
+                        app.close_prompt();
+                    }
+                    KeyCode::Backspace => {
+                        app.prompt_remove_char();
+                    }
+                    KeyCode::Char(c) => {
+                        app.prompt_add_char(c);
+                    }
+                    _ => {}
+                },
+            }
+
+            if let Some(action) = app.queued_action.take() {
+                if run_guarded(terminal, app, action)? {
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::{KeyEvent, KeyModifiers};
+    use todo_core::Status;
+    use todo_core::config::{Config, DEFAULT_PROFILE};
+    use todo_core::file_manager::FileManager;
+    use todo_core::keymap::{Key, Resolution};
+    use todo_core::lists::ListRole;
+
+    use crate::app::Popup;
+    use crate::app::tests::{daily_app, messages_of, todo};
+    use crate::test_dir::TestDir;
+
+    #[test]
+    fn keymap_from_config_rebinds_and_takes_over_default_keys() {
+        let config = Config::parse("[keys]\nquit = \"x\"\nremove = \"q\"\n", DEFAULT_PROFILE);
+        let press = |c : char| config.keymap.resolve(&[Key::from_event(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE))]);
+
+        assert_eq!(press('x'), Resolution::Action(Action::Quit));
+        assert_eq!(press('q'), Resolution::Action(Action::Remove));
+        assert_eq!(press('r'), Resolution::Unbound);
+        assert_eq!(press('e'), Resolution::Action(Action::Edit));
+    }
+
+    #[test]
+    fn external_filter_output_larger_than_a_pipe_buffer_does_not_block() {
+        let lines : Vec<String> = (0..20_000).map(|number| format!("todo number {}", number)).collect();
+
+        assert_eq!(run_external_filter("cat", &lines).unwrap(), lines);
+        assert!(run_external_filter("exit 3", &lines).is_err());
+    }
+
+    /// Plays back a script, running out of it ends `run_app` with an error
+    struct ScriptedEvents(std::collections::VecDeque<Event>);
+
+    impl ScriptedEvents {
+        fn keys(codes : impl IntoIterator<Item = KeyCode>) -> Self {
+            ScriptedEvents(codes.into_iter().map(|code| Event::Key(KeyEvent::from(code))).collect())
+        }
+    }
+
+    impl EventSource for ScriptedEvents {
+        fn next_event(&mut self, _timeout : Duration) -> io::Result<Option<Event>> {
+            let event = self.0.pop_front().ok_or_else(|| io::Error::new(io::ErrorKind::UnexpectedEof, "the script ran out"))?;
+            Ok(Some(event))
+        }
+    }
+
+    fn typed(text : &str) -> Vec<KeyCode> {
+        text.chars().map(KeyCode::Char).collect()
+    }
+
+    /// Runs `app` with `script` on a `width` x `height` test terminal and returns the text
+    /// of the last frame, one line per row
+    fn run_headless(app : &mut App, script : Vec<KeyCode>, width : u16, height : u16) -> Vec<String> {
+        let data_dir = TestDir::new("headless");
+        app.engine.file_manager = FileManager::new(data_dir.path().to_path_buf());
+        app.engine.config.notifications = false;
+        let mut terminal = Terminal::new(tui::backend::TestBackend::new(width, height)).unwrap();
+
+        run_app(&mut terminal, app, &mut ScriptedEvents::keys(script)).unwrap();
+        let buffer = terminal.backend().buffer();
+        (0..height)
+            .map(|y| (0..width).map(|x| buffer.get(x, y).symbol.clone()).collect::<String>().trim_end().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn scripted_keys_drive_the_event_loop() {
+        let mut app = daily_app(vec![todo("")]);
+        let mut script = vec![KeyCode::Char('e')];
+        script.extend(typed("Buy milk"));
+        // Enter goes back to normal mode on a fresh entry
+        script.extend([KeyCode::Enter, KeyCode::Char('e')]);
+        script.extend(typed("Call mum"));
+        script.extend([KeyCode::Enter, KeyCode::Up, KeyCode::Up, KeyCode::Char('d'), KeyCode::Char('q')]);
+
+        run_headless(&mut app, script, 60, 20);
+
+        assert_eq!(messages_of(&app), vec!["Buy milk", "Call mum", ""]);
+        assert_eq!(app.engine.get_messages()[0].status, Status::Done);
+        assert_eq!(app.engine.get_messages()[1].status, Status::Todo);
+    }
+
+    #[test]
+    fn split_view_shows_both_lists_and_tab_moves_between_them() {
+        let mut app = daily_app(vec![todo("Buy milk"), todo("")]);
+        *app.engine.entries_mut(ListRole::LongTerm) = vec![todo("Learn piano"), todo("")];
+
+        let screen = run_headless(&mut app, vec![KeyCode::Char('|'), KeyCode::Tab, KeyCode::Char('q')], 60, 14);
+
+        assert_eq!(app.engine.active_list().role, ListRole::LongTerm);
+        assert!(screen.iter().any(|line| line.contains("┌Daily") && line.contains("┌Long Term")));
+        assert!(screen.iter().any(|line| line.contains("[ ] Buy milk") && line.contains("> [ ] Learn piano")));
+    }
+
+    #[test]
+    fn input_box_can_go_below_the_list_without_the_help_bar() {
+        let mut app = daily_app(vec![todo("Buy milk"), todo("")]);
+        app.engine.config.input_at_bottom = true;
+        app.engine.config.help_bar = false;
+
+        let screen = run_headless(&mut app, vec![KeyCode::Char('q')], 40, 12);
+
+        let row = |start : &str| screen.iter().position(|line| line.trim_start().starts_with(start)).unwrap();
+        assert!(row("┌Daily") < row("┌Input"));
+        assert_eq!(row("┌Daily"), 3);
+        assert!(!screen.iter().any(|line| line.contains("Press q")));
+    }
+
+    #[test]
+    fn a_panicking_action_becomes_an_error_popup() {
+        let mut app = daily_app(vec![todo("Buy milk"), todo("")]);
+        let mut terminal = Terminal::new(tui::backend::TestBackend::new(40, 12)).unwrap();
+        // An index no entry has, like a bug would leave behind
+        app.engine.target_row = 5;
+
+        assert!(!run_guarded(&mut terminal, &mut app, Action::Done).unwrap());
+
+        assert!(matches!(&app.popup, Some(Popup::Error(message)) if message.contains("out of bounds")));
+        assert_eq!(app.engine.target_row, 1);
+        assert_eq!(messages_of(&app), vec!["Buy milk", ""]);
+    }
+
+    #[test]
+    fn rendered_screen_matches_the_snapshot() {
+        let mut app = daily_app(vec![
+            TodoData { status : Status::Done, ..todo("Buy milk") },
+            todo("Call mum"),
+        ]);
+
+        let screen = run_headless(&mut app, vec![KeyCode::Char('q')], 50, 14);
+
+        assert_eq!(screen, vec![
+            "",
+            "",
+            "   Daily │ Long Term │ Journal │ Someday / Maybe",
+            "  Press q to exit, e to start editing t to chang",
+            "  ┌Input───────────────────────────────────────┐",
+            "  │                                            │",
+            "  └────────────────────────────────────────────┘",
+            "  ┌Daily───────────────────────────────────────┐",
+            "  │> [x] Buy milk                              │",
+            "  │  [ ] Call mum                              │",
+            "  └────────────────────────────────────────────┘",
+            "  2 items, 1 done, 1 left [█████░░░░░] 50%",
+            "",
+            "",
+        ]);
+    }
+}
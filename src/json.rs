@@ -0,0 +1,312 @@
+use std::fmt::Write;
+
+/// A parsed JSON document. Objects keep their keys in file order.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    /// Value stored under `key` when this is an object
+    pub fn get(&self, key : &str) -> Option<&Value> {
+        match self {
+            Value::Object(fields) => fields.iter().find(|(name, _)| name == key).map(|(_, value)| value),
+            _ => None
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(text) => Some(text),
+            _ => None
+        }
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            Value::Number(number) if *number >= 0.0 && number.fract() == 0.0 => Some(*number as u64),
+            _ => None
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(value) => Some(*value),
+            _ => None
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Value::Array(values) => Some(values),
+            _ => None
+        }
+    }
+
+    /// Writes the value with two spaces of indentation per level
+    pub fn to_pretty_string(&self) -> String {
+        let mut out = String::new();
+        self.write_pretty(&mut out, 0);
+        out.push('\n');
+        out
+    }
+
+    fn write_pretty(&self, out : &mut String, depth : usize) {
+        let indent = "  ".repeat(depth + 1);
+        match self {
+            Value::Null => out.push_str("null"),
+            Value::Bool(value) => out.push_str(if *value { "true" } else { "false" }),
+            Value::Number(number) => {
+                let _ = write!(out, "{}", number);
+            }
+            Value::String(text) => write_string(out, text),
+            Value::Array(values) if values.is_empty() => out.push_str("[]"),
+            Value::Array(values) => {
+                out.push_str("[\n");
+                for (index, value) in values.iter().enumerate() {
+                    out.push_str(&indent);
+                    value.write_pretty(out, depth + 1);
+                    out.push_str(if index + 1 < values.len() { ",\n" } else { "\n" });
+                }
+                out.push_str(&"  ".repeat(depth));
+                out.push(']');
+            }
+            Value::Object(fields) if fields.is_empty() => out.push_str("{}"),
+            Value::Object(fields) => {
+                out.push_str("{\n");
+                for (index, (key, value)) in fields.iter().enumerate() {
+                    out.push_str(&indent);
+                    write_string(out, key);
+                    out.push_str(": ");
+                    value.write_pretty(out, depth + 1);
+                    out.push_str(if index + 1 < fields.len() { ",\n" } else { "\n" });
+                }
+                out.push_str(&"  ".repeat(depth));
+                out.push('}');
+            }
+        }
+    }
+}
+
+fn write_string(out : &mut String, text : &str) {
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c)
+        }
+    }
+    out.push('"');
+}
+
+/// Parses a complete JSON document, the error names what was expected and where
+pub fn parse(text : &str) -> Result<Value, String> {
+    let mut parser = Parser { chars : text.chars().collect(), position : 0 };
+    let value = parser.value()?;
+    parser.skip_whitespace();
+    if parser.position < parser.chars.len() {
+        return Err(parser.error("end of input"));
+    }
+    Ok(value)
+}
+
+struct Parser {
+    chars : Vec<char>,
+    position : usize,
+}
+
+impl Parser {
+    fn error(&self, expected : &str) -> String {
+        format!("expected {} at character {}", expected, self.position + 1)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.position += 1;
+        }
+    }
+
+    fn expect(&mut self, expected : char) -> Result<(), String> {
+        self.skip_whitespace();
+        if self.peek() != Some(expected) {
+            return Err(self.error(&format!("'{}'", expected)));
+        }
+        self.position += 1;
+        Ok(())
+    }
+
+    fn keyword(&mut self, word : &str, value : Value) -> Result<Value, String> {
+        for expected in word.chars() {
+            if self.peek() != Some(expected) {
+                return Err(self.error(word));
+            }
+            self.position += 1;
+        }
+        Ok(value)
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('n') => self.keyword("null", Value::Null),
+            Some('t') => self.keyword("true", Value::Bool(true)),
+            Some('f') => self.keyword("false", Value::Bool(false)),
+            Some('"') => self.string().map(Value::String),
+            Some('[') => self.array(),
+            Some('{') => self.object(),
+            Some(c) if c == '-' || c.is_ascii_digit() => self.number(),
+            _ => Err(self.error("a value"))
+        }
+    }
+
+    fn number(&mut self) -> Result<Value, String> {
+        let start = self.position;
+        while self.peek().is_some_and(|c| c.is_ascii_digit() || "+-.eE".contains(c)) {
+            self.position += 1;
+        }
+        let text : String = self.chars[start..self.position].iter().collect();
+        text.parse().map(Value::Number).map_err(|_| {
+            self.position = start;
+            self.error("a number")
+        })
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut text = String::new();
+        loop {
+            let c = self.peek().ok_or_else(|| self.error("'\"'"))?;
+            self.position += 1;
+            match c {
+                '"' => return Ok(text),
+                '\\' => {
+                    let escaped = self.peek().ok_or_else(|| self.error("an escape"))?;
+                    self.position += 1;
+                    match escaped {
+                        '"' | '\\' | '/' => text.push(escaped),
+                        'n' => text.push('\n'),
+                        'r' => text.push('\r'),
+                        't' => text.push('\t'),
+                        'b' => text.push('\u{8}'),
+                        'f' => text.push('\u{c}'),
+                        'u' => text.push(self.unicode_escape()?),
+                        _ => return Err(self.error("an escape"))
+                    }
+                }
+                c => text.push(c)
+            }
+        }
+    }
+
+    /// The `XXXX` of a `\uXXXX` escape, joining surrogate pairs
+    fn unicode_escape(&mut self) -> Result<char, String> {
+        let high = self.hex4()?;
+        if !(0xD800..0xDC00).contains(&high) {
+            return char::from_u32(high).ok_or_else(|| self.error("a character"));
+        }
+
+        if self.peek() != Some('\\') || self.chars.get(self.position + 1) != Some(&'u') {
+            return Err(self.error("a low surrogate"));
+        }
+        self.position += 2;
+        let low = self.hex4()?;
+        let code = 0x10000 + ((high - 0xD800) << 10) + (low.wrapping_sub(0xDC00) & 0x3FF);
+        char::from_u32(code).ok_or_else(|| self.error("a character"))
+    }
+
+    fn hex4(&mut self) -> Result<u32, String> {
+        let digits : String = self.chars.iter().skip(self.position).take(4).collect();
+        let code = u32::from_str_radix(&digits, 16).map_err(|_| self.error("four hex digits"))?;
+        self.position += 4;
+        Ok(code)
+    }
+
+    fn array(&mut self) -> Result<Value, String> {
+        self.expect('[')?;
+        let mut values = vec![];
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.position += 1;
+            return Ok(Value::Array(values));
+        }
+
+        loop {
+            values.push(self.value()?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => self.position += 1,
+                Some(']') => {
+                    self.position += 1;
+                    return Ok(Value::Array(values));
+                }
+                _ => return Err(self.error("',' or ']'"))
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<Value, String> {
+        self.expect('{')?;
+        let mut fields = vec![];
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.position += 1;
+            return Ok(Value::Object(fields));
+        }
+
+        loop {
+            self.skip_whitespace();
+            let key = self.string()?;
+            self.expect(':')?;
+            fields.push((key, self.value()?));
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => self.position += 1,
+                Some('}') => {
+                    self.position += 1;
+                    return Ok(Value::Object(fields));
+                }
+                _ => return Err(self.error("',' or '}'"))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pretty_output_parses_back_to_the_same_value() {
+        let value = Value::Object(vec![
+            ("name".to_string(), Value::String("Say \"hi\"\n\tüñí 😀".to_string())),
+            ("count".to_string(), Value::Number(1664280000.0)),
+            ("flags".to_string(), Value::Array(vec![Value::Bool(true), Value::Null])),
+            ("empty".to_string(), Value::Array(vec![])),
+        ]);
+
+        assert_eq!(parse(&value.to_pretty_string()), Ok(value));
+    }
+
+    #[test]
+    fn parse_reads_escapes_and_reports_errors() {
+        assert_eq!(parse(r#""\u00e9\ud83d\ude00""#), Ok(Value::String("é😀".to_string())));
+        assert!(parse("[1, 2").is_err());
+        assert!(parse("{\"a\": 1} x").is_err());
+    }
+}
@@ -0,0 +1,431 @@
+use std::collections::HashMap;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// Something a key can be bound to in normal mode
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Action {
+    Edit,
+    /// Starts editing one character after the cursor, vim's `a`
+    Append,
+    /// Shows or hides the pane with the notes of the selected todo
+    Details,
+    /// Edits the notes of the selected todo in the detail pane
+    EditNotes,
+    /// Opens the first link in the selected todo or its notes in the browser
+    OpenLink,
+    /// Popup that adds a todo to a list without moving the selection
+    QuickAdd,
+    /// Moves the selected daily todo into the file of a later day
+    Snooze,
+    /// Asks which todo the selected one waits for
+    BlockedBy,
+    /// Overlay listing every key binding
+    Help,
+    /// Command line for the features without a key of their own
+    Command,
+    /// Shows the daily and the long term list side by side
+    SplitView,
+    /// Moves the focus to the other list of the split view, Tab does too while it's shown
+    SwitchPane,
+    /// Puts the input box above or below the list
+    MoveInput,
+    /// Shows or hides the line of key hints
+    HelpBar,
+    /// Widens the focused list against the other one of the split view, or else against the
+    /// notes pane
+    GrowPane,
+    ShrinkPane,
+    Quit,
+    SwitchList,
+    Remove,
+    Done,
+    ToggleDone,
+    InProgress,
+    Mark,
+    /// Marks everything the cursor passes over until it's pressed again
+    Visual,
+    InsertTop,
+    /// Moves the selected todo between the daily and the long term list
+    MoveToOtherList,
+    /// Copies the selected todo and the ones nested below it
+    Yank,
+    Cut,
+    Paste,
+    /// Starts a new todo below the selected one
+    OpenBelow,
+    /// Starts a new todo above the selected one
+    OpenAbove,
+    /// Sorts the active list by its default ordering, A-Z unless `:sort!` saved another one
+    Sort,
+    Filter,
+    FocusTimer,
+    /// Starts or stops the stopwatch on the selected todo
+    TrackTime,
+    Someday,
+    TrackedTime,
+    TagFilter,
+    NextTag,
+    PreviousTag,
+    DayDiff,
+    /// Chart of the todo's finished over the last days
+    Stats,
+    /// Month grid of the days with a list or due todo's
+    Calendar,
+    /// Asks for a day to open, creating its file when it's ahead
+    PlanDay,
+    /// Picker of every day with a file, newest first
+    History,
+    /// Picker of the profiles in the config, switching reloads everything from the other one
+    Profiles,
+    /// Syncs with the CalDAV server and Todoist project set up in the config
+    Sync,
+    Export,
+    Jump,
+    Undo,
+    Redo,
+    RaisePriority,
+    LowerPriority,
+    DueDate,
+    /// Asks how often the selected todo repeats
+    Repeat,
+    /// Asks how long the selected todo should take
+    Estimate,
+    Search,
+    NextMatch,
+    PreviousMatch,
+    Up,
+    Down,
+    Left,
+    Right,
+    Top,
+    Bottom,
+    PageUp,
+    PageDown,
+    MoveUp,
+    MoveDown,
+    Indent,
+    Outdent,
+    /// Hides or shows the entries nested below the selected one
+    Fold,
+    NewList,
+    RenameList,
+    DeleteList,
+    /// Brings back the most recently deleted list
+    ReopenList,
+    /// Empties the stash of deleted lists for good
+    PurgeClosed,
+}
+
+impl Action {
+    /// Whether the action changes the entries of the active list, those are refused on a
+    /// read-only list
+    pub fn changes_list(self) -> bool {
+        !matches!(self,
+            Action::Quit | Action::SwitchList | Action::Mark | Action::Visual | Action::Yank
+            | Action::TrackedTime | Action::TagFilter | Action::NextTag | Action::PreviousTag
+            | Action::DayDiff | Action::Stats | Action::Calendar | Action::PlanDay | Action::History
+            | Action::Profiles | Action::Sync | Action::Export | Action::Jump | Action::Search
+            | Action::NextMatch | Action::PreviousMatch | Action::Up | Action::Down | Action::Left
+            | Action::Right | Action::Top | Action::Bottom | Action::PageUp | Action::PageDown
+            | Action::Fold | Action::NewList | Action::DeleteList | Action::ReopenList
+            | Action::PurgeClosed | Action::Details | Action::OpenLink
+            | Action::QuickAdd | Action::Help | Action::Command | Action::SplitView | Action::SwitchPane
+            | Action::MoveInput | Action::HelpBar | Action::GrowPane | Action::ShrinkPane)
+    }
+
+    /// Every bindable action with its name in the config, in the order of the defaults
+    pub fn all() -> impl Iterator<Item = (&'static str, Action)> {
+        BINDINGS.iter().map(|(name, action, _)| (*name, *action))
+    }
+}
+
+/// `(name in the config, action, default keys)` for every bindable action. Alternative
+/// keys are separated by spaces, an empty default leaves the action unbound unless the
+/// config or the vim scheme binds it.
+const BINDINGS : [(&str, Action, &str); 77] = [
+    ("edit", Action::Edit, "e"),
+    ("append", Action::Append, ""),
+    ("details", Action::Details, "enter"),
+    ("edit_notes", Action::EditNotes, "E"),
+    ("open_link", Action::OpenLink, "L"),
+    ("quick_add", Action::QuickAdd, "A"),
+    ("snooze", Action::Snooze, "z"),
+    ("blocked_by", Action::BlockedBy, "b"),
+    ("help", Action::Help, "?"),
+    ("command", Action::Command, ":"),
+    ("split_view", Action::SplitView, "|"),
+    ("switch_pane", Action::SwitchPane, ""),
+    ("move_input", Action::MoveInput, "ctrl+t"),
+    ("help_bar", Action::HelpBar, "ctrl+b"),
+    ("grow_pane", Action::GrowPane, "ctrl+right"),
+    ("shrink_pane", Action::ShrinkPane, "ctrl+left"),
+    ("quit", Action::Quit, "q"),
+    ("switch_list", Action::SwitchList, "t"),
+    ("remove", Action::Remove, "r"),
+    ("done", Action::Done, "d"),
+    ("toggle_done", Action::ToggleDone, ""),
+    ("in_progress", Action::InProgress, "~"),
+    ("mark", Action::Mark, "space"),
+    ("visual", Action::Visual, "v"),
+    ("insert_top", Action::InsertTop, "I"),
+    ("move_to_other_list", Action::MoveToOtherList, "m"),
+    ("yank", Action::Yank, "y"),
+    ("cut", Action::Cut, "ctrl+x"),
+    ("paste", Action::Paste, "p"),
+    ("open_below", Action::OpenBelow, "o"),
+    ("open_above", Action::OpenAbove, "O"),
+    ("sort", Action::Sort, "a"),
+    ("filter", Action::Filter, "!"),
+    ("focus_timer", Action::FocusTimer, "P"),
+    ("track_time", Action::TrackTime, "T"),
+    ("someday", Action::Someday, "M"),
+    ("tracked_time", Action::TrackedTime, "="),
+    ("tag_filter", Action::TagFilter, "#"),
+    ("next_tag", Action::NextTag, "]"),
+    ("previous_tag", Action::PreviousTag, "["),
+    ("day_diff", Action::DayDiff, "Y"),
+    ("stats", Action::Stats, "s"),
+    ("calendar", Action::Calendar, "C"),
+    ("plan_day", Action::PlanDay, "ctrl+o"),
+    ("history", Action::History, "H"),
+    ("profiles", Action::Profiles, "ctrl+p"),
+    ("sync", Action::Sync, "S"),
+    ("export", Action::Export, "w"),
+    ("jump", Action::Jump, "f"),
+    ("undo", Action::Undo, "u"),
+    ("redo", Action::Redo, "ctrl+r"),
+    ("raise_priority", Action::RaisePriority, "+"),
+    ("lower_priority", Action::LowerPriority, "-"),
+    ("due_date", Action::DueDate, "D"),
+    ("repeat", Action::Repeat, "R"),
+    ("estimate", Action::Estimate, "ctrl+e"),
+    ("search", Action::Search, "/"),
+    ("next_match", Action::NextMatch, "n"),
+    ("previous_match", Action::PreviousMatch, "N"),
+    ("up", Action::Up, "up"),
+    ("down", Action::Down, "down"),
+    ("left", Action::Left, "left"),
+    ("right", Action::Right, "right"),
+    ("top", Action::Top, "home"),
+    ("bottom", Action::Bottom, "end"),
+    ("page_up", Action::PageUp, "pageup"),
+    ("page_down", Action::PageDown, "pagedown"),
+    ("move_up", Action::MoveUp, "K shift+up"),
+    ("move_down", Action::MoveDown, "J shift+down"),
+    ("indent", Action::Indent, "tab"),
+    ("outdent", Action::Outdent, "shift+tab"),
+    ("fold", Action::Fold, "c"),
+    ("new_list", Action::NewList, "ctrl+n"),
+    ("rename_list", Action::RenameList, "f2"),
+    ("delete_list", Action::DeleteList, "ctrl+w"),
+    ("reopen_list", Action::ReopenList, "U"),
+    ("purge_closed", Action::PurgeClosed, "X"),
+];
+
+/// Extra bindings of `scheme = "vim"`. They win over a default binding of the same key,
+/// so `a` appends instead of sorting and `d` only starts `dd`.
+const VIM_BINDINGS : [(Action, &str); 10] = [
+    (Action::Left, "h"),
+    (Action::Down, "j"),
+    (Action::Up, "k"),
+    (Action::Right, "l"),
+    (Action::Edit, "i"),
+    (Action::Append, "a"),
+    (Action::Remove, "dd"),
+    (Action::ToggleDone, "x"),
+    (Action::Top, "gg"),
+    (Action::Bottom, "G"),
+];
+
+/// What the keys typed so far in normal mode amount to
+#[derive(PartialEq, Debug)]
+pub enum Resolution {
+    Action(Action),
+    /// The keys start a longer binding like `dd`, wait for the next one
+    Pending,
+    Unbound,
+}
+
+/// A key press as written in the config: a single character (`q`, `I`, `#`) or a named key
+/// (`space`, `enter`, `tab`, `up`, `down`, `left`, `right`, `home`, `end`, `pageup`, `pagedown`,
+/// `f1`-`f12`), either with a `ctrl+` prefix.
+/// Named keys can also take a `shift+` prefix, characters already include Shift (`I`).
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Key {
+    code : KeyCode,
+    ctrl : bool,
+    shift : bool,
+}
+
+impl Key {
+    pub fn parse(text : &str) -> Option<Key> {
+        let (ctrl, name) = match text.strip_prefix("ctrl+") {
+            Some(name) => (true, name),
+            None => (false, text)
+        };
+        let (shift, name) = match name.strip_prefix("shift+") {
+            Some(name) => (true, name),
+            None => (false, name)
+        };
+
+        let code = match name {
+            "space" => KeyCode::Char(' '),
+            "enter" => KeyCode::Enter,
+            "tab" => KeyCode::Tab,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            _ if name.len() > 1 && name.starts_with('f') && name[1..].parse::<u8>().is_ok() => {
+                KeyCode::F(name[1..].parse().unwrap_or(1))
+            }
+            _ => {
+                let mut chars = name.chars();
+                match (chars.next(), chars.next(), shift) {
+                    (Some(c), None, false) => KeyCode::Char(c),
+                    _ => return None
+                }
+            }
+        };
+
+        Some(Key { code, ctrl, shift })
+    }
+
+    /// A single key, or a sequence of characters like `dd` or `gg`
+    pub fn parse_sequence(text : &str) -> Option<Vec<Key>> {
+        if text.is_empty() {
+            return None;
+        }
+
+        match Key::parse(text) {
+            Some(key) => Some(vec![key]),
+            None => Some(text.chars().map(|c| Key { code : KeyCode::Char(c), ctrl : false, shift : false }).collect())
+        }
+    }
+
+    /// Shift is left out for characters, it's already part of the character (`I` rather than `i`).
+    /// Terminals report Shift+Tab as its own key, it's read as `shift+tab`.
+    pub fn from_event(event : KeyEvent) -> Key {
+        if event.code == KeyCode::BackTab {
+            return Key { code : KeyCode::Tab, ctrl : event.modifiers.contains(KeyModifiers::CONTROL), shift : true };
+        }
+
+        Key {
+            code : event.code,
+            ctrl : event.modifiers.contains(KeyModifiers::CONTROL),
+            shift : !matches!(event.code, KeyCode::Char(_)) && event.modifiers.contains(KeyModifiers::SHIFT)
+        }
+    }
+
+    /// How the key is written in the config and the help line
+    pub fn name(&self) -> String {
+        let name = match self.code {
+            KeyCode::Char(' ') => "space".to_string(),
+            KeyCode::Char(c) => c.to_string(),
+            KeyCode::Enter => "enter".to_string(),
+            KeyCode::Tab => "tab".to_string(),
+            KeyCode::Up => "up".to_string(),
+            KeyCode::Down => "down".to_string(),
+            KeyCode::Left => "left".to_string(),
+            KeyCode::Right => "right".to_string(),
+            KeyCode::Home => "home".to_string(),
+            KeyCode::End => "end".to_string(),
+            KeyCode::PageUp => "pageup".to_string(),
+            KeyCode::PageDown => "pagedown".to_string(),
+            KeyCode::F(number) => format!("f{}", number),
+            _ => "?".to_string()
+        };
+
+        let name = if self.shift { format!("shift+{}", name) } else { name };
+        if self.ctrl { format!("ctrl+{}", name) } else { name }
+    }
+}
+
+/// Normal mode key bindings, the defaults with the `[keys]` section of the config on top
+#[derive(Clone)]
+pub struct KeyMap {
+    bindings : Vec<(Vec<Key>, Action)>,
+}
+
+impl Default for KeyMap {
+    fn default() -> Self {
+        Self::from_config(&HashMap::new())
+    }
+}
+
+impl KeyMap {
+    /// Reads `keys.<action> = "<keys>"` entries, an empty value unbinds the action, and
+    /// `keys.scheme = "vim"` for the vim bindings. A key bound in the config takes
+    /// precedence over a default or vim binding of the same key.
+    pub fn from_config(values : &HashMap<String, String>) -> Self {
+        let mut keymap = KeyMap { bindings : vec![] };
+        let mut configured = vec![];
+
+        for (name, action, default) in BINDINGS {
+            match values.get(&format!("keys.{}", name)) {
+                Some(text) => configured.push((action, text.as_str())),
+                None => {
+                    for keys in default.split_whitespace().filter_map(Key::parse_sequence) {
+                        keymap.bind(keys, action);
+                    }
+                }
+            }
+        }
+
+        if values.get("keys.scheme").is_some_and(|scheme| scheme == "vim") {
+            for (action, text) in VIM_BINDINGS {
+                if let Some(keys) = Key::parse_sequence(text) {
+                    keymap.bind(keys, action);
+                }
+            }
+        }
+
+        for (action, text) in configured {
+            keymap.bindings.retain(|(_, bound)| *bound != action);
+            for keys in text.split_whitespace().filter_map(Key::parse_sequence) {
+                keymap.bind(keys, action);
+            }
+        }
+
+        keymap
+    }
+
+    /// Adds a binding, dropping the ones it would be ambiguous with: the same keys, or
+    /// keys that are the start of the other binding (`d` next to `dd`)
+    fn bind(&mut self, keys : Vec<Key>, action : Action) {
+        self.bindings.retain(|(bound, _)| !bound.starts_with(&keys) && !keys.starts_with(bound));
+        self.bindings.push((keys, action));
+    }
+
+    /// Looks up the keys typed so far, `typed` ends with the latest key press
+    pub fn resolve(&self, typed : &[Key]) -> Resolution {
+        if let Some((_, action)) = self.bindings.iter().find(|(keys, _)| keys.as_slice() == typed) {
+            return Resolution::Action(*action);
+        }
+
+        if self.bindings.iter().any(|(keys, _)| keys.starts_with(typed)) {
+            Resolution::Pending
+        } else {
+            Resolution::Unbound
+        }
+    }
+
+    /// Name of the first key bound to `action`, `-` when it's unbound
+    pub fn key_name(&self, action : Action) -> String {
+        self.key_names(action).into_iter().next().unwrap_or_else(|| "-".to_string())
+    }
+
+    /// Names of every key or sequence like `dd` bound to `action`, empty when it's unbound
+    pub fn key_names(&self, action : Action) -> Vec<String> {
+        self.bindings
+            .iter()
+            .filter(|(_, bound)| *bound == action)
+            .map(|(keys, _)| if keys.len() == 1 { keys[0].name() } else { keys.iter().map(Key::name).collect() })
+            .collect()
+    }
+}
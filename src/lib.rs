@@ -0,0 +1,54 @@
+//! The todo lists without the interface: the entries and what can be done to them, the
+//! settings, and the files and services they're kept in. The `todo_rust` binary is a
+//! terminal frontend on top of this.
+//!
+//! Lists are plain `Vec<TodoData>`s. `add`, `remove`, `toggle`, `set_status` and
+//! `move_entry` change them, `file_manager::FileManager` loads and saves them:
+//!
+//! ```no_run
+//! use todo_core::file_manager::FileManager;
+//! use todo_core::{date, Status};
+//!
+//! let file_manager = FileManager::new(FileManager::resolve_data_dir(None));
+//! let mut entries = file_manager.load_today()?;
+//! let index = todo_core::add(&mut entries, "Buy milk");
+//! todo_core::toggle(&mut entries, index, date::Date::today());
+//! assert_eq!(entries[index].status, Status::Done);
+//! file_manager.save_today(&entries)?;
+//! # Ok::<(), todo_core::file_manager::Error>(())
+//! ```
+//!
+//! A frontend showing several lists at once keeps them as `lists::TodoList`s, which
+//! `lists::write_lists` saves and `lists::SaveWorker` saves off the frontend's thread.
+//! `undo::History` takes changes back a step at a time, and `sync` merges what a CalDAV
+//! server or Todoist project answered into the lists.
+//!
+//! `engine::Engine` puts these together into the whole app without its interface: the
+//! open lists with a cursor, the line being typed, undo, autosave and syncing. The binary
+//! only adds key handling, prompts, popups and drawing around one. Other tools can open
+//! one on a data directory with `Engine::open` and change the lists by role and index.
+
+pub mod caldav;
+pub mod config;
+pub mod date;
+pub mod engine;
+pub mod file_manager;
+mod http;
+pub mod json;
+pub mod keymap;
+pub mod links;
+pub mod lists;
+pub mod sort;
+pub mod sync;
+pub mod tags;
+#[cfg(test)]
+mod test_dir;
+pub mod text;
+mod todo;
+pub mod todoist;
+pub mod undo;
+
+pub use todo::{
+    add, child_count, is_folded_away, move_entry, progress, remove, schedule_next_occurrence, set_status,
+    sort_by_priority, toggle, Priority, Recurrence, Status, TodoData,
+};
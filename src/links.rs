@@ -0,0 +1,51 @@
+/// Byte ranges of the web links in `text`.
+///
+/// A link is a word starting with `http://` or `https://`. Punctuation right behind it,
+/// like the full stop ending a sentence or the `)` closing a remark, is not part of it.
+pub fn find_urls(text : &str) -> Vec<(usize, usize)> {
+    let mut ranges = vec![];
+    let mut offset = 0;
+
+    for word in text.split_inclusive(char::is_whitespace) {
+        let start = offset;
+        offset += word.len();
+        let word = word.trim_end();
+        if !word.starts_with("http://") && !word.starts_with("https://") {
+            continue;
+        }
+
+        // A `)` stays when it closes a `(` inside the link, as in Wikipedia's
+        let mut link = word;
+        while let Some(last) = link.chars().last() {
+            let unbalanced = last == ')' && link.matches('(').count() < link.matches(')').count();
+            if !unbalanced && !".,;:!?'\"".contains(last) {
+                break;
+            }
+            link = &link[..link.len() - 1];
+        }
+        if !link.ends_with("://") {
+            ranges.push((start, start + link.len()));
+        }
+    }
+
+    ranges
+}
+
+/// The first link in `text`
+pub fn first_url(text : &str) -> Option<&str> {
+    find_urls(text).first().map(|(start, end)| &text[*start..*end])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn links_leave_out_the_punctuation_behind_them() {
+        let text = "Read https://example.com/a_(b) (see http://example.org/x?y=1). Not https:// or ftp://z";
+
+        let links : Vec<&str> = find_urls(text).iter().map(|(start, end)| &text[*start..*end]).collect();
+        assert_eq!(links, vec!["https://example.com/a_(b)", "http://example.org/x?y=1"]);
+        assert_eq!(first_url("no link here"), None);
+    }
+}
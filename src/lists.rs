@@ -0,0 +1,197 @@
+//! The lists a frontend shows side by side: the built-in ones, the ones created by the user
+//! and days opened from the calendar, and how they're written back to the data directory
+
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::Instant;
+
+use crate::date;
+use crate::file_manager::{self, FileManager};
+use crate::TodoData;
+
+/// What a list is for, decides where it's saved and which features use it
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ListRole {
+    /// The list of the day it was loaded for, written to that day's file until the next
+    /// day is started
+    Daily(date::Date),
+    LongTerm,
+    Journal,
+    /// Deferred ideas, kept out of the active lists and their stats
+    Someday,
+    /// Created by the user, saved as `lists/<name>.todo`
+    Named,
+    /// A day opened from the calendar. Past days are read-only, future ones are saved to
+    /// their day file to plan ahead.
+    Day(date::Date)
+}
+
+impl ListRole {
+    /// Whether the list's todo's are synced with the CalDAV server
+    pub fn is_synced(self) -> bool {
+        matches!(self, ListRole::Daily(_) | ListRole::LongTerm | ListRole::Named)
+    }
+}
+
+/// Lists that are always there, in tab bar order, the daily one starting on `today`
+pub fn built_in_lists(today : date::Date) -> [(&'static str, ListRole); 4] {
+    [
+        ("Daily", ListRole::Daily(today)),
+        ("Long Term", ListRole::LongTerm),
+        ("Journal", ListRole::Journal),
+        ("Someday / Maybe", ListRole::Someday),
+    ]
+}
+
+/// One list of the tab bar
+#[derive(Clone)]
+pub struct TodoList {
+    /// Stays the same while other lists are added and deleted, unlike the list's index
+    pub id : usize,
+    pub name : String,
+    pub role : ListRole,
+    pub entries : Vec<TodoData>,
+    /// Where the list was left off when switching away from it
+    pub view : ListView
+}
+
+/// Cursor and scroll position of a list, kept while another list is shown
+#[derive(Copy, Clone, Default)]
+pub struct ListView {
+    pub row : i32,
+    pub column : i32,
+    pub offset : usize
+}
+
+/// Index of the built-in list with `role`
+pub fn role_index(lists : &[TodoList], role : ListRole) -> usize {
+    lists.iter().position(|list| list.role == role).expect("built-in lists are never removed")
+}
+
+/// Index of the list with `id`, `None` once it was deleted
+pub fn list_index(lists : &[TodoList], id : usize) -> Option<usize> {
+    lists.iter().position(|list| list.id == id)
+}
+
+/// Day the daily list belongs to, today unless the date changed since it was loaded
+pub fn daily_date(lists : &[TodoList]) -> date::Date {
+    lists
+        .iter()
+        .find_map(|list| match list.role {
+            ListRole::Daily(day) => Some(day),
+            _ => None
+        })
+        .expect("built-in lists are never removed")
+}
+
+/// Inserts `entry` in front of the trailing blank entry of `entries`, the one typed into
+pub fn insert_before_blank(entries : &mut Vec<TodoData>, entry : TodoData) {
+    let position = if entries.last().is_some_and(|m| m.message.is_empty()) { entries.len() - 1 } else { entries.len() };
+    entries.insert(position, entry);
+}
+
+/// Writes every list to its file, but the days that are past. The daily list goes to the
+/// day it was loaded for, even once that day is over. All lists are tried, the first
+/// failure is returned.
+pub fn write_lists(file_manager : &FileManager, lists : &[TodoList]) -> file_manager::Result<()> {
+    let mut result = Ok(());
+    for list in lists {
+        let saved = match list.role {
+            ListRole::Daily(day) => file_manager.save_day(day, &list.entries),
+            ListRole::LongTerm => file_manager.save_long_term(&list.entries),
+            ListRole::Someday => file_manager.save_someday(&list.entries),
+            ListRole::Journal => file_manager.save_journal(&list.entries),
+            ListRole::Named => file_manager.save_named_list(&list.name, &list.entries),
+            // A day planned ahead that turned into today is still written until the daily list takes it over
+            ListRole::Day(day) if day >= date::Date::today() => file_manager.save_day(day, &list.entries),
+            // Past days are read-only
+            ListRole::Day(_) => Ok(())
+        };
+        result = result.and(saved);
+    }
+    result
+}
+
+/// Outcome of the last save
+#[derive(PartialEq, Debug)]
+pub enum SaveStatus {
+    /// Nothing was written lately
+    Clean,
+    /// Handed to the save worker, not written yet
+    Saving,
+    /// Written at the given time
+    Saved(Instant),
+    Failed(String)
+}
+
+/// Thread writing the lists to disk, so a slow disk never stalls the frontend. Saves are
+/// written in the order they were sent, each one is answered with its result.
+pub struct SaveWorker {
+    pub sender : mpsc::Sender<Vec<TodoList>>,
+    pub results : Receiver<file_manager::Result<()>>,
+    pub handle : thread::JoinHandle<()>,
+    /// Saves sent that weren't answered yet
+    pub pending : usize
+}
+
+impl SaveWorker {
+    pub fn spawn(file_manager : FileManager) -> Self {
+        let (sender, requests) = mpsc::channel::<Vec<TodoList>>();
+        let (answers, results) = mpsc::channel();
+        let handle = thread::spawn(move || {
+            for lists in requests {
+                let _ = answers.send(write_lists(&file_manager, &lists));
+            }
+        });
+        SaveWorker { sender, results, handle, pending : 0 }
+    }
+
+    /// Status to show after a save was answered with `result`
+    pub fn answered(&mut self, result : file_manager::Result<()>) -> SaveStatus {
+        self.pending -= 1;
+        match result {
+            Err(err) => SaveStatus::Failed(err.to_string()),
+            Ok(()) if self.pending > 0 => SaveStatus::Saving,
+            Ok(()) => SaveStatus::Saved(Instant::now())
+        }
+    }
+
+    /// Waits for the saves still in flight
+    pub fn finish(self) {
+        drop(self.sender);
+        let _ = self.handle.join();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TestDir;
+
+    fn list(id : usize, role : ListRole, messages : &[&str]) -> TodoList {
+        let entries = messages.iter().map(|message| TodoData { message : message.to_string(), ..TodoData::default() }).collect();
+        TodoList { id, name : format!("list {}", id), role, entries, view : ListView::default() }
+    }
+
+    #[test]
+    fn lists_are_written_to_their_own_files_and_past_days_are_left_alone() {
+        let data_dir = TestDir::new("lists");
+        let file_manager = FileManager::new(data_dir.path().to_path_buf());
+        let today = date::Date::today();
+        let lists = vec![
+            list(0, ListRole::Daily(today.add_days(-1)), &["Call the bank", ""]),
+            list(1, ListRole::LongTerm, &["Learn Rust"]),
+            list(2, ListRole::Day(today), &["Planned"]),
+            list(3, ListRole::Day(today.add_days(-3)), &["Changed by mistake"]),
+        ];
+
+        write_lists(&file_manager, &lists).unwrap();
+
+        let messages = |entries : Option<Vec<TodoData>>| entries.unwrap_or_default().into_iter().map(|m| m.message).collect::<Vec<_>>();
+        assert_eq!(messages(file_manager.load_day(today.add_days(-1)).unwrap()), vec!["Call the bank"]);
+        assert_eq!(messages(Some(file_manager.load_long_term().unwrap())), vec!["Learn Rust"]);
+        assert_eq!(messages(file_manager.load_day(today).unwrap()), vec!["Planned"]);
+        assert!(file_manager.load_day(today.add_days(-3)).unwrap().is_none());
+        assert_eq!((daily_date(&lists), role_index(&lists, ListRole::LongTerm), list_index(&lists, 3)), (today.add_days(-1), 1, Some(3)));
+    }
+}
@@ -3,454 +3,308 @@ use crossterm::{
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use std::{error::Error, io};
+use std::{
+    env,
+    error::Error,
+    io,
+    path::Path,
+    time::Duration,
+};
 use tui::{
     backend::{Backend, CrosstermBackend},
-    layout::{Constraint, Direction, Layout},
-    style::{Color, Modifier, Style},
-    text::{Span, Spans, Text},
-    widgets::{Block, Borders, List, ListItem, Paragraph},
-    Frame, Terminal,
+    Terminal,
 };
-use unicode_width::UnicodeWidthStr;
 
-extern crate num;
 use std::cmp;
 
-mod file_manager;
-
-enum InputMode {
-    Normal,
-    Editing,
-}
-
-#[derive(PartialEq)]
-enum TargetMode {
-    Daily,
-    LongTerm
-}
-
-#[derive(Copy, Clone)]
-enum Status {
-    Todo,
-    Done
+mod app;
+mod cli;
+mod browser;
+mod clipboard;
+mod input;
+mod notify;
+#[cfg(test)]
+mod test_dir;
+mod ui;
+
+use todo_core::date;
+use todo_core::config::{Config, DEFAULT_PROFILE};
+use todo_core::engine::Engine;
+use todo_core::file_manager::FileManager;
+use todo_core::lists::SaveStatus;
+
+use app::App;
+use input::{install_panic_hook, run_app, EventSource, TerminalEvents};
+use ui::render_passphrase;
+
+/// Environment variable with the passphrase of encrypted lists, for commands
+const PASSPHRASE_VARIABLE : &str = "TODO_RUST_PASSPHRASE";
+
+/// The argument following `flag`, like the path in `--export-json backup.json`
+fn flag_value(args : &[String], flag : &str) -> Option<String> {
+    let position = args.iter().position(|arg| arg == flag)?;
+    args.get(position + 1).cloned()
 }
 
-#[derive(Clone)]
-struct TodoData {
-    message: String,
-    status: Status
-}
-
-impl Default for TodoData {
-    fn default() -> TodoData {
-        TodoData {
-            message: String::new(),
-            status: Status::Todo
-        }
+fn main() {
+    if let Err(err) = run() {
+        eprintln!("{}", err);
+        std::process::exit(1);
     }
 }
 
-enum MoveCursorOperation {
-    Right,
-    Left,
-    Up,
-    Down,
+/// Removes `flag` and the value after it from `args`, returning the value
+fn take_flag_value(args : &mut Vec<String>, flag : &str) -> Option<String> {
+    let position = args.iter().position(|arg| arg == flag)?;
+    let value = args.get(position + 1).cloned();
+    args.drain(position..cmp::min(position + 2, args.len()));
+    value
 }
 
-/// App holds the state of the application
-struct App {
-    /// Current value of the input box
-    input: String,
-    /// Current input mode
-    input_mode: InputMode,
-    /// Wether to write to daily or long term todo's
-    target_mode: TargetMode,
-    /// Daily todo's
-    messages: Vec<TodoData>,
-    /// Long term todo's
-    long_term_todo : Vec<TodoData>,
-    target_row : i32,
-    target_column : i32
-}
+fn run() -> Result<(), Box<dyn Error>> {
+    let mut args : Vec<String> = env::args().collect();
+    let data_dir = FileManager::resolve_data_dir(take_flag_value(&mut args, "--data-dir").as_deref());
+    let profile = take_flag_value(&mut args, "--profile").unwrap_or_else(|| DEFAULT_PROFILE.to_string());
+    let (config, mut file_manager) = open_profile(&data_dir, &profile);
+    if !config.profiles.contains(&profile) {
+        return Err(format!("No profile called {}, add a [profile.{}] section to the config", profile, profile).into());
+    }
 
-impl Default for App {
-    fn default() -> App {
-        App {
-            input: String::new(),
-            input_mode: InputMode::Normal,
-            target_mode: TargetMode::Daily,
-            messages: vec![TodoData::default()],
-            long_term_todo : vec![TodoData::default()],
-            target_row : 0,
-            target_column : 0
-        }
+    if env::args().any(|arg| arg == "--log") {
+        print!("{}", file_manager.read_rollover_log()?);
+        return Ok(());
     }
-}
 
-impl App {
-    fn get_messages(&self) -> Vec<TodoData> {
-        match self.target_mode {
-            TargetMode::Daily => {
-                self.messages.clone()
-            },
-            TargetMode::LongTerm => {
-                self.long_term_todo.clone()
-            }
+    let is_command = cli::is_command(&args[1..]) || flag_value(&args, "--export-json").is_some() || flag_value(&args, "--import-json").is_some();
+    if is_command {
+        // Commands stop at the first error, the interface starts anyway and shows it
+        if config.encrypt {
+            let passphrase = env::var(PASSPHRASE_VARIABLE)
+                .map_err(|_| format!("The lists are encrypted, set {} to use commands", PASSPHRASE_VARIABLE))?;
+            file_manager = file_manager.unlock(&passphrase)?.ok_or("Wrong passphrase")?;
         }
+        file_manager.initialize(&config)?;
     }
 
-    fn get_messages_mut(&mut self) -> &mut Vec<TodoData> {
-        match self.target_mode {
-            TargetMode::Daily => {
-                &mut self.messages
-            },
-            TargetMode::LongTerm => {
-                &mut self.long_term_todo
-            }
+    if let Some(result) = cli::run(&file_manager, &args[1..]) {
+        if let Err(err) = result {
+            eprintln!("{}", err);
+            std::process::exit(1);
         }
+        return Ok(());
     }
-
-    fn get_current_message(&self) -> String {
-        self.get_messages()[self.target_row as usize].message.clone()
+    if let Some(path) = flag_value(&args, "--export-json") {
+        let count = file_manager.export_json(&FileManager::expand_path(&path))?;
+        println!("Exported {} lists to {}", count, path);
+        return Ok(());
     }
-
-    fn push_message(&mut self, new_entry : TodoData) {
-        self.input = String::new();
-        self.get_messages_mut().push(new_entry);
+    if let Some(path) = flag_value(&args, "--import-json") {
+        let count = file_manager.import_json(&FileManager::expand_path(&path))?;
+        println!("Imported {} lists from {}", count, path);
+        return Ok(());
     }
 
-    fn add_char(&mut self, new_char : char) {
-        let input_index = self.target_column as usize;
-        self.input.insert(input_index, new_char);
-        let target_index = self.target_row;
-        self.get_messages_mut()[target_index as usize].message = self.input.clone();
-        self.target_column += 1;
+    // setup terminal
+    let alternate_screen = config.alternate_screen;
+    let guard = TerminalGuard::enter(alternate_screen)?;
+    install_panic_hook(alternate_screen);
+    let backend = CrosstermBackend::new(io::stdout());
+    let mut terminal = Terminal::new(backend)?;
+    if !alternate_screen {
+        // Drawing inline starts from whatever is on screen, wipe it first
+        terminal.clear()?;
     }
 
-    fn remove_char(&mut self) {
-        if self.input.is_empty() {
-            return;
+    // create app and run it, again for every profile switched to
+    let mut opened = (config, file_manager);
+    let (mut app, res) = loop {
+        let (config, mut file_manager) = opened;
+        if config.encrypt {
+            match ask_passphrase(&mut terminal, &mut TerminalEvents, &file_manager)? {
+                Some(unlocked) => file_manager = unlocked,
+                None => {
+                    guard.leave()?;
+                    return Ok(terminal.show_cursor()?);
+                }
+            }
         }
-
-        if self.target_column <= 0 {
-            return;
+        let startup_error = file_manager.initialize(&config).err();
+
+        let mut app = App {
+            engine : Engine::new(config, file_manager),
+            ..App::default()
+        };
+        if let Err(err) = app.engine.load_lists() {
+            app.engine.load_failed = true;
+            app.engine.save_status = SaveStatus::Failed(format!("lists failed to load, not saving ({})", err));
+        } else if let Some(err) = startup_error {
+            app.engine.status_message = Some(format!("Startup failed: {}", err));
+        } else if app.engine.config.encrypt && app.engine.config.status_file.is_some() {
+            app.engine.status_message = Some("The status file isn't written while the lists are encrypted".to_string());
+        }
+        let res = run_app(&mut terminal, &mut app, &mut TerminalEvents);
+        match app.switch_to.take() {
+            Some(profile) if res.is_ok() => {
+                let (mut config, file_manager) = open_profile(&data_dir, &profile);
+                // The terminal stays the way it was set up
+                config.alternate_screen = alternate_screen;
+                opened = (config, file_manager);
+            }
+            _ => break (app, res)
         }
+    };
 
-        self.input.remove((self.target_column - 1) as usize);
-        let target_index = self.target_row;
-        self.get_messages_mut()[target_index as usize].message = self.input.clone();
-        self.target_column -=1;
+    // restore terminal
+    guard.leave()?;
+    if !alternate_screen {
+        // Keep the last frame and continue below it
+        let size = terminal.size()?;
+        terminal.set_cursor(0, size.height.saturating_sub(1))?;
+        println!();
     }
+    terminal.show_cursor()?;
 
-    fn clamp_row(&mut self)
-    {
-        self.target_row = num::clamp(self.target_row, 0, self.get_messages().len() as i32 - 1);
+    if let Err(err) = res {
+        println!("{:?}", err)
     }
 
-    fn clamp_column(&mut self)
-    {
-        let current_message_length = cmp::max(self.get_current_message().len() as i32 - 1, 0);
-        self.target_column = num::clamp(self.target_column, 0, current_message_length);
+    app.stop_tracking();
+    if app.engine.load_failed {
+        println!("Lists failed to load, changes weren't saved");
+    } else if let Err(err) = app.engine.save_lists() {
+        println!("Failed to save lists! {}", err);
     }
 
-    fn move_cursor(&mut self, move_operation : MoveCursorOperation) {
-        match move_operation {
-            MoveCursorOperation::Down => {
-                self.target_row += 1; 
-            }
-            MoveCursorOperation::Up => {
-                self.target_row -= 1;
-            }
-            MoveCursorOperation::Left => {
-                self.target_column -= 1;
-            }
-            MoveCursorOperation::Right => {
-                self.target_column += 1;
-            }
+    if app.engine.config.summary_on_quit {
+        let (done, total) = app.engine.daily_progress();
+        let summary = format!("{}: {}/{} done", date::Date::today().format(), done, total);
+        if let Err(err) = app.engine.file_manager.append_summary(&summary) {
+            println!("Failed to write summary! {}", err);
         }
-        
-        self.clamp_row();
-        self.clamp_column();
-
-        let cur_messages = self.get_messages();
-        self.input = cur_messages[self.target_row as usize].message.clone();
     }
 
-    fn new_line(&mut self)
-    {
-        let prev_messages = self.get_messages();
-
-        // 1. Push new line to todo queue as we've finished writing current one
-        if self.target_row as usize >= prev_messages.len() - 1 {
-            let new_entry = TodoData { 
-                message : String::new(),
-                status : Status::Todo
-            };
-            self.push_message(new_entry);
-            self.target_row += 1;
-            self.target_column = 0;
-        }
+    Ok(())
+}
 
-        let cur_messages = self.get_messages();
-        self.input = cur_messages[self.target_row as usize].message.clone();
-        self.input_mode = InputMode::Normal;
+/// The settings of `profile` with the command line flags applied, and its lists
+fn open_profile(data_dir : &Path, profile : &str) -> (Config, FileManager) {
+    let mut config = Config::load(profile);
+    if env::args().any(|arg| arg == "--no-alt-screen") {
+        config.alternate_screen = false;
     }
-
-    fn change_target_mode(&mut self) 
-    {
-        if self.target_mode == TargetMode::Daily {
-            self.target_mode = TargetMode::LongTerm;
-            self.target_row = 0;
-        }
-        else {
-            self.target_mode = TargetMode::Daily;
-            self.target_row = 0;
-        }
-
-        let cur_messages = self.get_messages();
-        self.input = cur_messages[self.target_row as usize].message.clone();
+    if env::args().any(|arg| arg == "--ascii") {
+        config.ascii = true;
     }
+    let file_manager = FileManager::for_profile(data_dir, &config);
+    (config, file_manager)
+}
 
-    fn remove_message(&mut self) {
-        let index_to_remove = self.target_row as usize;
-        let cur_messages = self.get_messages_mut();
-        cur_messages.remove(index_to_remove);
-
-        if cur_messages.is_empty() {
-            let new_entry = TodoData { 
-                message : String::new(),
-                status : Status::Todo
-            };
-            self.push_message(new_entry);
+/// Asks for the passphrase of the encrypted lists before anything is loaded, twice when
+/// it's being chosen. `None` when Esc was pressed.
+fn ask_passphrase<B: Backend, E: EventSource>(terminal : &mut Terminal<B>, events : &mut E, file_manager : &FileManager) -> io::Result<Option<FileManager>> {
+    let choosing = !file_manager.has_passphrase();
+    let mut typed = String::new();
+    let mut chosen : Option<String> = None;
+    let mut message = None;
+    loop {
+        let title = match (choosing, &chosen) {
+            (false, _) => "Passphrase",
+            (true, None) => "Choose a passphrase for the lists",
+            (true, Some(_)) => "Repeat the passphrase"
+        };
+        terminal.draw(|f| render_passphrase(f, title, typed.chars().count(), message))?;
+
+        let key = match events.next_event(Duration::from_secs(1))? {
+            Some(Event::Key(key)) => key,
+            _ => continue
+        };
+        match key.code {
+            KeyCode::Esc => return Ok(None),
+            KeyCode::Char('c') if key.modifiers.contains(event::KeyModifiers::CONTROL) => return Ok(None),
+            KeyCode::Char(c) => typed.push(c),
+            KeyCode::Backspace => {
+                typed.pop();
+            }
+            KeyCode::Enter if typed.is_empty() => {}
+            KeyCode::Enter if choosing && chosen.is_none() => {
+                chosen = Some(std::mem::take(&mut typed));
+                message = None;
+            }
+            KeyCode::Enter if choosing && chosen.as_ref() != Some(&typed) => {
+                chosen = None;
+                typed.clear();
+                message = Some("The passphrases didn't match, choose again");
+            }
+            KeyCode::Enter => {
+                terminal.draw(|f| render_passphrase(f, title, typed.chars().count(), Some("Unlocking...")))?;
+                match file_manager.unlock(&typed)? {
+                    Some(unlocked) => return Ok(Some(unlocked)),
+                    None => {
+                        typed.clear();
+                        message = Some("Wrong passphrase");
+                    }
+                }
+            }
+            _ => {}
         }
-
-        self.move_cursor(MoveCursorOperation::Up);
-    }
-
-    fn set_message_status(&mut self, new_status : Status)
-    {
-        let target_index = self.target_row as usize;
-        let cur_messages = self.get_messages_mut();
-        cur_messages[target_index].status = new_status;
     }
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let file_manager = file_manager::FileManager::new();
-    file_manager.initialize();
-
-    // setup terminal
+/// Puts the terminal in raw mode, on the alternate screen unless drawing inline
+fn enter_terminal(alternate_screen : bool) -> io::Result<()> {
     enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
-
-    // create app and run it
-    let app = App::default();
-    let res = run_app(&mut terminal, app);
+    if alternate_screen {
+        execute!(io::stdout(), EnterAlternateScreen)?;
+    }
+    execute!(io::stdout(), EnableMouseCapture)
+}
 
-    // restore terminal
+fn leave_terminal(alternate_screen : bool) -> io::Result<()> {
     disable_raw_mode()?;
-    execute!(
-        terminal.backend_mut(),
-        LeaveAlternateScreen,
-        DisableMouseCapture
-    )?;
-    terminal.show_cursor()?;
-
-    if let Err(err) = res {
-        println!("{:?}", err)
+    if alternate_screen {
+        execute!(io::stdout(), LeaveAlternateScreen)?;
     }
-
-    Ok(())
+    execute!(io::stdout(), DisableMouseCapture)
 }
 
-fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> io::Result<()> {
-    loop {
-        terminal.draw(|f| ui(f, &app))?;
-
+/// Leaves the terminal the way `enter_terminal` found it when dropped, also when `run`
+/// returns early with an error or unwinds from a panic
+struct TerminalGuard {
+    alternate_screen : bool,
+    left : bool
+}
 
-        if let Event::Key(key) = event::read()? {
-            match app.input_mode {
-                InputMode::Normal => match key.code {
-                    KeyCode::Char('e') => {
-                        app.input_mode = InputMode::Editing;
-                    }
-                    KeyCode::Char('q') => {
-                        return Ok(());
-                    }
-                    KeyCode::Char('t') => {
-                        app.change_target_mode();
-                    }
-                    KeyCode::Char('r') => {
-                        app.remove_message();
-                    }
-                    KeyCode::Char('d') => {
-                        app.set_message_status(Status::Done);
-                    }
-                    KeyCode::Up => {
-                        app.move_cursor(MoveCursorOperation::Up);
-                    }
-                    KeyCode::Down => {
-                        app.move_cursor(MoveCursorOperation::Down);
-                    }
-                    KeyCode::Left => {
-                        app.move_cursor(MoveCursorOperation::Left);
-                    }
-                    KeyCode::Right => {
-                        app.move_cursor(MoveCursorOperation::Right);
-                    }
-                    _ => {}
-                },
+impl TerminalGuard {
+    fn enter(alternate_screen : bool) -> io::Result<TerminalGuard> {
+        enter_terminal(alternate_screen)?;
+        Ok(TerminalGuard { alternate_screen, left : false })
+    }
 
-                InputMode::Editing => match key.code {
-                    KeyCode::Enter => {
-                        app.new_line();
-                    }
-                    KeyCode::Char(c) => {
-                        app.add_char(c);
-                    }
-                    KeyCode::Backspace => {
-                        app.remove_char();
-                    }
-                    KeyCode::Esc => {
-                        app.input_mode = InputMode::Normal;
-                    }
-                    KeyCode::Up => {
-                        app.move_cursor(MoveCursorOperation::Up);
-                    }
-                    KeyCode::Down => {
-                        app.move_cursor(MoveCursorOperation::Down);
-                    }
-                    KeyCode::Left => {
-                        app.move_cursor(MoveCursorOperation::Left);
-                    }
-                    KeyCode::Right => {
-                        app.move_cursor(MoveCursorOperation::Right);
-                    }
-                    _ => {}
-                },
-            }
-        }
+    /// Leaves the terminal now, reporting what went wrong unlike dropping the guard
+    fn leave(mut self) -> io::Result<()> {
+        self.left = true;
+        leave_terminal(self.alternate_screen)
     }
 }
 
-fn get_title(app : &App) -> String {
-        match app.target_mode {
-        TargetMode::Daily => {
-            "Daily".to_string() 
-        },
-        TargetMode::LongTerm => {
-            "Long Term".to_string()
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        if !self.left {
+            let _ = leave_terminal(self.alternate_screen);
         }
     }
 }
 
-fn ui<B: Backend>(f: &mut Frame<B>, app: &App) {
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .margin(2)
-        .constraints(
-            [
-                Constraint::Length(1),
-                Constraint::Length(3),
-                Constraint::Min(1),
-            ]
-            .as_ref(),
-        )
-        .split(f.size());
-
-    let (msg, style) = match app.input_mode {
-        InputMode::Normal => (
-            vec![
-                Span::raw("Press "),
-                Span::styled("q", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(" to exit, "),
-                Span::styled("e", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(" to start editing "),
-                Span::styled("t", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(" to change todo type "),
-                Span::styled("d", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(" to set status to done "),
-                Span::styled("r", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(" to remove the message "),
-            ],
-            Style::default().add_modifier(Modifier::RAPID_BLINK),
-        ),
-        InputMode::Editing => (
-            vec![
-                Span::raw("Press "),
-                Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(" to stop editing, "),
-                Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(" to record the message"),
-            ],
-            Style::default(),
-        ),
-    };
-    let mut text = Text::from(Spans::from(msg));
-    text.patch_style(style);
-    let help_message = Paragraph::new(text);
-    f.render_widget(help_message, chunks[0]);
-
-    let input = Paragraph::new(app.input.as_ref())
-        .style(match app.input_mode {
-            InputMode::Normal => Style::default(),
-            InputMode::Editing => Style::default().fg(Color::Yellow),
-        })
-        .block(Block::default().borders(Borders::ALL).title("Input"));
-    f.render_widget(input, chunks[1]);
-    match app.input_mode {
-        InputMode::Normal =>
-            // Hide the cursor. `Frame` does this by default, so we don't need to do anything here
-            {}
-
-        InputMode::Editing => {
-            // Make the cursor visible and ask tui-rs to put it at the specified coordinates after rendering
-            f.set_cursor(
-                // Put cursor past the end of the input text
-                chunks[1].x + app.input.width() as u16 + 1,
-                // Move one line down, from the border to the input line
-                chunks[1].y + 1,
-            )
-        }
-    }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn take_flag_value_removes_the_flag_for_the_subcommand() {
+        let mut args : Vec<String> = ["todo_rust", "--data-dir", "~/todos", "add", "milk"].iter().map(|arg| arg.to_string()).collect();
 
-    let messages_to_display : Vec<TodoData> =app.get_messages();
-    let title = get_title(app);
-
-    let messages: Vec<ListItem> = messages_to_display
-        .iter()
-        .enumerate()
-        .map(|(_index, m)| {
-            let prefix = match m.status {
-                Status::Todo => '#',
-                Status::Done => '*'
-
-            };
-            let content = vec![Spans::from(Span::raw(format!("{} {}", prefix, m.message)))];
-            ListItem::new(content)
-        })
-        .collect();
-
-    let messages =
-        List::new(messages).block(
-            Block::default()
-            .borders(Borders::ALL)
-            .title(title)
-            .style(match app.input_mode {
-                InputMode::Normal => Style::default(),
-                InputMode::Editing => Style::default().fg(Color::Yellow),
-            }));
-    f.render_widget(messages, chunks[2]);
-
-    let x_offset = 3 + app.target_column as u16;
-    let y_offset = 1 + app.target_row as u16;
-
-    f.set_cursor(
-        chunks[2].x + x_offset,
-        chunks[2].y + y_offset 
-    )
-}
\ No newline at end of file
+        assert_eq!(take_flag_value(&mut args, "--data-dir"), Some("~/todos".to_string()));
+        assert_eq!(args, vec!["todo_rust", "add", "milk"]);
+        assert_eq!(take_flag_value(&mut args, "--data-dir"), None);
+    }
+}
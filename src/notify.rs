@@ -0,0 +1,42 @@
+use std::io;
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+
+/// Runs `program`, `None` when it isn't installed
+fn run(program : &str, args : &[&str]) -> Option<io::Result<()>> {
+    let status = match Command::new(program).args(args).stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::null()).status() {
+        Ok(status) => status,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return None,
+        Err(err) => return Some(Err(err))
+    };
+
+    if !status.success() {
+        return Some(Err(io::Error::other(format!("`{}` exited with {}", program, status))));
+    }
+    Some(Ok(()))
+}
+
+/// Shows a desktop notification through notify-send, or osascript on macOS
+pub fn send(summary : &str, body : &str) -> io::Result<()> {
+    if let Some(result) = run("notify-send", &["--app-name", "todo_rust", summary, body]) {
+        return result;
+    }
+
+    let quote = |text : &str| format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""));
+    let script = format!("display notification {} with title {}", quote(body), quote(summary));
+    run("osascript", &["-e", &script])
+        .unwrap_or_else(|| Err(io::Error::new(io::ErrorKind::NotFound, "no notification tool found (notify-send or osascript)")))
+}
+
+/// Starts a thread showing every message sent to it as a notification, so a slow
+/// notification daemon never holds up the interface. Failures are dropped.
+pub fn spawn() -> Sender<String> {
+    let (sender, receiver) = mpsc::channel::<String>();
+    thread::spawn(move || {
+        for message in receiver {
+            let _ = send("Todo due", &message);
+        }
+    });
+    sender
+}
@@ -0,0 +1,145 @@
+use std::cmp::Ordering;
+
+use crate::{child_count, Status, TodoData};
+
+/// Something a list can be ordered by, several of them break ties left to right
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum SortKey {
+    /// In progress, then todo, then done
+    Status,
+    /// Highest priority first
+    Priority,
+    /// A-Z, case-insensitive
+    Alphabetical,
+    /// Oldest first, todo's without a creation time keep their place in front
+    Created,
+}
+
+impl SortKey {
+    pub const ALL : [SortKey; 4] = [SortKey::Status, SortKey::Priority, SortKey::Alphabetical, SortKey::Created];
+
+    /// The name used in `:sort` and the sort order file
+    pub fn name(self) -> &'static str {
+        match self {
+            SortKey::Status => "status",
+            SortKey::Priority => "priority",
+            SortKey::Alphabetical => "alphabetical",
+            SortKey::Created => "created",
+        }
+    }
+
+    /// Parses a name like `priority`, `alpha` and `az` are accepted for alphabetical
+    pub fn parse(name : &str) -> Option<SortKey> {
+        match name.trim().to_lowercase().as_str() {
+            "alpha" | "az" => Some(SortKey::Alphabetical),
+            name => SortKey::ALL.into_iter().find(|key| key.name() == name)
+        }
+    }
+
+    fn compare(self, a : &TodoData, b : &TodoData) -> Ordering {
+        let status_rank = |status : Status| match status {
+            Status::InProgress => 0,
+            Status::Todo => 1,
+            Status::Done => 2
+        };
+        match self {
+            SortKey::Status => status_rank(a.status).cmp(&status_rank(b.status)),
+            SortKey::Priority => b.priority.cmp(&a.priority),
+            SortKey::Alphabetical => a.message.to_lowercase().cmp(&b.message.to_lowercase()),
+            SortKey::Created => a.created_at.cmp(&b.created_at),
+        }
+    }
+}
+
+/// Parses keys separated by commas or spaces, like `status,priority`. `None` when one of
+/// them isn't a known key or there are none.
+pub fn parse_keys(text : &str) -> Option<Vec<SortKey>> {
+    let keys : Option<Vec<SortKey>> = text
+        .split(|c : char| c == ',' || c.is_whitespace())
+        .filter(|name| !name.is_empty())
+        .map(SortKey::parse)
+        .collect();
+    keys.filter(|keys| !keys.is_empty())
+}
+
+/// `keys` the way `parse_keys` reads them
+pub fn format_keys(keys : &[SortKey]) -> String {
+    keys.iter().map(|key| key.name()).collect::<Vec<_>>().join(",")
+}
+
+/// Sorts `entries` by `keys`. The sort is stable, so entries that compare equal keep their
+/// order. Nested entries move along with the entry above them and are sorted among
+/// themselves, a trailing blank entry stays last. Returns the index every entry had before,
+/// in the new order.
+pub fn sort(entries : &mut Vec<TodoData>, keys : &[SortKey]) -> Vec<usize> {
+    let has_trailing_blank = entries.last().is_some_and(|m| m.message.is_empty());
+    let sort_len = if has_trailing_blank { entries.len() - 1 } else { entries.len() };
+
+    let mut order = Vec::with_capacity(entries.len());
+    sort_siblings(entries, 0, sort_len, keys, &mut order);
+    order.extend(sort_len..entries.len());
+
+    let mut sorted : Vec<TodoData> = order.iter().map(|&index| entries[index].clone()).collect();
+    std::mem::swap(entries, &mut sorted);
+    order
+}
+
+/// Pushes the indices of `entries[start..end]` onto `order`, every entry followed by its
+/// nested entries
+fn sort_siblings(entries : &[TodoData], start : usize, end : usize, keys : &[SortKey], order : &mut Vec<usize>) {
+    let mut groups = vec![];
+    let mut index = start;
+    while index < end {
+        let group_end = (index + 1 + child_count(entries, index)).min(end);
+        groups.push((index, group_end));
+        index = group_end;
+    }
+
+    groups.sort_by(|&(a, _), &(b, _)| {
+        keys.iter()
+            .map(|key| key.compare(&entries[a], &entries[b]))
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(Ordering::Equal)
+    });
+
+    for (head, group_end) in groups {
+        order.push(head);
+        sort_siblings(entries, head + 1, group_end, keys, order);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Priority;
+
+    fn todo(message : &str, status : Status, priority : Priority) -> TodoData {
+        TodoData { message : message.to_string(), status, priority, ..TodoData::default() }
+    }
+
+    #[test]
+    fn sorts_by_several_keys_and_keeps_nested_entries_with_their_parent() {
+        let mut entries = vec![
+            todo("write report", Status::Done, Priority::High),
+            todo("call bank", Status::Todo, Priority::Medium),
+            TodoData { indent : 1, ..todo("find number", Status::Todo, Priority::Medium) },
+            TodoData { indent : 1, ..todo("ask about fees", Status::Todo, Priority::Urgent) },
+            todo("Buy milk", Status::Todo, Priority::Medium),
+            todo("fix bike", Status::InProgress, Priority::Low),
+            todo("", Status::Todo, Priority::Medium),
+        ];
+
+        let keys = parse_keys("status, priority").unwrap();
+        assert_eq!(format_keys(&keys), "status,priority");
+        let order = sort(&mut entries, &keys);
+
+        let messages : Vec<&str> = entries.iter().map(|m| m.message.as_str()).collect();
+        // "call bank" and "Buy milk" tie on both keys and keep their order
+        assert_eq!(messages, vec!["fix bike", "call bank", "ask about fees", "find number", "Buy milk", "write report", ""]);
+        assert_eq!(order, vec![5, 1, 3, 2, 4, 0, 6]);
+
+        sort(&mut entries, &[SortKey::Alphabetical]);
+        assert_eq!(entries[0].message, "Buy milk");
+        assert_eq!(parse_keys("status,size"), None);
+    }
+}
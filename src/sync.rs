@@ -0,0 +1,246 @@
+//! Syncing the lists with the services set up in the config: the daily, long term and named
+//! lists with a CalDAV server, the long term list with a Todoist project
+
+use std::io;
+
+use crate::lists::{self, ListRole, TodoList};
+use crate::{caldav, date, todoist, Status, TodoData};
+
+/// Start of the UIDs given to todo's on their first sync, tells them apart from the tasks
+/// created on the server
+pub const SYNC_UID_PREFIX : &str = "todo_rust-";
+
+/// What the services set up in the config answered, `None` for the ones that aren't
+pub struct Synced {
+    /// Tasks on the CalDAV server as they were before the sync pushed to it
+    pub caldav : Option<Vec<caldav::RemoteTodo>>,
+    /// UIDs of the todo's pushed to the CalDAV server
+    pub caldav_pushed : Vec<String>,
+    pub todoist : Option<todoist::Outcome>,
+    /// Ids of the Todoist tasks of the todo's pushed, the ones created by the sync included
+    pub todoist_pushed : Vec<String>,
+    /// Error the Todoist sync stopped at, `todoist` has the tasks it created before
+    pub failed : Option<io::Error>
+}
+
+impl Synced {
+    /// Syncs each service that's given with its local todo's and the ids removed from the
+    /// lists, stopping at the first that fails. Blocks on the network, frontends run it on a
+    /// thread of its own.
+    pub fn run(
+        caldav : Option<(caldav::Account, Vec<caldav::LocalTodo>, Vec<String>)>,
+        todoist : Option<(todoist::Account, Vec<todoist::LocalTodo>, Vec<String>)>
+    ) -> io::Result<Synced> {
+        let caldav_pushed : Vec<String> = caldav.iter().flat_map(|(_, local, _)| local.iter().map(|(uid, _)| uid.clone())).collect();
+        let caldav = caldav.map(|(account, local, removed)| caldav::sync(&account, &local, &removed)).transpose()?;
+        let mut todoist_pushed : Vec<String> = todoist.iter().flat_map(|(_, local, _)| local.iter().filter_map(|(id, _)| id.clone())).collect();
+        let (todoist, failed) = match todoist {
+            Some((account, local, removed)) => {
+                let (outcome, result) = todoist::sync(&account, &local, &removed);
+                todoist_pushed.extend(outcome.created.iter().map(|(_, id)| id.clone()));
+                (Some(outcome), result.err())
+            }
+            None => (None, None)
+        };
+        Ok(Synced { caldav, caldav_pushed, todoist, todoist_pushed, failed })
+    }
+}
+
+/// `(uid, entry)` of every todo in the lists synced with CalDAV, giving the ones that
+/// weren't synced before a UID. The number of UIDs given out comes along, the lists need
+/// saving when it isn't 0.
+pub fn caldav_entries(lists : &mut [TodoList]) -> (Vec<caldav::LocalTodo>, usize) {
+    let stamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_nanos());
+    let mut assigned = 0;
+    let mut synced = vec![];
+    for list in lists.iter_mut().filter(|list| list.role.is_synced()) {
+        for entry in list.entries.iter_mut().filter(|entry| !entry.message.is_empty()) {
+            let uid = entry.sync_uid.get_or_insert_with(|| {
+                assigned += 1;
+                format!("{}{}-{}", SYNC_UID_PREFIX, stamp, assigned)
+            });
+            synced.push((uid.clone(), entry.clone()));
+        }
+    }
+    (synced, assigned)
+}
+
+/// UIDs of the todo's in the lists synced with CalDAV
+fn caldav_uids(lists : &[TodoList]) -> impl Iterator<Item = &String> {
+    lists
+        .iter()
+        .filter(|list| list.role.is_synced())
+        .flat_map(|list| &list.entries)
+        .filter_map(|entry| entry.sync_uid.as_ref())
+}
+
+/// UIDs of `known`, the ones synced before, that no synced list holds anymore. Their open
+/// tasks are deleted from the server, a todo moved back to a synced list is pushed again.
+pub fn caldav_removed(lists : &[TodoList], known : &[String]) -> Vec<String> {
+    let held : Vec<&String> = caldav_uids(lists).collect();
+    known.iter().filter(|uid| !held.contains(uid)).cloned().collect()
+}
+
+/// UIDs to keep as synced after a sync: the ones `pushed` and the ones the synced lists
+/// hold now, tasks picked up from the server included
+pub fn caldav_known(lists : &[TodoList], pushed : Vec<String>) -> Vec<String> {
+    let mut known = pushed;
+    known.extend(caldav_uids(lists).cloned());
+    known.sort();
+    known.dedup();
+    known
+}
+
+/// The long term todo's with the id of their Todoist task
+pub fn todoist_entries(lists : &[TodoList]) -> Vec<todoist::LocalTodo> {
+    lists[lists::role_index(lists, ListRole::LongTerm)].entries
+        .iter()
+        .filter(|entry| !entry.message.is_empty())
+        .map(|entry| (entry.todoist_id.clone(), entry.clone()))
+        .collect()
+}
+
+/// Finishes the todo's that were finished on the CalDAV server and adds the tasks
+/// created there to the daily list. Tasks removed here or moved to a list that isn't synced
+/// aren't added again. Returns how many were added and finished.
+pub fn apply_caldav(lists : &mut [TodoList], remote : Vec<caldav::RemoteTodo>, today : date::Date) -> (usize, usize) {
+    let daily = lists::role_index(lists, ListRole::Daily(lists::daily_date(lists)));
+    let (mut added, mut finished) = (0, 0);
+    for todo in remote {
+        let moved = lists
+            .iter()
+            .filter(|list| !list.role.is_synced())
+            .flat_map(|list| &list.entries)
+            .any(|entry| entry.sync_uid.as_deref() == Some(todo.uid.as_str()));
+        let local = lists
+            .iter_mut()
+            .filter(|list| list.role.is_synced())
+            .flat_map(|list| list.entries.iter_mut())
+            .find(|entry| entry.sync_uid.as_deref() == Some(todo.uid.as_str()));
+        match local {
+            Some(entry) if todo.status == Status::Done && entry.status != Status::Done => {
+                entry.set_status(Status::Done, today);
+                finished += 1;
+            }
+            Some(_) => {}
+            // A UID of ours that isn't in the lists anymore was removed here. UIDs with
+            // spaces can't be written to the day files.
+            None if todo.status != Status::Done && !moved && !todo.uid.starts_with(SYNC_UID_PREFIX) && !todo.uid.contains(char::is_whitespace) => {
+                let entry = TodoData {
+                    message : todo.summary,
+                    status : todo.status,
+                    due : todo.due,
+                    sync_uid : Some(todo.uid),
+                    created_at : Some(date::now()),
+                    ..TodoData::default()
+                };
+                lists::insert_before_blank(&mut lists[daily].entries, entry);
+                added += 1;
+            }
+            None => {}
+        }
+    }
+    (added, finished)
+}
+
+/// Ids of the Todoist tasks of the todo's in any list, a long term todo moved to another
+/// list keeps its id
+fn todoist_ids(lists : &[TodoList]) -> impl Iterator<Item = &String> {
+    lists.iter().flat_map(|list| &list.entries).filter_map(|entry| entry.todoist_id.as_ref())
+}
+
+/// Ids of `known`, the tasks synced before, that no list holds anymore. Their tasks are
+/// closed in Todoist.
+pub fn todoist_removed(lists : &[TodoList], known : &[String]) -> Vec<String> {
+    let held : Vec<&String> = todoist_ids(lists).collect();
+    known.iter().filter(|id| !held.contains(id)).cloned().collect()
+}
+
+/// Ids to keep as synced after a sync: the ones `pushed` and the ones the lists hold now
+pub fn todoist_known(lists : &[TodoList], pushed : Vec<String>) -> Vec<String> {
+    let mut known = pushed;
+    known.extend(todoist_ids(lists).cloned());
+    known.sort();
+    known.dedup();
+    known
+}
+
+/// Gives the todo's added to the Todoist project their task id, finishes the ones
+/// completed there and adds the tasks created there to the long term list. Tasks of todo's
+/// moved to another list aren't added again. Returns how many were added and finished.
+pub fn apply_todoist(lists : &mut [TodoList], outcome : todoist::Outcome, today : date::Date) -> (usize, usize) {
+    let held : Vec<String> = todoist_ids(lists).cloned().collect();
+    let new_tasks : Vec<todoist::Task> = outcome.added.into_iter().filter(|task| !held.contains(&task.id)).collect();
+    let long_term = lists::role_index(lists, ListRole::LongTerm);
+    let entries = &mut lists[long_term].entries;
+    // The list may have changed while syncing, the created todo's are found again by their text
+    for (message, id) in outcome.created {
+        if let Some(entry) = entries.iter_mut().find(|entry| entry.todoist_id.is_none() && entry.message == message) {
+            entry.todoist_id = Some(id);
+        }
+    }
+
+    let mut finished = 0;
+    for entry in entries.iter_mut().filter(|entry| entry.status != Status::Done) {
+        if entry.todoist_id.as_ref().is_some_and(|id| outcome.finished.contains(id)) {
+            entry.set_status(Status::Done, today);
+            finished += 1;
+        }
+    }
+
+    let added = new_tasks.len();
+    for task in new_tasks {
+        let entry = TodoData {
+            message : task.content,
+            due : task.due,
+            todoist_id : Some(task.id),
+            created_at : Some(date::now()),
+            ..TodoData::default()
+        };
+        lists::insert_before_blank(entries, entry);
+    }
+    (added, finished)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lists::ListView;
+
+    fn list(id : usize, role : ListRole, messages : &[&str]) -> TodoList {
+        let entries = messages.iter().map(|message| TodoData { message : message.to_string(), ..TodoData::default() }).collect();
+        TodoList { id, name : String::new(), role, entries, view : ListView::default() }
+    }
+
+    #[test]
+    fn caldav_tasks_finish_and_join_the_lists_they_belong_to() {
+        let today = date::Date::parse("2022-09-28").unwrap();
+        let mut lists = vec![
+            list(0, ListRole::Daily(today), &["pay rent", ""]),
+            list(1, ListRole::LongTerm, &["learn rust", ""]),
+            list(2, ListRole::Journal, &["not synced"]),
+        ];
+        let (local, assigned) = caldav_entries(&mut lists);
+        assert_eq!(assigned, 2);
+        assert_eq!(local.iter().map(|(_, entry)| entry.message.as_str()).collect::<Vec<_>>(), vec!["pay rent", "learn rust"]);
+        assert_eq!(caldav_entries(&mut lists).1, 0);
+
+        let remote = |uid : &str, summary : &str, status| caldav::RemoteTodo {
+            uid : uid.to_string(),
+            href : format!("/tasks/{}.ics", uid),
+            summary : summary.to_string(),
+            status,
+            due : None
+        };
+        let (added, finished) = apply_caldav(&mut lists, vec![
+            remote(&local[1].0, "learn rust", Status::Done),
+            remote("phone-1", "call mum", Status::Todo),
+        ], today);
+
+        assert_eq!((added, finished), (1, 1));
+        assert_eq!((lists[1].entries[0].status, lists[1].entries[0].completed_on), (Status::Done, Some(today)));
+        assert_eq!(lists[0].entries.iter().map(|m| m.message.as_str()).collect::<Vec<_>>(), vec!["pay rent", "call mum", ""]);
+    }
+}
@@ -0,0 +1,46 @@
+/// Byte ranges of the inline `#tag`s in `message`.
+///
+/// A tag starts with `#` at the beginning of the message or after whitespace and runs
+/// over alphanumerics, `_` and `-`. A lone `#` or something like `C#` is not a tag.
+pub fn find_tags(message : &str) -> Vec<(usize, usize)> {
+    let mut ranges = vec![];
+    let mut previous : Option<char> = None;
+    let mut chars = message.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        let at_word_start = previous.is_none_or(char::is_whitespace);
+        previous = Some(c);
+
+        if c != '#' || !at_word_start {
+            continue;
+        }
+
+        let mut end = start + c.len_utf8();
+        while let Some(&(index, next)) = chars.peek() {
+            if !is_tag_char(next) {
+                break;
+            }
+            end = index + next.len_utf8();
+            previous = Some(next);
+            chars.next();
+        }
+
+        if end > start + 1 {
+            ranges.push((start, end));
+        }
+    }
+
+    ranges
+}
+
+/// Lowercased names (without `#`) of the tags in `message`
+pub fn parse_tags(message : &str) -> Vec<String> {
+    find_tags(message)
+        .into_iter()
+        .map(|(start, end)| message[start + 1..end].to_lowercase())
+        .collect()
+}
+
+fn is_tag_char(c : char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-'
+}
@@ -0,0 +1,33 @@
+//! Scratch data directories for the tests
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// An empty directory in the system's temp directory, deleted again when it's dropped, so
+/// a failing assert doesn't leave it behind. Every one gets its own name, tests running in
+/// parallel never share one.
+pub struct TestDir {
+    path : PathBuf
+}
+
+impl TestDir {
+    pub fn new(name : &str) -> Self {
+        static CREATED : AtomicUsize = AtomicUsize::new(0);
+        let number = CREATED.fetch_add(1, Ordering::Relaxed);
+        let path = std::env::temp_dir().join(format!("todo_rust_{}_{}_{}", name, std::process::id(), number));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        TestDir { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
@@ -0,0 +1,92 @@
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// Number of user-perceived characters (grapheme clusters) in `text`
+pub fn grapheme_count(text : &str) -> usize {
+    text.graphemes(true).count()
+}
+
+/// Byte offset at which the grapheme cluster with index `column` starts,
+/// or the length of `text` when `column` is past the end
+pub fn byte_offset(text : &str, column : usize) -> usize {
+    text.grapheme_indices(true)
+        .nth(column)
+        .map_or(text.len(), |(offset, _)| offset)
+}
+
+/// Terminal cells taken up by the grapheme clusters in front of `column`, where the
+/// cursor goes. Wide characters like `日` take two cells, combining marks none.
+pub fn display_width(text : &str, column : usize) -> usize {
+    text[..byte_offset(text, column)].width()
+}
+
+/// Grapheme indices at which the lines of `text` start when it's wrapped to `width`
+/// cells. Lines break after the last space that fits, or inside a word that is longer
+/// than a whole line. The first line always starts at 0.
+pub fn wrap_starts(text : &str, width : usize) -> Vec<usize> {
+    let graphemes : Vec<&str> = text.graphemes(true).collect();
+    let mut starts = vec![0];
+    let mut line_start = 0;
+    let mut line_width = 0;
+    let mut after_space = None;
+
+    let mut index = 0;
+    while index < graphemes.len() {
+        let grapheme_width = graphemes[index].width();
+        if line_width + grapheme_width > width && index > line_start {
+            let start = after_space.filter(|start| *start > line_start).unwrap_or(index);
+            starts.push(start);
+            line_start = start;
+            line_width = graphemes[start..index].iter().map(|grapheme| grapheme.width()).sum();
+            after_space = None;
+            // The grapheme may still not fit behind the words carried over
+            continue;
+        }
+
+        line_width += grapheme_width;
+        if graphemes[index] == " " {
+            after_space = Some(index + 1);
+        }
+        index += 1;
+    }
+
+    starts
+}
+
+/// Wrapped line and cell in it of the cursor in front of grapheme `column`, for `text`
+/// wrapped at `starts` as returned by `wrap_starts`
+pub fn wrapped_cursor(text : &str, starts : &[usize], column : usize) -> (usize, usize) {
+    let line = starts.iter().rposition(|start| *start <= column).unwrap_or(0);
+    (line, display_width(text, column) - display_width(text, starts[line]))
+}
+
+/// Byte range of the grapheme cluster with index `column`
+pub fn grapheme_range(text : &str, column : usize) -> Option<(usize, usize)> {
+    text.grapheme_indices(true)
+        .nth(column)
+        .map(|(offset, grapheme)| (offset, offset + grapheme.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cursor_is_placed_by_display_width() {
+        assert_eq!(display_width("日本語", 2), 4);
+        assert_eq!(display_width("cafe\u{301}!", 4), 4);
+        assert_eq!(display_width("ab", 5), 2);
+    }
+
+    #[test]
+    fn wrap_starts_breaks_after_spaces_and_inside_long_words() {
+        assert_eq!(wrap_starts("buy milk and eggs", 10), vec![0, 9]);
+        assert_eq!(wrap_starts("abcdefghij", 4), vec![0, 4, 8]);
+        assert_eq!(wrap_starts("short", 10), vec![0]);
+        // Wide characters take two cells
+        assert_eq!(wrap_starts("日本語です", 4), vec![0, 2, 4]);
+
+        let starts = wrap_starts("buy milk and eggs", 10);
+        assert_eq!(wrapped_cursor("buy milk and eggs", &starts, 11), (1, 2));
+    }
+}
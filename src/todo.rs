@@ -0,0 +1,329 @@
+//! The entries of a list and the operations frontends run on them
+
+use std::cmp;
+
+use crate::date;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Status {
+    Todo,
+    /// Started but not finished yet
+    InProgress,
+    Done
+}
+
+impl Status {
+    /// Todo and in progress become Done, Done goes back to Todo
+    pub fn toggled(self) -> Status {
+        match self {
+            Status::Todo | Status::InProgress => Status::Done,
+            Status::Done => Status::Todo
+        }
+    }
+}
+
+/// How urgent a todo is, higher priorities are listed first
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Default)]
+pub enum Priority {
+    Low,
+    #[default]
+    Medium,
+    High,
+    Urgent
+}
+
+impl Priority {
+    pub fn raised(self) -> Priority {
+        match self {
+            Priority::Low => Priority::Medium,
+            Priority::Medium => Priority::High,
+            Priority::High | Priority::Urgent => Priority::Urgent
+        }
+    }
+
+    pub fn lowered(self) -> Priority {
+        match self {
+            Priority::Urgent => Priority::High,
+            Priority::High => Priority::Medium,
+            Priority::Medium | Priority::Low => Priority::Low
+        }
+    }
+}
+
+/// How often a todo comes back after it's done
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Recurrence {
+    Daily,
+    Weekly,
+    /// Every given number of days
+    Days(u32)
+}
+
+impl Recurrence {
+    /// Reads `daily`, `weekly` or a number of days like `3d`, the format written by `format`
+    pub fn parse(text : &str) -> Option<Recurrence> {
+        match text {
+            "daily" => Some(Recurrence::Daily),
+            "weekly" => Some(Recurrence::Weekly),
+            _ => {
+                let days = text.strip_suffix('d')?.parse::<u32>().ok().filter(|days| *days > 0)?;
+                Some(Recurrence::Days(days))
+            }
+        }
+    }
+
+    pub fn format(self) -> String {
+        match self {
+            Recurrence::Daily => "daily".to_string(),
+            Recurrence::Weekly => "weekly".to_string(),
+            Recurrence::Days(days) => format!("{}d", days)
+        }
+    }
+
+    pub fn interval_days(self) -> i64 {
+        match self {
+            Recurrence::Daily => 1,
+            Recurrence::Weekly => 7,
+            Recurrence::Days(days) => days as i64
+        }
+    }
+}
+
+/// One entry of a list
+#[derive(Clone)]
+pub struct TodoData {
+    pub message: String,
+    /// Longer description shown in the detail pane, may span several lines
+    pub notes: String,
+    pub status: Status,
+    /// Unix timestamp of when the todo was first written, or a journal entry was committed
+    pub created_at: Option<u64>,
+    /// Marked with the space bar for a batch operation, never saved
+    pub marked: bool,
+    /// Nesting level below the entry above it
+    pub indent: usize,
+    /// Seconds the todo is expected to take, 0 when it wasn't estimated
+    pub estimate_secs: u32,
+    /// Seconds spent on this todo in focus timer sessions
+    pub actual_secs: u32,
+    /// Focus timer sessions that ran their full length on this todo
+    pub pomodoros: u32,
+    /// Days the todo was carried over to a later daily list without getting done
+    pub carried_days: u32,
+    pub priority: Priority,
+    pub due: Option<date::Date>,
+    /// The entries nested below this one are hidden
+    pub folded: bool,
+    pub recurrence: Option<Recurrence>,
+    /// UID of the task on the CalDAV server, set by the first sync
+    pub sync_uid: Option<String>,
+    /// Id of the task in the Todoist project, for long term todo's
+    pub todoist_id: Option<String>,
+    /// Day the todo was finished, while it's done
+    pub completed_on: Option<date::Date>,
+    /// Unix timestamp of when the todo was finished, while it's done
+    pub completed_at: Option<u64>,
+    /// Number other todo's refer to this one by, given out when one is first blocked by it
+    pub id: Option<u64>,
+    /// `id` of the todo this one waits for, it's blocked until that one is done
+    pub blocked_by: Option<u64>
+}
+
+impl Default for TodoData {
+    fn default() -> TodoData {
+        TodoData {
+            message: String::new(),
+            notes: String::new(),
+            status: Status::Todo,
+            created_at: None,
+            marked: false,
+            indent: 0,
+            estimate_secs: 0,
+            actual_secs: 0,
+            pomodoros: 0,
+            carried_days: 0,
+            priority: Priority::default(),
+            due: None,
+            folded: false,
+            recurrence: None,
+            sync_uid: None,
+            todoist_id: None,
+            completed_on: None,
+            completed_at: None,
+            id: None,
+            blocked_by: None
+        }
+    }
+}
+
+impl TodoData {
+    /// Changes the status, a todo that becomes done remembers `today` as its completion day
+    pub fn set_status(&mut self, status : Status, today : date::Date) {
+        self.completed_on = if status == Status::Done { self.completed_on.or(Some(today)) } else { None };
+        self.completed_at = if status == Status::Done { self.completed_at.or(Some(date::now())) } else { None };
+        self.status = status;
+    }
+
+    /// A fresh copy of a recurring todo, due on the first date of its schedule after
+    /// `today`. The schedule starts from the due date, or from `today` without one.
+    pub fn next_occurrence(&self, today : date::Date) -> Option<TodoData> {
+        let recurrence = self.recurrence?;
+        let interval = recurrence.interval_days();
+        let start = self.due.unwrap_or(today).to_days();
+        let missed = cmp::max(today.to_days() - start, 0) / interval;
+
+        Some(TodoData {
+            message : self.message.clone(),
+            indent : self.indent,
+            priority : self.priority,
+            due : Some(date::Date::from_days(start + (missed + 1) * interval)),
+            recurrence : Some(recurrence),
+            created_at : Some(date::now()),
+            ..TodoData::default()
+        })
+    }
+}
+
+/// Puts the next occurrence of the recurring todo at `index` behind it once it's done. The
+/// rule moves over to the new entry, so finishing it again later doesn't add a second one.
+pub fn schedule_next_occurrence(entries : &mut Vec<TodoData>, index : usize, today : date::Date) {
+    if entries[index].status != Status::Done {
+        return;
+    }
+    if let Some(next) = entries[index].next_occurrence(today) {
+        entries[index].recurrence = None;
+        let position = index + 1 + child_count(entries, index);
+        entries.insert(position, next);
+    }
+}
+
+/// (done, total) over `entries`, ignoring blank entries
+pub fn progress(entries : &[TodoData]) -> (usize, usize) {
+    let entries = entries.iter().filter(|m| !m.message.is_empty());
+    let total = entries.clone().count();
+    let done = entries.filter(|m| matches!(m.status, Status::Done)).count();
+    (done, total)
+}
+
+/// Number of entries nested below the one at `index`
+pub fn child_count(entries : &[TodoData], index : usize) -> usize {
+    let indent = entries[index].indent;
+    entries[index + 1..].iter().take_while(|entry| entry.indent > indent).count()
+}
+
+/// Appends a top level todo reading `message`, returns its index
+pub fn add(entries : &mut Vec<TodoData>, message : &str) -> usize {
+    entries.push(TodoData { message : message.trim().to_string(), created_at : Some(date::now()), ..TodoData::default() });
+    entries.len() - 1
+}
+
+/// Takes the entry at `index` out of the list. The entries nested below it stay, under
+/// the entry above.
+pub fn remove(entries : &mut Vec<TodoData>, index : usize) -> TodoData {
+    entries.remove(index)
+}
+
+/// Changes the status of the entry at `index`, a recurring todo that gets done brings
+/// its next occurrence along
+pub fn set_status(entries : &mut Vec<TodoData>, index : usize, status : Status, today : date::Date) {
+    entries[index].set_status(status, today);
+    schedule_next_occurrence(entries, index, today);
+}
+
+/// Flips the entry at `index` between done and not done, see `Status::toggled`
+pub fn toggle(entries : &mut Vec<TodoData>, index : usize, today : date::Date) {
+    let status = entries[index].status.toggled();
+    set_status(entries, index, status, today);
+}
+
+/// Orders `indices` into `entries` highest priority first, otherwise in list order. Indented
+/// entries move along with the top level entry above them and the trailing blank entry stays last.
+pub fn sort_by_priority(entries : &[TodoData], indices : &mut [usize]) {
+    let mut group_priority = Priority::default();
+    let sort_keys : Vec<(bool, cmp::Reverse<Priority>)> = entries
+        .iter()
+        .enumerate()
+        .map(|(index, m)| {
+            if m.indent == 0 {
+                group_priority = m.priority;
+            }
+            let trailing_blank = index == entries.len() - 1 && m.message.is_empty();
+            (trailing_blank, cmp::Reverse(group_priority))
+        })
+        .collect();
+    indices.sort_by_key(|index| sort_keys[*index]);
+}
+
+/// Whether one of the entries the one at `index` is nested below is folded
+pub fn is_folded_away(entries : &[TodoData], index : usize) -> bool {
+    let mut indent = entries[index].indent;
+    for entry in entries[..index].iter().rev() {
+        if indent == 0 {
+            break;
+        }
+        if entry.indent < indent {
+            if entry.folded {
+                return true;
+            }
+            indent = entry.indent;
+        }
+    }
+    false
+}
+
+/// Swaps the entry at `from` with the one at `to`, usually its neighbour
+pub fn move_entry(entries : &mut [TodoData], from : usize, to : usize) {
+    entries.swap(from, to);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn todo(message : &str) -> TodoData {
+        TodoData { message : message.to_string(), ..TodoData::default() }
+    }
+
+    #[test]
+    fn finishing_a_recurring_todo_schedules_the_next_one() {
+        let today = date::Date::parse("2022-09-28").unwrap();
+        let mut entries = vec![
+            TodoData {
+                status : Status::Done,
+                due : date::Date::parse("2022-09-19"),
+                recurrence : Some(Recurrence::Weekly),
+                ..todo("Water the plants")
+            },
+            todo(""),
+        ];
+
+        schedule_next_occurrence(&mut entries, 0, today);
+
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].recurrence, None);
+        assert_eq!(entries[1].message, "Water the plants");
+        assert_eq!(entries[1].status, Status::Todo);
+        // The 26th was missed, the schedule stays on Mondays
+        assert_eq!(entries[1].due, date::Date::parse("2022-10-03"));
+        assert_eq!(entries[1].recurrence, Some(Recurrence::Weekly));
+    }
+
+    #[test]
+    fn list_operations_keep_the_entries_in_order() {
+        let today = date::Date::parse("2022-09-28").unwrap();
+        let mut entries = vec![];
+        assert_eq!(add(&mut entries, " Buy milk "), 0);
+        add(&mut entries, "Call mum");
+
+        toggle(&mut entries, 1, today);
+        assert_eq!((entries[1].status, entries[1].completed_on), (Status::Done, Some(today)));
+        move_entry(&mut entries, 1, 0);
+        assert_eq!(entries[0].message, "Call mum");
+        toggle(&mut entries, 0, today);
+        assert_eq!((entries[0].status, entries[0].completed_on), (Status::Todo, None));
+
+        assert_eq!(remove(&mut entries, 0).message, "Call mum");
+        assert_eq!(entries.iter().map(|entry| entry.message.as_str()).collect::<Vec<_>>(), vec!["Buy milk"]);
+        assert_eq!(progress(&entries), (0, 1));
+    }
+}
@@ -0,0 +1,186 @@
+use std::io;
+
+use crate::date;
+use crate::http;
+use crate::json;
+use crate::{Status, TodoData};
+
+const API : &str = "https://api.todoist.com/api/v1";
+
+/// Todoist project the long term list is synced with, from the `[todoist]` section of the config
+#[derive(Clone)]
+pub struct Account {
+    /// API token from Settings > Integrations > Developer
+    pub token : String,
+    /// The number at the end of the project's URL
+    pub project_id : String,
+}
+
+/// A todo of the long term list with the id of its task, `None` before it was synced
+pub type LocalTodo = (Option<String>, TodoData);
+
+/// An open task of the project
+#[derive(Clone, PartialEq, Debug)]
+pub struct Task {
+    pub id : String,
+    pub content : String,
+    pub due : Option<date::Date>,
+}
+
+/// What the long term list has to change to match the project after a sync
+#[derive(Default, PartialEq, Debug)]
+pub struct Outcome {
+    /// `(message, id)` of the todo's that were added to the project
+    pub created : Vec<(String, String)>,
+    /// Ids of the tasks that were completed or deleted in Todoist
+    pub finished : Vec<String>,
+    /// Tasks added in Todoist that aren't in the list yet
+    pub added : Vec<Task>,
+}
+
+/// Pulls the open tasks of the project, closes the ones whose id is in `removed`, then
+/// closes, updates or creates a task for every `(id, entry)` of `local`. A task that isn't
+/// open anymore was completed in Todoist and is left alone. A sync that fails partway still
+/// returns the tasks it created, so they aren't created again by the next one.
+pub fn sync(account : &Account, local : &[LocalTodo], removed : &[String]) -> (Outcome, io::Result<()>) {
+    let mut outcome = Outcome::default();
+    let result = sync_into(&mut outcome, account, local, removed);
+    (outcome, result)
+}
+
+fn sync_into(outcome : &mut Outcome, account : &Account, local : &[LocalTodo], removed : &[String]) -> io::Result<()> {
+    let mut remote = pull(account)?;
+    for task in remote.iter().filter(|task| removed.contains(&task.id)) {
+        request(account, "POST", &format!("{}/tasks/{}/close", API, task.id), "")?;
+    }
+    remote.retain(|task| !removed.contains(&task.id));
+
+    for (id, entry) in local {
+        let id = match id {
+            Some(id) => id,
+            None => {
+                if entry.status != Status::Done {
+                    let body = task_json(entry, Some(&account.project_id));
+                    let created = json::parse(&request(account, "POST", &format!("{}/tasks", API), &body)?)
+                        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+                    let id = created.get("id").and_then(id_text)
+                        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "created task has no id"))?;
+                    outcome.created.push((entry.message.clone(), id));
+                }
+                continue;
+            }
+        };
+
+        match remote.iter().find(|task| task.id == *id) {
+            None => outcome.finished.push(id.clone()),
+            Some(_) if entry.status == Status::Done => {
+                request(account, "POST", &format!("{}/tasks/{}/close", API, id), "")?;
+            }
+            Some(task) if task.content != entry.message || task.due != entry.due => {
+                request(account, "POST", &format!("{}/tasks/{}", API, id), &task_json(entry, None))?;
+            }
+            Some(_) => {}
+        }
+    }
+
+    let known : Vec<&String> = local.iter().filter_map(|(id, _)| id.as_ref()).collect();
+    outcome.added = remote.into_iter().filter(|task| !known.contains(&&task.id)).collect();
+    Ok(())
+}
+
+/// Every open task of the project, following the pages of the response
+pub fn pull(account : &Account) -> io::Result<Vec<Task>> {
+    let mut tasks = vec![];
+    let mut cursor : Option<String> = None;
+    loop {
+        let mut url = format!("{}/tasks?project_id={}", API, account.project_id);
+        if let Some(cursor) = &cursor {
+            url.push_str(&format!("&cursor={}", cursor));
+        }
+        let response = json::parse(&request(account, "GET", &url, "")?)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        let (page, next) = parse_page(&response);
+        tasks.extend(page);
+        match next {
+            Some(next) => cursor = Some(next),
+            None => return Ok(tasks)
+        }
+    }
+}
+
+/// Tasks of one response and the cursor of the next page. Older API versions answer
+/// with a bare array.
+fn parse_page(response : &json::Value) -> (Vec<Task>, Option<String>) {
+    let (values, next) = match response {
+        json::Value::Array(values) => (values.as_slice(), None),
+        _ => (
+            response.get("results").and_then(json::Value::as_array).unwrap_or(&[]),
+            response.get("next_cursor").and_then(json::Value::as_str).map(str::to_string)
+        )
+    };
+
+    let tasks = values.iter().filter_map(|value| {
+        Some(Task {
+            id : value.get("id").and_then(id_text)?,
+            content : value.get("content")?.as_str()?.to_string(),
+            due : value.get("due")
+                .and_then(|due| due.get("date"))
+                .and_then(json::Value::as_str)
+                .and_then(|due| date::Date::parse(due.get(..10)?)),
+        })
+    }).collect();
+    (tasks, next)
+}
+
+/// Ids are strings, very old projects still hand out numbers
+fn id_text(value : &json::Value) -> Option<String> {
+    match value {
+        json::Value::String(id) => Some(id.clone()),
+        json::Value::Number(id) => Some(format!("{}", id)),
+        _ => None
+    }
+}
+
+/// Body of a create or update request, a todo without a due date clears the task's
+fn task_json(entry : &TodoData, project_id : Option<&str>) -> String {
+    let mut fields = vec![("content".to_string(), json::Value::String(entry.message.clone()))];
+    if let Some(project_id) = project_id {
+        fields.push(("project_id".to_string(), json::Value::String(project_id.to_string())));
+    }
+    match entry.due {
+        Some(due) => fields.push(("due_date".to_string(), json::Value::String(due.format()))),
+        None => fields.push(("due_string".to_string(), json::Value::String("no date".to_string())))
+    }
+    json::Value::Object(fields).to_pretty_string()
+}
+
+fn request(account : &Account, method : &str, url : &str, body : &str) -> io::Result<String> {
+    let mut options = vec![("header", format!("Authorization: Bearer {}", account.token))];
+    if !body.is_empty() {
+        options.push(("header", "Content-Type: application/json".to_string()));
+    }
+    http::request(method, url, &options, body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pages_read_tasks_and_the_next_cursor() {
+        let response = json::parse(r#"{
+            "results": [
+                {"id": "6X7rM8997g3RQmvh", "content": "Renew passport", "due": {"date": "2022-09-30T17:00:00"}},
+                {"id": 2995104339, "content": "Read a book", "due": null},
+                {"content": "no id"}
+            ],
+            "next_cursor": "abc"
+        }"#).unwrap();
+
+        assert_eq!(parse_page(&response), (vec![
+            Task { id : "6X7rM8997g3RQmvh".to_string(), content : "Renew passport".to_string(), due : date::Date::parse("2022-09-30") },
+            Task { id : "2995104339".to_string(), content : "Read a book".to_string(), due : None },
+        ], Some("abc".to_string())));
+        assert_eq!(parse_page(&json::Value::Array(vec![])), (vec![], None));
+    }
+}
@@ -0,0 +1,1313 @@
+//! Drawing the app: the tab bar, the lists, the input box, the status bar and the popups
+
+use std::cmp;
+use tui::{
+    backend::Backend,
+    buffer::Buffer,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    symbols,
+    text::{Span, Spans, Text},
+    widgets::{BarChart, Block, Borders, Clear, List, ListItem, ListState, Paragraph, Tabs, Widget, Wrap},
+    Frame,
+};
+use unicode_width::UnicodeWidthStr;
+
+use todo_core::{child_count, date, is_folded_away, links, progress, sort_by_priority, tags, text, Priority, Status, TodoData};
+use todo_core::config::DEFAULT_PROFILE;
+use todo_core::keymap::{Action, KeyMap};
+use todo_core::lists::{ListRole, SaveStatus};
+
+use crate::app::{App, Calendar, DayDiff, History, InputMode, Popup, Profiles, PromptKind, QuickAdd, RenderedRow, SyncState};
+
+/// Keys of the modes that can't be configured, listed in the help overlay below the normal
+/// mode bindings
+const FIXED_KEYS : [(&str, &[(&str, &str)]); 6] = [
+    ("Editing", &[
+        ("enter", "record the message and start a new todo below"),
+        ("esc", "discard the changes"),
+        ("tab", "indent at the start of the line, otherwise insert spaces"),
+        ("shift+tab", "outdent"),
+        ("up down", "record the message and move to another todo"),
+        ("left right home end", "move the cursor"),
+    ]),
+    ("Notes", &[
+        ("esc", "save the notes"),
+        ("enter", "start a new line"),
+        ("left right", "move the cursor"),
+    ]),
+    ("Searching", &[
+        ("enter", "keep the search for the next and previous match"),
+        ("esc", "cancel the search"),
+    ]),
+    ("Prompts", &[
+        ("enter", "confirm"),
+        ("esc", "cancel"),
+    ]),
+    ("Jumping", &[
+        ("a letter", "jump to the next todo starting with it"),
+        ("esc enter", "stop jumping"),
+    ]),
+    ("Split view", &[
+        ("tab", "move to the other list instead of indenting"),
+    ]),
+];
+
+/// Drawn in front of the selected row, the other rows are indented by as much
+const SELECTED_SYMBOL : &str = "> ";
+
+/// Cells of the progress bar behind a long term todo with subtasks
+const SUBTASK_GAUGE_WIDTH : usize = 6;
+
+/// Days after which an open todo's age is highlighted
+const STALE_DAYS : u64 = 7;
+
+/// Days of being carried over after which the indicator of a daily todo turns yellow, then red
+const CARRIED_WARNING_DAYS : u32 = 3;
+
+const CARRIED_ALERT_DAYS : u32 = 7;
+
+pub fn render_passphrase<B: Backend>(f : &mut Frame<B>, title : &str, typed : usize, message : Option<&str>) {
+    let lines = vec![
+        Spans::from("*".repeat(typed)),
+        Spans::from(Span::styled(message.unwrap_or(""), Style::default().fg(Color::Red))),
+        Spans::from(Span::styled("Enter unlocks, Esc quits", Style::default().fg(Color::DarkGray))),
+    ];
+    let area = centered_rect(50, 20, f.size());
+    let paragraph = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title(title.to_string()))
+        .wrap(Wrap { trim: false });
+    f.render_widget(Clear, area);
+    f.render_widget(paragraph, area);
+}
+
+fn get_title(app : &App) -> String {
+    app.engine.active_list().name.clone()
+}
+
+/// Title of the input box, describing what typing into it will do
+fn input_title(app : &App) -> &'static str {
+    match app.input_mode() {
+        InputMode::Normal => "Input",
+        InputMode::Notes => "Editing the notes of",
+        InputMode::Jumping => "Jump to letter",
+        InputMode::Searching => "Search",
+        InputMode::Prompting => match app.prompt.as_ref().map(|prompt| &prompt.kind) {
+            Some(PromptKind::ExportPath) => "Write list to file (.md for a Markdown checklist)",
+            Some(PromptKind::ConfirmOverwrite(_)) => "File exists, overwrite? (y/n)",
+            Some(PromptKind::PromoteTarget) => "Promote to (d)aily or (l)ong term?",
+            Some(PromptKind::DueDate) => "Due date (YYYY-MM-DD, today, tomorrow or +days, empty clears)",
+            Some(PromptKind::Recurrence) => "Repeat (daily, weekly or every N days as Nd, empty stops)",
+            Some(PromptKind::Estimate) => "Estimate (minutes, 25m or 1h 30m, empty clears)",
+            Some(PromptKind::NewListName) => "Name of the new list",
+            Some(PromptKind::RenameList) => "Rename list to",
+            Some(PromptKind::ConfirmDeleteList) => "Delete this list? It can be reopened with U (y/n)",
+            Some(PromptKind::ConfirmPurgeClosed) => "Delete all closed lists for good? (y/n)",
+            Some(PromptKind::PlanDay) => "Open day (YYYY-MM-DD, tomorrow or +days)",
+            Some(PromptKind::Snooze) => "Snooze until (tomorrow, a weekday like monday, +days or YYYY-MM-DD)",
+            Some(PromptKind::BlockedBy) => "Blocked by (part of the other todo's message, empty clears)",
+            Some(PromptKind::Command) => "Command (sort[!] <keys>, archive, export <file or md>, open <day> or an action like stats)",
+            None => "Input"
+        },
+        InputMode::Editing => {
+            let is_last_row = app.engine.target_row as usize == app.engine.get_messages().len() - 1;
+            match (app.engine.is_journal(), is_last_row) {
+                (true, _) => "New journal entry",
+                (false, true) => "New todo",
+                (false, false) => "Editing"
+            }
+        }
+    }
+}
+
+/// Splits `text` so trailing whitespace gets a visible background while editing
+fn highlight_trailing_whitespace(text : &str) -> Spans<'_> {
+    let trimmed = text.trim_end();
+    if trimmed.len() == text.len() {
+        return Spans::from(text);
+    }
+
+    Spans::from(vec![
+        Span::raw(trimmed),
+        Span::styled(&text[trimmed.len()..], Style::default().bg(Color::Red)),
+    ])
+}
+
+/// Splits `message` into spans with the inline #tags colored and the links underlined
+fn message_spans(message : &str) -> Vec<Span<'static>> {
+    let mut spans = vec![];
+    let mut last_end = 0;
+
+    let tag_style = Style::default().fg(Color::Cyan);
+    let link_style = Style::default().fg(Color::Blue).add_modifier(Modifier::UNDERLINED);
+    let mut ranges : Vec<(usize, usize, Style)> = tags::find_tags(message)
+        .into_iter()
+        .map(|(start, end)| (start, end, tag_style))
+        .chain(links::find_urls(message).into_iter().map(|(start, end)| (start, end, link_style)))
+        .collect();
+    ranges.sort_by_key(|(start, _, _)| *start);
+
+    for (start, end, style) in ranges {
+        if start > last_end {
+            spans.push(Span::raw(message[last_end..start].to_string()));
+        }
+        spans.push(Span::styled(message[start..end].to_string(), style));
+        last_end = end;
+    }
+
+    if last_end < message.len() {
+        spans.push(Span::raw(message[last_end..].to_string()));
+    }
+
+    spans
+}
+
+/// The message of the entry at `index` with its tags highlighted, followed by the time
+/// spent, due date, repeat rule and folded entries. `tracking` is how long the stopwatch
+/// has been running on the entry.
+/// `subtask_gauge` is `Some(ascii)` on lists that show how many subtasks of an entry are done
+fn content_spans(entries : &[TodoData], index : usize, tracking : Option<u32>, subtask_gauge : Option<bool>) -> Vec<Span<'static>> {
+    let m = &entries[index];
+    let mut spans = message_spans(&m.message);
+    if let Some(tracking) = tracking {
+        spans.push(Span::styled(
+            format!(" ({}, tracking)", date::format_duration(m.actual_secs + tracking)),
+            Style::default().fg(Color::Green)));
+    } else if m.actual_secs > 0 {
+        spans.push(Span::styled(
+            format!(" ({})", date::format_duration(m.actual_secs)),
+            Style::default().fg(Color::DarkGray)));
+    }
+    if m.pomodoros > 0 {
+        let plural = if m.pomodoros == 1 { "" } else { "s" };
+        spans.push(Span::styled(format!(" ({} pomodoro{})", m.pomodoros, plural), Style::default().fg(Color::DarkGray)));
+    }
+    if let Some(due) = m.due {
+        spans.push(Span::raw(format!(" (due {})", due.format())));
+    }
+    if let Some(recurrence) = m.recurrence {
+        spans.push(Span::styled(format!(" (repeats {})", recurrence.format()), Style::default().fg(Color::DarkGray)));
+    }
+    if m.folded {
+        let hidden = child_count(entries, index);
+        spans.push(Span::styled(format!(" [+{}]", hidden), Style::default().fg(Color::DarkGray)));
+    }
+    if let Some(ascii) = subtask_gauge {
+        let (done, total) = progress(&entries[index + 1..index + 1 + child_count(entries, index)]);
+        if total > 0 {
+            let color = if done == total { Color::Green } else { Color::DarkGray };
+            spans.push(Span::styled(
+                format!(" {} {}/{}", progress_bar(done, total, SUBTASK_GAUGE_WIDTH, ascii), done, total),
+                Style::default().fg(color)));
+        }
+    }
+    spans
+}
+
+/// ` (3d old)` behind an open todo written at least a day before `now`, in yellow once
+/// it's been open for `STALE_DAYS`
+fn age_span(m : &TodoData, now : u64) -> Option<Span<'static>> {
+    let days = now.saturating_sub(m.created_at?) / 86_400;
+    if m.status == Status::Done || days == 0 {
+        return None;
+    }
+    let color = if days >= STALE_DAYS { Color::Yellow } else { Color::DarkGray };
+    Some(Span::styled(format!(" ({}d old)", days), Style::default().fg(color)))
+}
+
+/// ` (carried 4d)` behind an open todo that keeps being carried over, turning from white to
+/// yellow to red the longer it's put off
+fn carried_span(m : &TodoData) -> Option<Span<'static>> {
+    if m.status == Status::Done || m.carried_days == 0 {
+        return None;
+    }
+    let color = match m.carried_days {
+        days if days >= CARRIED_ALERT_DAYS => Color::Red,
+        days if days >= CARRIED_WARNING_DAYS => Color::Yellow,
+        _ => Color::White
+    };
+    Some(Span::styled(format!(" (carried {}d)", m.carried_days), Style::default().fg(color)))
+}
+
+/// Cuts `spans` into lines at the byte offsets `breaks` into their joined text, keeping
+/// the style of a span that is cut in two on both halves
+fn split_spans(spans : Vec<Span<'static>>, breaks : &[usize]) -> Vec<Vec<Span<'static>>> {
+    let mut lines = vec![vec![]];
+    let mut breaks = breaks.iter().peekable();
+    let mut offset = 0;
+
+    for span in spans {
+        let content = span.content.to_string();
+        let mut start = 0;
+        // A break right at the end of this span starts the next span on a new line
+        while let Some(split) = breaks.next_if(|at| **at < offset + content.len()).map(|at| at - offset) {
+            if split > start {
+                lines.last_mut().unwrap().push(Span::styled(content[start..split].to_string(), span.style));
+            }
+            lines.push(vec![]);
+            start = split;
+        }
+        if start < content.len() {
+            lines.last_mut().unwrap().push(Span::styled(content[start..].to_string(), span.style));
+        }
+        offset += content.len();
+    }
+
+    lines
+}
+
+/// Tags of the active list next to it, the highlighted tag is what `#` filters by
+fn render_tag_panel<B: Backend>(f : &mut Frame<B>, app : &App, all_tags : &[String], area : Rect) {
+    let highlighted = app.highlighted_tag();
+    let items : Vec<ListItem> = all_tags
+        .iter()
+        .map(|tag| {
+            let mut style = Style::default().fg(Color::Cyan);
+            if app.engine.tag_filter.as_ref() == Some(tag) {
+                style = style.add_modifier(Modifier::BOLD);
+            }
+            if highlighted.as_ref() == Some(tag) {
+                style = style.add_modifier(Modifier::REVERSED);
+            }
+            ListItem::new(format!("#{}", tag)).style(style)
+        })
+        .collect();
+
+    let panel = List::new(items).block(Block::default().borders(Borders::ALL).title("Tags"));
+    f.render_widget(panel, area);
+}
+
+/// Open todo's whose due date has passed
+fn is_overdue(m : &TodoData, today : date::Date) -> bool {
+    m.status != Status::Done && m.due.is_some_and(|due| due < today)
+}
+
+/// Color of a todo with `priority`
+fn priority_style(priority : Priority) -> Style {
+    match priority {
+        Priority::Low => Style::default().fg(Color::DarkGray),
+        Priority::Medium => Style::default(),
+        Priority::High => Style::default().fg(Color::Yellow),
+        Priority::Urgent => Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)
+    }
+}
+
+/// Done entries are crossed out and dimmed, overdue ones red, the rest colored by priority
+fn entry_style(m : &TodoData, today : date::Date) -> Style {
+    let style = if m.status == Status::Done {
+        Style::default().fg(Color::DarkGray).add_modifier(Modifier::CROSSED_OUT | Modifier::DIM)
+    } else if is_overdue(m, today) {
+        Style::default().fg(Color::Red)
+    } else {
+        priority_style(m.priority)
+    };
+    if m.marked { style.bg(Color::Blue) } else { style }
+}
+
+/// Marker rendered in front of an entry's message. Every marker of a list has the same
+/// width so the messages line up in a column.
+fn entry_prefix(app : &App, m : &TodoData) -> String {
+    if app.engine.is_journal() {
+        return match m.created_at {
+            Some(timestamp) => format!("[{}] ", date::DateTime::from_timestamp(timestamp).format()),
+            // Same width as "[2022-09-27 14:03] "
+            None => format!("{:<19}", ">")
+        };
+    }
+
+    let checkbox = match (m.status, app.engine.config.ascii) {
+        (Status::Todo, false) => "[ ]",
+        (Status::InProgress, false) => "[~]",
+        (Status::Done, false) => "[x]",
+        (Status::Todo, true) => "#",
+        (Status::InProgress, true) => "~",
+        (Status::Done, true) => "*"
+    };
+    format!("{}{} ", "  ".repeat(m.indent), checkbox)
+}
+
+/// `[####------] 40%`, `width` characters between the brackets
+fn progress_bar(done : usize, total : usize, width : usize, ascii : bool) -> String {
+    let filled = (done * width).checked_div(total).unwrap_or(0);
+    let percentage = (done * 100).checked_div(total).unwrap_or(0);
+    let (full, empty) = if ascii { ("#", "-") } else { ("█", "░") };
+    format!("[{}{}] {}%", full.repeat(filled), empty.repeat(width - filled), percentage)
+}
+
+pub fn ui<B: Backend>(f: &mut Frame<B>, app: &mut App) {
+    // The help bar can be hidden and the input box can go below the list
+    let mut rows = vec![
+        Constraint::Length(1),
+        Constraint::Length(if app.engine.config.help_bar { 1 } else { 0 }),
+        Constraint::Length(3),
+        Constraint::Min(1),
+        Constraint::Length(1),
+    ];
+    if app.engine.config.input_at_bottom {
+        rows.swap(2, 3);
+    }
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(2)
+        .constraints(rows)
+        .split(f.size());
+    let (input_area, main_area) = if app.engine.config.input_at_bottom { (chunks[3], chunks[2]) } else { (chunks[2], chunks[3]) };
+
+    // The open profile, the running focus timer and the sync state are shown at the right end of the header,
+    // a long task name is cut off so the tabs keep at least two thirds
+    let mut status = vec![];
+    if app.engine.config.profile != DEFAULT_PROFILE {
+        status.push(Span::styled(app.engine.config.profile.clone(), Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD)));
+    }
+    if let Some(timer) = timer_text(app) {
+        if !status.is_empty() {
+            status.push(Span::raw("  "));
+        }
+        status.push(Span::styled(timer, Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)));
+    }
+    if let Some(sync) = sync_text(app.sync_state) {
+        if !status.is_empty() {
+            status.push(Span::raw("  "));
+        }
+        status.push(sync);
+    }
+    let status_width = cmp::min(status.iter().map(|span| span.width() as u16).sum(), chunks[0].width / 3);
+    let header = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Min(1), Constraint::Length(status_width)].as_ref())
+        .split(chunks[0]);
+    let tab_titles : Vec<Spans> = app.engine.lists.iter().map(|list| Spans::from(list.name.clone())).collect();
+    let tabs = Tabs::new(tab_titles)
+        .select(app.engine.target_list)
+        .divider(if app.engine.config.ascii { "|" } else { symbols::line::VERTICAL })
+        .highlight_style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD));
+    f.render_widget(tabs, header[0]);
+    if !status.is_empty() {
+        f.render_widget(Paragraph::new(Spans::from(status)), header[1]);
+    }
+
+    let keys = &app.engine.config.keymap;
+    let (msg, style) = match app.input_mode() {
+        InputMode::Normal => (
+            vec![
+                Span::raw("Press "),
+                Span::styled(keys.key_name(Action::Quit), Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" to exit, "),
+                Span::styled(keys.key_name(Action::Edit), Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" to start editing "),
+                Span::styled(keys.key_name(Action::SwitchList), Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" to change todo type "),
+                Span::styled(keys.key_name(Action::Done), Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" to toggle done "),
+                Span::styled(keys.key_name(Action::Remove), Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" to remove the message "),
+                Span::styled(keys.key_name(Action::Sort), Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" to sort "),
+                Span::styled(keys.key_name(Action::Undo), Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" to undo "),
+                Span::styled(keys.key_name(Action::Help), Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" for all keys "),
+            ],
+            Style::default().add_modifier(Modifier::RAPID_BLINK),
+        ),
+        InputMode::Editing => (
+            vec![
+                Span::raw("Press "),
+                Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" to discard the changes, "),
+                Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" to record the message"),
+            ],
+            Style::default(),
+        ),
+        InputMode::Notes => (
+            vec![
+                Span::raw("Press "),
+                Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" to save the notes, "),
+                Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" to start a new line"),
+            ],
+            Style::default(),
+        ),
+        InputMode::Jumping => (
+            vec![
+                Span::raw("Type a letter to jump to the next todo starting with it, "),
+                Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" to stop jumping"),
+            ],
+            Style::default(),
+        ),
+        InputMode::Searching => (
+            vec![
+                Span::raw("Type to search, "),
+                Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" to keep the search for n/N, "),
+                Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" to cancel"),
+            ],
+            Style::default(),
+        ),
+        InputMode::Prompting => (
+            vec![
+                Span::raw("Press "),
+                Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" to cancel, "),
+                Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" to confirm"),
+            ],
+            Style::default(),
+        ),
+    };
+    let (msg, style) = match &app.engine.status_message {
+        Some(status) => (vec![Span::raw(status.clone())], Style::default().fg(Color::Red)),
+        None => (msg, style)
+    };
+    let mut text = Text::from(Spans::from(msg));
+    text.patch_style(style);
+    let help_message = Paragraph::new(text);
+    f.render_widget(help_message, chunks[1]);
+
+    let input_title = match app.engine.config.max_todo_length {
+        Some(limit) => format!("{} ({}/{})", input_title(app), app.engine.input_length(), limit),
+        None => input_title(app).to_string()
+    };
+    let input_border_style = if app.engine.is_at_length_limit() {
+        Style::default().fg(Color::Red)
+    } else if app.engine.is_near_length_limit() {
+        Style::default().fg(Color::Yellow)
+    } else {
+        Style::default()
+    };
+
+    let prompt_text = match app.input_mode() {
+        InputMode::Searching => app.engine.search.as_deref().unwrap_or(""),
+        _ => app.prompt.as_ref().map_or("", |prompt| prompt.text.as_str())
+    };
+    let input_text = match app.input_mode() {
+        InputMode::Normal | InputMode::Jumping | InputMode::Notes => Spans::from(app.engine.input.as_str()),
+        InputMode::Editing => highlight_trailing_whitespace(&app.engine.input),
+        InputMode::Prompting | InputMode::Searching => Spans::from(prompt_text)
+    };
+
+    let input = Paragraph::new(input_text)
+        .style(match app.input_mode() {
+            InputMode::Normal | InputMode::Jumping | InputMode::Notes => Style::default(),
+            InputMode::Editing | InputMode::Prompting | InputMode::Searching => Style::default().fg(Color::Yellow),
+        })
+        .block(Block::default().borders(Borders::ALL).border_style(input_border_style).title(input_title));
+    f.render_widget(input, input_area);
+    match app.input_mode() {
+        InputMode::Normal =>
+            // Hide the cursor. `Frame` does this by default, so we don't need to do anything here
+            {}
+
+        InputMode::Editing => {
+            // Make the cursor visible and ask tui-rs to put it at the specified coordinates after rendering
+            f.set_cursor(
+                // Put cursor in front of the character it's on
+                input_area.x + text::display_width(&app.engine.input, app.engine.target_column as usize) as u16 + 1,
+                // Move one line down, from the border to the input line
+                input_area.y + 1,
+            )
+        }
+
+        InputMode::Prompting | InputMode::Jumping | InputMode::Searching | InputMode::Notes => {}
+    }
+
+    // The daily list goes on the left of the split view and the long term list on the right
+    let mut list_area = main_area;
+    app.side_pane = None;
+    if let Some(other) = app.split_partner() {
+        let panes = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(app.engine.config.split_width), Constraint::Percentage(100 - app.engine.config.split_width)].as_ref())
+            .split(main_area);
+        let (active, side) = if matches!(app.engine.active_list().role, ListRole::Daily(_)) { (panes[0], panes[1]) } else { (panes[1], panes[0]) };
+        list_area = active;
+        render_side_list(f, app, other, side);
+        app.side_pane = Some(side);
+    }
+
+    let all_tags = app.engine.tag_index();
+    let (list_area, tag_area) = if all_tags.is_empty() {
+        (list_area, None)
+    } else {
+        let widest = all_tags.iter().map(|tag| tag.width()).max().unwrap_or(0);
+        let panel_width = cmp::min(widest as u16 + 4, 24);
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Min(1), Constraint::Length(panel_width)].as_ref())
+            .split(list_area);
+        (columns[0], Some(columns[1]))
+    };
+    let (list_area, details_area) = if app.show_details {
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(100 - app.engine.config.details_width), Constraint::Percentage(app.engine.config.details_width)].as_ref())
+            .split(list_area);
+        (columns[0], Some(columns[1]))
+    } else {
+        (list_area, None)
+    };
+
+    let mut messages_to_display : Vec<TodoData> =app.engine.get_messages();
+    if app.engine.editing {
+        // Show what's typed so far, the entry only changes once it's confirmed
+        messages_to_display[app.engine.target_row as usize].message = app.engine.input.clone();
+    }
+    let visible_indices = app.engine.visible_indices();
+    let title = match &app.engine.tag_filter {
+        Some(tag) => format!("{} [#{}]", get_title(app), tag),
+        None => get_title(app)
+    };
+
+    let today = date::Date::today();
+    // Long entries wrap, continuation lines line up with the message behind the prefix
+    let subtask_gauge = (app.engine.active_list().role == ListRole::LongTerm).then_some(app.engine.config.ascii);
+    // Todo's waiting for one that isn't done are dimmed behind a marker
+    let open_blockers = app.engine.open_blocker_ids();
+    let is_blocked = |m : &TodoData| m.status != Status::Done && m.blocked_by.is_some_and(|id| open_blockers.contains(&id));
+    let blocked_marker = if app.engine.config.ascii { "[blocked] " } else { "⛔ " };
+    let now = (app.engine.config.show_age && !app.engine.is_journal()).then(date::now);
+    let daily = matches!(app.engine.active_list().role, ListRole::Daily(_));
+    let entry_content = |index : usize, tracking : Option<u32>| {
+        let mut content = content_spans(&messages_to_display, index, tracking, subtask_gauge);
+        if is_blocked(&messages_to_display[index]) {
+            content.insert(0, Span::styled(blocked_marker, Style::default().fg(Color::Red)));
+        }
+        content.extend(now.and_then(|now| age_span(&messages_to_display[index], now)));
+        if daily {
+            content.extend(carried_span(&messages_to_display[index]));
+        }
+        content
+    };
+    let list_width = (list_area.width.saturating_sub(2) as usize).saturating_sub(SELECTED_SYMBOL.width());
+    let wrap_width = |prefix : &str| cmp::max(list_width.saturating_sub(prefix.width()), 1);
+    let messages: Vec<ListItem> = visible_indices
+        .iter()
+        .map(|index| {
+            let m = &messages_to_display[*index];
+            let prefix = entry_prefix(app, m);
+            let content = entry_content(*index, app.tracking_secs(m));
+            let content_text : String = content.iter().map(|span| span.content.as_ref()).collect();
+            let breaks : Vec<usize> = text::wrap_starts(&content_text, wrap_width(&prefix))[1..]
+                .iter()
+                .map(|start| text::byte_offset(&content_text, *start))
+                .collect();
+
+            let padding = " ".repeat(prefix.width());
+            let mut lines : Vec<Spans> = split_spans(content, &breaks)
+                .into_iter()
+                .enumerate()
+                .map(|(line, mut spans)| {
+                    spans.insert(0, Span::raw(if line == 0 { prefix.clone() } else { padding.clone() }));
+                    Spans::from(spans)
+                })
+                .collect();
+            lines.extend((0..app.engine.config.item_spacing).map(|_| Spans::default()));
+
+            let style = entry_style(m, today);
+            ListItem::new(lines).style(if is_blocked(m) { style.fg(Color::DarkGray) } else { style })
+        })
+        .collect();
+
+    // Scroll just enough to keep the selected entry fully visible
+    let item_heights : Vec<usize> = messages.iter().map(|item| item.height()).collect();
+    let list_height = list_area.height.saturating_sub(2) as usize;
+    let selected = visible_indices
+        .iter()
+        .position(|index| *index == app.engine.target_row as usize)
+        .unwrap_or(0);
+    app.engine.list_offset = list_scroll_offset(&item_heights, selected, app.engine.list_offset, list_height);
+    app.page_size = list_height / (1 + app.engine.config.item_spacing);
+    let visible_messages : Vec<ListItem> = messages.into_iter().skip(app.engine.list_offset).collect();
+
+    let messages =
+        List::new(visible_messages).block(
+            Block::default()
+            .borders(Borders::ALL)
+            .title(title)
+            .style(match app.input_mode() {
+                InputMode::Normal | InputMode::Prompting | InputMode::Searching | InputMode::Notes => Style::default(),
+                InputMode::Editing => Style::default().fg(Color::Yellow),
+                InputMode::Jumping => Style::default().fg(Color::Cyan),
+            }))
+        .highlight_symbol(SELECTED_SYMBOL)
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    // The items are already scrolled, the state only picks the selected one among them
+    let mut list_state = ListState::default();
+    list_state.select(Some(selected - app.engine.list_offset));
+    f.render_stateful_widget(messages, list_area, &mut list_state);
+
+    if let Some(tag_area) = tag_area {
+        render_tag_panel(f, app, &all_tags, tag_area);
+    }
+    let notes_cursor = details_area.map(|area| render_details(f, app, area));
+
+    app.rendered_rows.clear();
+    let mut row_y = list_area.y + 1;
+    for (visible_position, height) in item_heights.iter().enumerate().skip(app.engine.list_offset) {
+        if row_y >= list_area.y + list_area.height.saturating_sub(1) {
+            break;
+        }
+
+        let index = visible_indices[visible_position];
+        let m = &messages_to_display[index];
+        let checkbox_start = list_area.x + 1 + (SELECTED_SYMBOL.width() + m.indent * 2) as u16;
+        let checkbox_width = if app.engine.config.ascii { 1 } else { 3 };
+        app.rendered_rows.push(RenderedRow {
+            index,
+            y : row_y,
+            height : *height as u16,
+            checkbox : (checkbox_start, checkbox_start + checkbox_width)
+        });
+        row_y += *height as u16;
+    }
+
+    let selected_prefix = entry_prefix(app, &messages_to_display[app.engine.target_row as usize]);
+    let selected_entry = &messages_to_display[app.engine.target_row as usize];
+    let selected_content : String = entry_content(app.engine.target_row as usize, app.tracking_secs(selected_entry))
+        .iter()
+        .map(|span| span.content.as_ref())
+        .collect();
+    let selected_width = wrap_width(&selected_prefix);
+    let starts = text::wrap_starts(&selected_content, selected_width);
+    let (cursor_line, cursor_cell) = text::wrapped_cursor(&selected_content, &starts, app.engine.target_column as usize);
+    // Keep a cursor behind a full last line inside the list
+    let cursor_cell = cmp::min(cursor_cell, selected_width - 1);
+    let x_offset = 1 + (SELECTED_SYMBOL.width() + selected_prefix.width() + cursor_cell) as u16;
+    let rows_above_selected : usize = item_heights[app.engine.list_offset..selected].iter().sum();
+    let y_offset = 1 + (rows_above_selected + cursor_line) as u16;
+
+    if matches!(app.input_mode(), InputMode::Prompting | InputMode::Searching) {
+        // The answer is typed in the input box, keep the cursor there
+        f.set_cursor(input_area.x + prompt_text.width() as u16 + 1, input_area.y + 1);
+    } else if let (InputMode::Notes, Some((x, y))) = (app.input_mode(), notes_cursor) {
+        f.set_cursor(x, y);
+    } else {
+        f.set_cursor(
+            list_area.x + x_offset,
+            list_area.y + y_offset 
+        );
+    }
+
+    // Without the help bar messages take the place of the status bar until the next key
+    let status_bar = match (&app.engine.status_message, app.engine.config.help_bar) {
+        (Some(message), false) => Paragraph::new(Span::styled(message.clone(), Style::default().fg(Color::Red))),
+        _ => Paragraph::new(status_bar_text(app))
+    };
+    f.render_widget(status_bar, chunks[4]);
+
+    if let Some(popup) = &app.popup {
+        render_popup(f, popup, &app.engine.config.keymap);
+    }
+
+    if app.engine.config.ascii {
+        f.render_widget(AsciiBorders, f.size());
+    }
+}
+
+/// The list without the focus in the split view, in the order it's shown with the focus and
+/// with the entry it was left off on selected
+fn render_side_list<B: Backend>(f: &mut Frame<B>, app : &App, index : usize, area : Rect) {
+    let list = &app.engine.lists[index];
+    let today = date::Date::today();
+    let mut visible : Vec<usize> = (0..list.entries.len()).filter(|index| !is_folded_away(&list.entries, *index)).collect();
+    sort_by_priority(&list.entries, &mut visible);
+
+    let items : Vec<ListItem> = visible
+        .iter()
+        .map(|index| {
+            let m = &list.entries[*index];
+            let mut spans = vec![Span::raw(entry_prefix(app, m))];
+            spans.extend(content_spans(&list.entries, *index, None, None));
+            ListItem::new(Spans::from(spans)).style(entry_style(m, today))
+        })
+        .collect();
+
+    let mut list_state = ListState::default();
+    list_state.select(visible.iter().position(|index| *index as i32 == list.view.row));
+    let side_list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(list.name.clone()).border_style(Style::default().fg(Color::DarkGray)))
+        .highlight_symbol(SELECTED_SYMBOL);
+    f.render_stateful_widget(side_list, area, &mut list_state);
+}
+
+/// Pane with the notes of the selected entry, or the ones being typed. Returns where the
+/// cursor goes while they're edited.
+fn render_details<B: Backend>(f: &mut Frame<B>, app : &App, area : Rect) -> (u16, u16) {
+    let editing = matches!(app.input_mode(), InputMode::Notes);
+    let notes = if editing { app.notes_input.clone() } else { app.engine.get_messages()[app.engine.target_row as usize].notes.clone() };
+    let width = cmp::max(area.width.saturating_sub(2) as usize, 1);
+
+    // Every line of the notes wraps on its own, counting the cursor's graphemes down as the
+    // lines before it are passed
+    let mut lines = vec![];
+    let mut cursor = (0, 0);
+    let mut column_left = Some(app.notes_column);
+    for line in notes.split('\n') {
+        let starts = text::wrap_starts(line, width);
+        let length = text::grapheme_count(line);
+        if let Some(column) = column_left {
+            if column <= length {
+                let (row, cell) = text::wrapped_cursor(line, &starts, column);
+                cursor = (cmp::min(cell, width - 1), lines.len() + row);
+                column_left = None;
+            } else {
+                column_left = Some(column - length - 1);
+            }
+        }
+        for (position, start) in starts.iter().enumerate() {
+            let end = starts.get(position + 1).map_or(line.len(), |end| text::byte_offset(line, *end));
+            lines.push(Spans::from(line[text::byte_offset(line, *start)..end].to_string()));
+        }
+    }
+    if notes.is_empty() && !editing {
+        let hint = format!("No notes, {} adds some", app.engine.config.keymap.key_name(Action::EditNotes));
+        lines = vec![Spans::from(Span::styled(hint, Style::default().fg(Color::DarkGray)))];
+    }
+
+    // Scroll just enough to keep the cursor inside the pane
+    let height = cmp::max(area.height.saturating_sub(2) as usize, 1);
+    let scroll = cursor.1.saturating_sub(height - 1);
+    let style = if editing { Style::default().fg(Color::Yellow) } else { Style::default() };
+    let entry = &app.engine.get_messages()[app.engine.target_row as usize];
+    let mut title = "Notes".to_string();
+    if let Some(created_at) = entry.created_at {
+        title.push_str(&format!(", written {}", date::DateTime::from_timestamp(created_at).format()));
+    }
+    if let Some(completed_at) = entry.completed_at {
+        title.push_str(&format!(", done {}", date::DateTime::from_timestamp(completed_at).format()));
+    }
+    if entry.estimate_secs > 0 {
+        title.push_str(&format!(", estimated {}", date::format_duration(entry.estimate_secs)));
+    }
+    let spent = entry.actual_secs + app.tracking_secs(entry).unwrap_or(0);
+    if spent > 0 {
+        title.push_str(&format!(", spent {}", date::format_duration(spent)));
+    }
+    let details = Paragraph::new(lines)
+        .scroll((scroll as u16, 0))
+        .block(Block::default().borders(Borders::ALL).title(title).style(style));
+    f.render_widget(details, area);
+    (area.x + 1 + cursor.0 as u16, area.y + 1 + (cursor.1 - scroll) as u16)
+}
+
+/// Redraws the box-drawing characters of everything rendered so far with `+`, `-` and `|`.
+/// Rendered last so every block in the frame gets converted, message text is left alone.
+struct AsciiBorders;
+
+impl Widget for AsciiBorders {
+    fn render(self, area : Rect, buf : &mut Buffer) {
+        for y in area.top()..area.bottom() {
+            for x in area.left()..area.right() {
+                let cell = buf.get_mut(x, y);
+                let replacement = match cell.symbol.as_str() {
+                    "─" | "━" | "═" => "-",
+                    "│" | "┃" | "║" => "|",
+                    "┌" | "┐" | "└" | "┘" | "╭" | "╮" | "╰" | "╯" | "┏" | "┓" | "┗" | "┛" | "╔" | "╗" | "╚" | "╝" => "+",
+                    _ => continue
+                };
+                cell.set_symbol(replacement);
+            }
+        }
+    }
+}
+
+/// Returns a rect of `percent_x` by `percent_y` centered in `area`
+fn centered_rect(percent_x : u16, percent_y : u16, area : Rect) -> Rect {
+    let vertical = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage((100 - percent_y) / 2),
+            Constraint::Percentage(percent_y),
+            Constraint::Percentage((100 - percent_y) / 2),
+        ].as_ref())
+        .split(area);
+
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage((100 - percent_x) / 2),
+            Constraint::Percentage(percent_x),
+            Constraint::Percentage((100 - percent_x) / 2),
+        ].as_ref())
+        .split(vertical[1])[1]
+}
+
+fn render_popup<B: Backend>(f: &mut Frame<B>, popup : &Popup, keymap : &KeyMap) {
+    let area = match popup {
+        Popup::ConfirmRemove(_) => centered_rect(50, 20, f.size()),
+        _ => centered_rect(60, 60, f.size())
+    };
+    let (title, lines) = match popup {
+        Popup::DayDiff(diff) => ("Changes since yesterday", day_diff_lines(diff)),
+        Popup::Stats(history) => return render_stats(f, history),
+        Popup::Calendar(calendar) => return render_calendar(f, calendar),
+        Popup::History(history) => return render_history(f, history),
+        Popup::Profiles(profiles) => return render_profiles(f, profiles),
+        Popup::QuickAdd(quick_add) => return render_quick_add(f, quick_add),
+        Popup::Help(scroll) => return render_help(f, keymap, *scroll),
+        Popup::Error(message) => ("Something went wrong", vec![
+            Spans::from(Span::styled(message.clone(), Style::default().fg(Color::Red))),
+            Spans::from(""),
+            Spans::from("What the key was doing was stopped and the lists are still open. Any key closes this."),
+        ]),
+        Popup::ConfirmRemove(message) => ("Remove todo", vec![
+            Spans::from(message.clone()),
+            Spans::from(""),
+            Spans::from(vec![
+                Span::styled("y", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" removes it, any other key keeps it"),
+            ]),
+        ])
+    };
+
+    let paragraph = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .wrap(Wrap { trim: false });
+    f.render_widget(Clear, area);
+    f.render_widget(paragraph, area);
+}
+
+/// Month grid of the selected day's month. Days with a list are bold, days with due
+/// todo's yellow, today is underlined.
+fn render_calendar<B: Backend>(f: &mut Frame<B>, calendar : &Calendar) {
+    const MONTHS : [&str; 12] = ["January", "February", "March", "April", "May", "June", "July",
+        "August", "September", "October", "November", "December"];
+    let selected = calendar.selected;
+    let today = date::Date::today();
+
+    let mut lines = vec![Spans::from(Span::styled("Mo Tu We Th Fr Sa Su", Style::default().fg(Color::DarkGray)))];
+    let first = date::Date { day : 1, ..selected };
+    let mut week = vec![Span::raw("   ".repeat(first.weekday() as usize))];
+    for day in 1..=date::days_in_month(selected.year, selected.month) {
+        let date = date::Date { day, ..selected };
+        let mut style = Style::default();
+        if calendar.with_file.contains(&date) {
+            style = style.add_modifier(Modifier::BOLD);
+        }
+        if calendar.with_due.contains(&date) {
+            style = style.fg(Color::Yellow);
+        }
+        if date == today {
+            style = style.add_modifier(Modifier::UNDERLINED);
+        }
+        if date == selected {
+            style = style.add_modifier(Modifier::REVERSED);
+        }
+        week.push(Span::styled(format!("{:>2}", day), style));
+        week.push(Span::raw(" "));
+        if date.weekday() == 6 {
+            lines.push(Spans::from(std::mem::take(&mut week)));
+        }
+    }
+    if !week.is_empty() {
+        lines.push(Spans::from(week));
+    }
+    lines.push(Spans::from(""));
+    lines.push(Spans::from(vec![
+        Span::styled("bold", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" has a list, "),
+        Span::styled("yellow", Style::default().fg(Color::Yellow)),
+        Span::raw(" has due todo's"),
+    ]));
+    lines.push(Spans::from("Arrows move, PgUp/PgDn change month, Enter opens, Esc closes"));
+
+    let area = centered_rect(60, 60, f.size());
+    let title = format!("{} {}", MONTHS[selected.month as usize - 1], selected.year);
+    let paragraph = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .wrap(Wrap { trim: false });
+    f.render_widget(Clear, area);
+    f.render_widget(paragraph, area);
+}
+
+/// Profiles in the config, the active one marked
+fn render_profiles<B: Backend>(f: &mut Frame<B>, profiles : &Profiles) {
+    let items : Vec<ListItem> = profiles.names.iter().enumerate().map(|(index, name)| {
+        let marker = if index == profiles.open { " (open)" } else { "" };
+        ListItem::new(Spans::from(vec![
+            Span::raw(name.clone()),
+            Span::styled(marker, Style::default().fg(Color::DarkGray)),
+        ]))
+    }).collect();
+
+    let area = centered_rect(40, 40, f.size());
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title("Profiles, Enter switches, Esc closes"))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    let mut state = ListState::default();
+    state.select(Some(profiles.selected));
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(list, area, &mut state);
+}
+
+/// Heading of every mode followed by its keys and what they do, normal mode's as they are
+/// bound in `keymap`. Unbound actions are left out.
+pub fn help_lines(keymap : &KeyMap) -> Vec<Spans<'static>> {
+    let heading = |name : &str| Spans::from(Span::styled(name.to_string(), Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)));
+    let row = |keys : String, description : String| Spans::from(vec![
+        Span::styled(format!("  {:<22}", keys), Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(description),
+    ]);
+
+    let mut lines = vec![heading("Normal")];
+    for (name, action) in Action::all() {
+        let keys = keymap.key_names(action);
+        if keys.is_empty() {
+            continue;
+        }
+        let description = match action {
+            // `o` opens a line below in vim, so links are opened with `L`
+            Action::OpenLink => "open link in the browser (o is open below)".to_string(),
+            _ => name.replace('_', " ")
+        };
+        lines.push(row(keys.join(" "), description));
+    }
+    for (mode, keys) in FIXED_KEYS {
+        lines.push(Spans::default());
+        lines.push(heading(mode));
+        lines.extend(keys.iter().map(|(keys, description)| row(keys.to_string(), description.to_string())));
+    }
+    lines
+}
+
+/// Every key binding over the whole screen
+fn render_help<B: Backend>(f: &mut Frame<B>, keymap : &KeyMap, scroll : usize) {
+    let area = centered_rect(90, 90, f.size());
+    let paragraph = Paragraph::new(help_lines(keymap))
+        .scroll((scroll as u16, 0))
+        .block(Block::default().borders(Borders::ALL).title("Keys, up and down scroll, Esc closes"));
+    f.render_widget(Clear, area);
+    f.render_widget(paragraph, area);
+}
+
+/// The todo typed so far and the list it goes to, with the cursor behind the text
+fn render_quick_add<B: Backend>(f: &mut Frame<B>, quick_add : &QuickAdd) {
+    let area = centered_rect(60, 20, f.size());
+    let list = &quick_add.targets[quick_add.selected].1;
+    let lines = vec![
+        Spans::from(quick_add.text.clone()),
+        Spans::from(""),
+        Spans::from(vec![
+            Span::raw("Adds to "),
+            Span::styled(list.clone(), Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            Span::styled(", Tab picks another list, Esc closes", Style::default().fg(Color::DarkGray)),
+        ]),
+    ];
+
+    let paragraph = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title("Quick add"))
+        .wrap(Wrap { trim: false });
+    f.render_widget(Clear, area);
+    f.render_widget(paragraph, area);
+    let cursor = cmp::min(quick_add.text.width() as u16, area.width.saturating_sub(3));
+    f.set_cursor(area.x + 1 + cursor, area.y + 1);
+}
+
+/// Days with a file, newest first, with their weekday and how far back they are
+fn render_history<B: Backend>(f: &mut Frame<B>, history : &History) {
+    const WEEKDAYS : [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+    let today = date::Date::today();
+    let items : Vec<ListItem> = history.days.iter().map(|day| {
+        let distance = today.to_days() - day.to_days();
+        let when = match distance {
+            0 => "today".to_string(),
+            1 => "yesterday".to_string(),
+            -1 => "tomorrow".to_string(),
+            distance if distance < 0 => format!("in {} days", -distance),
+            distance => format!("{} days ago", distance)
+        };
+        ListItem::new(Spans::from(vec![
+            Span::raw(format!("{} {}  ", day.format(), WEEKDAYS[day.weekday() as usize])),
+            Span::styled(when, Style::default().fg(Color::DarkGray)),
+        ]))
+    }).collect();
+
+    let area = centered_rect(60, 60, f.size());
+    let title = if items.is_empty() {
+        "No days with a file yet".to_string()
+    } else {
+        "Previous days, Enter opens, Esc closes".to_string()
+    };
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    let mut state = ListState::default();
+    state.select(Some(history.selected));
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(list, area, &mut state);
+}
+
+/// Bar chart of the todo's finished per day with totals above it
+fn render_stats<B: Backend>(f: &mut Frame<B>, history : &[(date::Date, usize)]) {
+    let area = centered_rect(80, 60, f.size());
+    let block = Block::default().borders(Borders::ALL).title(format!("Finished todo's, last {} days", history.len()));
+    let inner = block.inner(area);
+    f.render_widget(Clear, area);
+    f.render_widget(block, area);
+
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(2), Constraint::Min(1)].as_ref())
+        .split(inner);
+
+    let total : usize = history.iter().map(|(_, done)| done).sum();
+    let best = history.iter().max_by_key(|(_, done)| *done);
+    let mut summary = format!("{} finished, {:.1} a day", total, total as f64 / cmp::max(history.len(), 1) as f64);
+    if let Some((day, done)) = best.filter(|(_, done)| *done > 0) {
+        summary.push_str(&format!(", best day {} with {}", day.format(), done));
+    }
+    f.render_widget(Paragraph::new(summary), rows[0]);
+
+    // Every bar takes its width plus a gap, leave out the oldest days that don't fit
+    const BAR_WIDTH : u16 = 5;
+    let fitting = cmp::max((rows[1].width + 1) / (BAR_WIDTH + 1), 1) as usize;
+    let shown = &history[history.len().saturating_sub(fitting)..];
+    let labels : Vec<String> = shown.iter().map(|(day, _)| format!("{:02}-{:02}", day.month, day.day)).collect();
+    let data : Vec<(&str, u64)> = labels.iter().zip(shown).map(|(label, (_, done))| (label.as_str(), *done as u64)).collect();
+
+    let chart = BarChart::default()
+        .data(&data)
+        .bar_width(BAR_WIDTH)
+        .bar_gap(1)
+        .bar_style(Style::default().fg(Color::Cyan))
+        .value_style(Style::default().fg(Color::Black).bg(Color::Cyan));
+    f.render_widget(chart, rows[1]);
+}
+
+fn day_diff_lines(diff : &Option<DayDiff>) -> Vec<Spans<'static>> {
+    let diff = match diff {
+        Some(diff) => diff,
+        None => return vec![Spans::from("No prior data")]
+    };
+
+    let mut lines = vec![];
+    let sections = [
+        ("Added", &diff.added, Color::Green),
+        ("Completed", &diff.completed, Color::Cyan),
+        ("Removed", &diff.removed, Color::Red),
+    ];
+    for (heading, messages, color) in sections {
+        lines.push(Spans::from(Span::styled(
+            format!("{} ({})", heading, messages.len()),
+            Style::default().fg(color).add_modifier(Modifier::BOLD))));
+        for message in messages {
+            lines.push(Spans::from(format!("  {}", message)));
+        }
+        lines.push(Spans::from(""));
+    }
+    lines
+}
+
+/// `Focus 24:59 write report` while a focus timer runs
+fn timer_text(app : &App) -> Option<String> {
+    let secs = app.timer_remaining()?.as_secs();
+    Some(format!("Focus {:02}:{:02} {}", secs / 60, secs % 60, app.timer_task()))
+}
+
+fn sync_text(state : SyncState) -> Option<Span<'static>> {
+    match state {
+        SyncState::Idle => None,
+        SyncState::Running => Some(Span::styled("Syncing", Style::default().fg(Color::Yellow))),
+        SyncState::Synced(time) => Some(Span::styled(
+            format!("Synced {:02}:{:02}", time.hour, time.minute),
+            Style::default().fg(Color::DarkGray))),
+        SyncState::Failed => Some(Span::styled("Sync failed", Style::default().fg(Color::Red)))
+    }
+}
+
+fn status_bar_text(app : &App) -> Spans<'static> {
+    let mut spans = vec![];
+
+    if let Some(spinner) = app.job_spinner() {
+        spans.push(Span::styled(format!("{} ", spinner), Style::default().fg(Color::Yellow)));
+    }
+
+    let marked = app.engine.marked_indices().len();
+    if app.engine.visual_anchor.is_some() || marked > 0 {
+        let mode = if app.engine.visual_anchor.is_some() { "VISUAL " } else { "" };
+        spans.push(Span::styled(format!("{}{} marked ", mode, marked), Style::default().fg(Color::Blue).add_modifier(Modifier::BOLD)));
+    }
+
+    if let Some(warning) = app.work_hours_warning() {
+        if !spans.is_empty() {
+            spans.push(Span::raw(" | "));
+        }
+        spans.push(Span::styled(
+            format!("{} (Esc to dismiss)", warning),
+            Style::default().fg(Color::Magenta)));
+    }
+
+    if !app.engine.is_journal() {
+        let (done, total) = progress(&app.engine.active_list().entries);
+        if !spans.is_empty() {
+            spans.push(Span::raw(" | "));
+        }
+        spans.push(Span::raw(format!(
+            "{} items, {} done, {} left {}",
+            total, done, total - done, progress_bar(done, total, 10, app.engine.config.ascii))));
+    }
+
+    // Nothing is shown while an autosave waits for the typing to stop, "saved" would be stale
+    let save_state = match (&app.engine.save_status, app.engine.unsaved_since) {
+        (SaveStatus::Saving, _) => Some(Span::styled("saving...", Style::default().fg(Color::Yellow))),
+        (SaveStatus::Clean, _) | (SaveStatus::Saved(_), Some(_)) => None,
+        (SaveStatus::Saved(_), None) => Some(Span::styled("saved", Style::default().fg(Color::DarkGray))),
+        (SaveStatus::Failed(err), _) => Some(Span::styled(format!("autosave failed: {}", err), Style::default().fg(Color::Red)))
+    };
+    if let Some(save_state) = save_state {
+        if !spans.is_empty() {
+            spans.push(Span::raw(" | "));
+        }
+        spans.push(save_state);
+    }
+
+    Spans::from(spans)
+}
+
+/// Returns the first entry to render so that `selected` fits within `max_height` rows,
+/// moving as little as possible from `previous_offset`.
+fn list_scroll_offset(item_heights : &[usize], selected : usize, previous_offset : usize, max_height : usize) -> usize {
+    if item_heights.is_empty() {
+        return 0;
+    }
+
+    let selected = selected.min(item_heights.len() - 1);
+    let mut offset = previous_offset.min(selected);
+
+    while offset < selected && item_heights[offset..=selected].iter().sum::<usize>() > max_height {
+        offset += 1;
+    }
+
+    offset
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tui::Terminal;
+
+    use crate::app::tests::{daily_app, todo};
+
+    #[test]
+    fn progress_bar_fills_with_the_done_share() {
+        assert_eq!(progress_bar(2, 5, 10, true), "[####------] 40%");
+        assert_eq!(progress_bar(0, 0, 4, true), "[----] 0%");
+        assert_eq!(progress_bar(3, 3, 4, false), "[████] 100%");
+    }
+
+    #[test]
+    fn new_todos_are_stamped_and_show_their_age_until_done() {
+        let mut app = daily_app(vec![todo("")]);
+        app.engine.start_editing();
+        app.engine.add_char('x');
+        app.engine.commit_edit();
+        let written = app.engine.get_messages()[0].created_at.unwrap();
+        assert!(written.abs_diff(date::now()) < 60);
+
+        let age = |m : &TodoData, days : u64| age_span(m, written + days * 86_400).map(|span| (span.content.to_string(), span.style.fg));
+        let m = &app.engine.get_messages()[0];
+        assert_eq!(age(m, 0), None);
+        assert_eq!(age(m, 3), Some((" (3d old)".to_string(), Some(Color::DarkGray))));
+        assert_eq!(age(m, STALE_DAYS), Some((format!(" ({}d old)", STALE_DAYS), Some(Color::Yellow))));
+
+        todo_core::toggle(app.engine.get_messages_mut(), 0, date::Date::today());
+        let m = &app.engine.get_messages()[0];
+        assert!(m.completed_at.is_some());
+        assert_eq!(age(m, 3), None);
+    }
+
+    #[test]
+    fn help_lists_the_configured_keys_by_mode() {
+        let values = std::collections::HashMap::from([("keys.quit".to_string(), "x ctrl+q".to_string()), ("keys.sort".to_string(), String::new())]);
+        let text : Vec<String> = help_lines(&KeyMap::from_config(&values))
+            .iter()
+            .map(|line| line.0.iter().map(|span| span.content.as_ref()).collect::<String>().trim_end().to_string())
+            .collect();
+
+        assert_eq!(text[0], "Normal");
+        assert!(text.contains(&format!("  {:<22}quit", "x ctrl+q")));
+        assert!(text.contains(&format!("  {:<22}move to other list", "m")));
+        assert!(!text.iter().any(|line| line.ends_with(" sort")));
+        assert!(text.contains(&"Notes".to_string()));
+    }
+
+    #[test]
+    fn subtask_gauge_counts_the_done_children() {
+        let entries = vec![
+            todo("project"),
+            TodoData { indent : 1, status : Status::Done, ..todo("first") },
+            TodoData { indent : 1, ..todo("second") },
+            todo("alone"),
+        ];
+        let text = |index, gauge| -> String {
+            content_spans(&entries, index, None, gauge).iter().map(|span| span.content.as_ref()).collect()
+        };
+
+        assert_eq!(text(0, Some(true)), "project [###---] 50% 1/2");
+        assert_eq!(text(3, Some(true)), "alone");
+        assert_eq!(text(0, None), "project");
+    }
+
+    #[test]
+    fn split_spans_keeps_styles_across_the_break() {
+        let tag = Style::default().fg(Color::Cyan);
+        let lines = split_spans(vec![Span::raw("call "), Span::styled("#mom", tag), Span::raw(" soon")], &[5, 7]);
+
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0][0].content, "call ");
+        assert_eq!((lines[1][0].content.as_ref(), lines[1][0].style), ("#m", tag));
+        assert_eq!(lines[2][0].content, "om");
+        assert_eq!(lines[2][1].content, " soon");
+    }
+
+    #[test]
+    fn selected_row_is_drawn_behind_the_symbol() {
+        let mut app = daily_app(vec![
+            TodoData { message : "first".to_string(), ..TodoData::default() },
+            TodoData { message : "second".to_string(), ..TodoData::default() },
+        ]);
+        app.engine.target_row = 1;
+        let mut terminal = Terminal::new(tui::backend::TestBackend::new(60, 20)).unwrap();
+        terminal.draw(|f| ui(f, &mut app)).unwrap();
+
+        let rows : Vec<String> = app.rendered_rows
+            .iter()
+            .map(|row| {
+                let start = row.checkbox.0 - SELECTED_SYMBOL.width() as u16;
+                (start..start + 12).map(|x| terminal.backend().buffer().get(x, row.y).symbol.clone()).collect()
+            })
+            .collect();
+        assert_eq!(rows, vec!["  [ ] first ", "> [ ] second"]);
+    }
+
+    #[test]
+    fn done_entries_are_crossed_out_even_when_overdue() {
+        let today = date::Date::today();
+        let overdue = TodoData { due : Some(today.add_days(-1)), ..TodoData::default() };
+        let done = TodoData { status : Status::Done, ..overdue.clone() };
+
+        assert_eq!(entry_style(&overdue, today).fg, Some(Color::Red));
+        assert!(entry_style(&done, today).add_modifier.contains(Modifier::CROSSED_OUT));
+        assert_eq!(entry_style(&done, today).fg, Some(Color::DarkGray));
+    }
+
+    #[test]
+    fn list_scroll_offset_keeps_selected_row_visible() {
+        let heights = vec![1; 20];
+
+        // Fits without scrolling
+        assert_eq!(list_scroll_offset(&heights, 4, 0, 5), 0);
+        // Scrolling down puts the selection on the last visible row
+        assert_eq!(list_scroll_offset(&heights, 12, 0, 5), 8);
+        // Moving back up within the window leaves the offset alone
+        assert_eq!(list_scroll_offset(&heights, 10, 8, 5), 8);
+        // Moving above the window scrolls up to the selection
+        assert_eq!(list_scroll_offset(&heights, 3, 8, 5), 3);
+        // Taller (wrapped) entries take up more rows
+        assert_eq!(list_scroll_offset(&[2, 2, 2, 2], 3, 0, 5), 2);
+    }
+}
@@ -0,0 +1,167 @@
+//! Undo and redo of the changes made to the lists, a step at a time
+
+use std::mem;
+
+use crate::lists::{self, TodoList};
+use crate::TodoData;
+
+/// Undo steps kept before the oldest ones are dropped
+pub const UNDO_LIMIT : usize = 100;
+
+/// A list as it was before a change, put back by undo
+#[derive(Clone)]
+pub struct Snapshot {
+    pub list_id : usize,
+    pub entries : Vec<TodoData>,
+    /// Cursor in the list when the snapshot was taken
+    pub row : i32,
+    pub column : i32,
+    /// Other lists changed by the same step, as `(list id, entries)`
+    pub others : Vec<(usize, Vec<TodoData>)>
+}
+
+impl Snapshot {
+    /// `list` as it is now, with the cursor at `row` and `column`
+    pub fn of(list : &TodoList, row : i32, column : i32) -> Snapshot {
+        Snapshot {
+            list_id : list.id,
+            entries : list.entries.clone(),
+            row,
+            column,
+            others : vec![]
+        }
+    }
+}
+
+/// Where an undone or redone step leaves off: the list it changed and the cursor in it
+pub struct Restored {
+    /// Index of the list
+    pub list : usize,
+    pub row : i32,
+    pub column : i32
+}
+
+/// The steps that can be undone, and the undone ones that can be redone
+#[derive(Default)]
+pub struct History {
+    undo : Vec<Snapshot>,
+    redo : Vec<Snapshot>
+}
+
+impl History {
+    /// Records `snapshot` as an undo step, dropping anything that could be redone
+    pub fn record(&mut self, snapshot : Snapshot) {
+        self.undo.push(snapshot);
+        if self.undo.len() > UNDO_LIMIT {
+            self.undo.remove(0);
+        }
+        self.redo.clear();
+    }
+
+    /// Forgets every step, for when the lists were replaced as a whole
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+
+    /// Number of steps that can be undone
+    pub fn undo_steps(&self) -> usize {
+        self.undo.len()
+    }
+
+    /// Number of steps that can be redone
+    pub fn redo_steps(&self) -> usize {
+        self.redo.len()
+    }
+
+    /// Puts the latest step back into `lists`, `cursor` being the `(row, column)` the
+    /// frontend's cursor is at now. `None` when there's nothing to undo.
+    pub fn undo(&mut self, lists : &mut [TodoList], cursor : (i32, i32)) -> Option<Restored> {
+        step(&mut self.undo, &mut self.redo, lists, cursor)
+    }
+
+    /// Makes the latest undone step again, see `undo`
+    pub fn redo(&mut self, lists : &mut [TodoList], cursor : (i32, i32)) -> Option<Restored> {
+        step(&mut self.redo, &mut self.undo, lists, cursor)
+    }
+}
+
+/// Restores the latest snapshot of `from` and pushes the state it replaced onto `to`. Steps
+/// of deleted lists can't be restored anymore and are skipped.
+fn step(from : &mut Vec<Snapshot>, to : &mut Vec<Snapshot>, lists : &mut [TodoList], cursor : (i32, i32)) -> Option<Restored> {
+    while let Some(snapshot) = from.pop() {
+        let index = match lists::list_index(lists, snapshot.list_id) {
+            Some(index) => index,
+            None => continue
+        };
+
+        let (row, column) = cursor;
+        let mut replaced = Snapshot {
+            list_id : snapshot.list_id,
+            entries : mem::replace(&mut lists[index].entries, snapshot.entries),
+            row,
+            column,
+            others : vec![]
+        };
+        for (list_id, entries) in snapshot.others {
+            if let Some(other) = lists::list_index(lists, list_id) {
+                replaced.others.push((list_id, mem::replace(&mut lists[other].entries, entries)));
+            }
+        }
+        to.push(replaced);
+        return Some(Restored { list : index, row : snapshot.row, column : snapshot.column });
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lists::{ListRole, ListView};
+
+    fn list(id : usize, messages : &[&str]) -> TodoList {
+        let entries = messages.iter().map(|message| TodoData { message : message.to_string(), ..TodoData::default() }).collect();
+        TodoList { id, name : String::new(), role : ListRole::Named, entries, view : ListView::default() }
+    }
+
+    fn messages(list : &TodoList) -> Vec<&str> {
+        list.entries.iter().map(|m| m.message.as_str()).collect()
+    }
+
+    #[test]
+    fn undo_puts_back_every_list_of_the_step_and_redo_makes_it_again() {
+        let mut lists = vec![list(0, &["a", "b"]), list(1, &["c"])];
+        let mut history = History::default();
+        let mut snapshot = Snapshot::of(&lists[0], 1, 0);
+        snapshot.others.push((1, lists[1].entries.clone()));
+        history.record(snapshot);
+        let moved = lists[0].entries.remove(1);
+        lists[1].entries.push(moved);
+
+        let restored = history.undo(&mut lists, (0, 0)).unwrap();
+        assert_eq!((restored.list, restored.row), (0, 1));
+        assert_eq!((messages(&lists[0]), messages(&lists[1])), (vec!["a", "b"], vec!["c"]));
+        assert_eq!((history.undo_steps(), history.redo_steps()), (0, 1));
+
+        history.redo(&mut lists, (1, 0)).unwrap();
+        assert_eq!((messages(&lists[0]), messages(&lists[1])), (vec!["a"], vec!["c", "b"]));
+        assert!(history.redo(&mut lists, (0, 0)).is_none());
+    }
+
+    #[test]
+    fn steps_of_deleted_lists_are_skipped_and_old_ones_dropped() {
+        let mut lists = vec![list(0, &["a"]), list(1, &["b"])];
+        let mut history = History::default();
+        history.record(Snapshot::of(&lists[0], 0, 0));
+        history.record(Snapshot::of(&lists[1], 0, 0));
+        lists.remove(1);
+
+        assert_eq!(history.undo(&mut lists, (0, 0)).unwrap().list, 0);
+        assert!(history.undo(&mut lists, (0, 0)).is_none());
+
+        for _ in 0..UNDO_LIMIT + 5 {
+            history.record(Snapshot::of(&lists[0], 0, 0));
+        }
+        assert_eq!((history.undo_steps(), history.redo_steps()), (UNDO_LIMIT, 0));
+    }
+}